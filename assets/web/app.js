@@ -0,0 +1,299 @@
+// Astra mobile web client, served by the desktop server at /
+"use strict";
+
+const POINTER_SPEED = 1.6;
+const SCROLL_PIXELS_PER_DETENT = 24;
+const TAP_MAX_MS = 200;
+const TAP_MAX_MOVE = 6;
+
+const statusEl = document.getElementById("status");
+
+function token() {
+  return localStorage.getItem("astra-token");
+}
+
+// Round trip of the last input action, reported with the next one so the
+// server can tell network time from its own
+let lastRtt = null;
+
+async function api(method, path, body) {
+  const headers = {
+    "Content-Type": "application/json",
+    "X-Astra-Client-Time": String(Date.now()),
+  };
+  if (token()) {
+    headers["Authorization"] = "Bearer " + token();
+  }
+  if (lastRtt !== null) {
+    headers["X-Astra-Rtt-Ms"] = lastRtt.toFixed(1);
+  }
+  const started = performance.now();
+  const response = await fetch(path, {
+    method,
+    headers,
+    body: body === undefined ? undefined : JSON.stringify(body),
+  });
+  const json = await response.json().catch(() => ({}));
+  if (!response.ok) {
+    throw new Error(json.message || response.statusText);
+  }
+  if (json.timing) {
+    lastRtt = performance.now() - started;
+  }
+  return json;
+}
+
+function post(path, body) {
+  return api("POST", path, body).catch((e) => console.warn(path, e.message));
+}
+
+async function checkConnection() {
+  try {
+    await api("GET", "/ping");
+    statusEl.textContent = token() ? "Connected · paired" : "Connected";
+    statusEl.classList.add("online");
+  } catch (e) {
+    statusEl.textContent = "Offline";
+    statusEl.classList.remove("online");
+  }
+}
+checkConnection();
+setInterval(checkConnection, 5000);
+
+// --- Touchpad ---
+// Movement is accumulated and sent at most once per frame, with one request in flight
+
+const touchpad = document.getElementById("touchpad");
+let pending = { dx: 0, dy: 0 };
+let inFlight = false;
+
+function flushMovement() {
+  if (inFlight || (pending.dx === 0 && pending.dy === 0)) {
+    return;
+  }
+  const move = { dx: Math.round(pending.dx), dy: Math.round(pending.dy) };
+  pending.dx -= move.dx;
+  pending.dy -= move.dy;
+  if (move.dx === 0 && move.dy === 0) {
+    return;
+  }
+  inFlight = true;
+  post("/mouse", move).finally(() => {
+    inFlight = false;
+    requestAnimationFrame(flushMovement);
+  });
+}
+
+const pointers = new Map();
+let gesture = null;
+
+touchpad.addEventListener("pointerdown", (e) => {
+  touchpad.setPointerCapture(e.pointerId);
+  pointers.set(e.pointerId, { x: e.clientX, y: e.clientY });
+  if (pointers.size === 1) {
+    gesture = { start: performance.now(), moved: 0, fingers: 1 };
+  } else if (gesture) {
+    gesture.fingers = Math.max(gesture.fingers, pointers.size);
+  }
+});
+
+touchpad.addEventListener("pointermove", (e) => {
+  const last = pointers.get(e.pointerId);
+  if (!last || pointers.size > 1) {
+    return;
+  }
+  const dx = e.clientX - last.x;
+  const dy = e.clientY - last.y;
+  pointers.set(e.pointerId, { x: e.clientX, y: e.clientY });
+  if (gesture) {
+    gesture.moved += Math.abs(dx) + Math.abs(dy);
+  }
+  pending.dx += dx * POINTER_SPEED;
+  pending.dy += dy * POINTER_SPEED;
+  requestAnimationFrame(flushMovement);
+});
+
+function endPointer(e) {
+  pointers.delete(e.pointerId);
+  if (pointers.size > 0 || !gesture) {
+    return;
+  }
+  const quick = performance.now() - gesture.start < TAP_MAX_MS;
+  if (quick && gesture.moved < TAP_MAX_MOVE) {
+    post("/click", { type: gesture.fingers > 1 ? "right" : "left" });
+  }
+  gesture = null;
+}
+touchpad.addEventListener("pointerup", endPointer);
+touchpad.addEventListener("pointercancel", endPointer);
+
+// --- Scroll strip ---
+// Dragging scrolls in detents; releasing after a fast swipe flings with momentum
+
+const strip = document.getElementById("scrollstrip");
+let scrollDrag = null;
+
+strip.addEventListener("pointerdown", (e) => {
+  strip.setPointerCapture(e.pointerId);
+  scrollDrag = { y: e.clientY, carry: 0, samples: [{ y: e.clientY, t: performance.now() }] };
+  post("/scroll", { direction: "stop" });
+});
+
+strip.addEventListener("pointermove", (e) => {
+  if (!scrollDrag) {
+    return;
+  }
+  scrollDrag.carry += scrollDrag.y - e.clientY;
+  scrollDrag.y = e.clientY;
+  scrollDrag.samples.push({ y: e.clientY, t: performance.now() });
+  scrollDrag.samples = scrollDrag.samples.slice(-5);
+
+  const detents = scrollDrag.carry / SCROLL_PIXELS_PER_DETENT;
+  if (Math.abs(detents) >= 0.25) {
+    scrollDrag.carry = 0;
+    post("/scroll", { direction: detents > 0 ? "up" : "down", amount: Math.abs(detents) });
+  }
+});
+
+function endScroll() {
+  if (!scrollDrag) {
+    return;
+  }
+  const first = scrollDrag.samples[0];
+  const last = scrollDrag.samples[scrollDrag.samples.length - 1];
+  const seconds = (last.t - first.t) / 1000;
+  if (seconds > 0) {
+    const velocity = (first.y - last.y) / seconds / SCROLL_PIXELS_PER_DETENT;
+    if (Math.abs(velocity) > 4) {
+      post("/scroll", { direction: velocity > 0 ? "up" : "down", velocity: Math.abs(velocity) });
+    }
+  }
+  scrollDrag = null;
+}
+strip.addEventListener("pointerup", endScroll);
+strip.addEventListener("pointercancel", endScroll);
+
+// --- Buttons and keyboard ---
+
+document.querySelectorAll("[data-click]").forEach((button) => {
+  button.addEventListener("click", () => post("/click", { type: button.dataset.click }));
+});
+
+document.querySelectorAll("[data-key]").forEach((button) => {
+  button.addEventListener("click", () => sendKey({ key: button.dataset.key }));
+});
+
+// Keystrokes go out one at a time so they arrive in order
+let keyQueue = Promise.resolve();
+function sendKey(body) {
+  keyQueue = keyQueue.then(() => post("/key", body));
+}
+
+function sendChar(ch) {
+  if (ch === " ") {
+    sendKey({ key: "space" });
+  } else if (/[A-Z]/.test(ch)) {
+    // Single letters are key names on the server, which are case-insensitive
+    sendKey({ key: ch.toLowerCase(), modifiers: ["shift"] });
+  } else {
+    sendKey({ key: ch });
+  }
+}
+
+// Each typed character is sent as it arrives; the field stays empty so
+// mobile keyboards keep working with autocorrect and IME composition
+const textInput = document.getElementById("text");
+textInput.addEventListener("input", (e) => {
+  if (e.inputType === "deleteContentBackward") {
+    sendKey({ key: "backspace" });
+  } else if (e.data) {
+    for (const ch of e.data) {
+      sendChar(ch);
+    }
+  }
+  textInput.value = "";
+});
+textInput.addEventListener("keydown", (e) => {
+  if (e.key === "Enter") {
+    sendKey({ key: "enter" });
+  }
+});
+
+// --- Media and volume ---
+
+document.querySelectorAll("[data-media]").forEach((button) => {
+  button.addEventListener("click", () => post("/media", { action: button.dataset.media }));
+});
+
+document.querySelectorAll("[data-volume]").forEach((button) => {
+  button.addEventListener("click", async () => {
+    const value = button.dataset.volume;
+    if (value === "mute") {
+      const current = await api("GET", "/volume").catch(() => ({ muted: false }));
+      post("/volume", { muted: !current.muted });
+    } else {
+      post("/volume", { delta: Number(value) });
+    }
+  });
+});
+
+// --- Voice ---
+// Uses the browser's speech recognition where available (most browsers only
+// allow it on HTTPS pages); the text field works everywhere
+
+const voiceResult = document.getElementById("voice-result");
+
+async function sendVoice(command) {
+  voiceResult.textContent = "“" + command + "”…";
+  try {
+    const result = await api("POST", "/voice", { command });
+    voiceResult.textContent = result.message || "Done";
+  } catch (e) {
+    voiceResult.textContent = e.message;
+  }
+}
+
+document.getElementById("voice-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  const input = document.getElementById("voice-text");
+  if (input.value.trim()) {
+    sendVoice(input.value.trim());
+    input.value = "";
+  }
+});
+
+const mic = document.getElementById("mic");
+const Recognition = window.SpeechRecognition || window.webkitSpeechRecognition;
+if (!Recognition) {
+  mic.disabled = true;
+  mic.textContent = "🎤 Speech input not available";
+} else {
+  const recognition = new Recognition();
+  recognition.lang = navigator.language || "en-US";
+  recognition.interimResults = false;
+  recognition.onresult = (e) => sendVoice(e.results[0][0].transcript);
+  recognition.onerror = (e) => (voiceResult.textContent = "Speech error: " + e.error);
+  recognition.onend = () => mic.classList.remove("listening");
+
+  mic.addEventListener("pointerdown", () => {
+    mic.classList.add("listening");
+    recognition.start();
+  });
+  mic.addEventListener("pointerup", () => recognition.stop());
+}
+
+// --- Pairing ---
+
+document.getElementById("pair-form").addEventListener("submit", async (e) => {
+  e.preventDefault();
+  const result = document.getElementById("pair-result");
+  const code = document.getElementById("pair-code").value.trim();
+  try {
+    const response = await api("POST", "/pair", { code, name: "Web client (" + navigator.platform + ")" });
+    localStorage.setItem("astra-token", response.token);
+    result.textContent = response.message || "Paired";
+    checkConnection();
+  } catch (err) {
+    result.textContent = err.message;
+  }
+});
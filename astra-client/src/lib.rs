@@ -0,0 +1,410 @@
+// Async client for the Astra remote control server
+//
+// One method per HTTP endpoint; the request/response types live in `types` and
+// mirror the server's /openapi.json. Endpoints that need a paired device use the
+// token set with `with_token` (see `pair`).
+pub mod types;
+
+use reqwest::{Method, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncReadExt;
+use types::*;
+
+pub const DEFAULT_PORT: u16 = 44828;
+// Per request, body included; long enough for a 4 MiB upload chunk on slow Wi-Fi
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub enum Error {
+    // Connection, timeout or body decoding failure
+    Http(reqwest::Error),
+    // The server answered with an error status; `code` is the machine-readable
+    // error code, e.g. "unknown_key" or "rate_limited"
+    Api {
+        status: u16,
+        code: Option<String>,
+        message: String,
+        // When to retry a "rate_limited" request
+        retry_after: Option<Duration>,
+        // Structured context, e.g. the per-step results of a "batch_failed" batch
+        details: Option<serde_json::Value>,
+    },
+    Io(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Http(e) => write!(f, "request failed: {}", e),
+            Error::Api {
+                status,
+                code: Some(code),
+                message,
+                ..
+            } => write!(f, "server returned {} ({}): {}", status, code, message),
+            Error::Api { status, message, .. } => write!(f, "server returned {}: {}", status, message),
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+}
+
+impl Client {
+    // `base_url` like "http://192.168.1.20:44828"
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            http: Self::http_client(DEFAULT_TIMEOUT),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            token: None,
+        }
+    }
+
+    fn http_client(timeout: Duration) -> reqwest::Client {
+        reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new())
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.http = Self::http_client(timeout);
+        self
+    }
+
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    // Every request carries the send time, which input responses echo in `timing`
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let sent = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let request = self
+            .http
+            .request(method, format!("{}{}", self.base_url, path))
+            .header("X-Astra-Client-Time", format!("{:.3}", sent.as_secs_f64() * 1000.0));
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    // Error bodies are {"status": "error", "code": ..., "message": ...}; fall back to the raw text
+    async fn api_error(response: reqwest::Response) -> Error {
+        let status = response.status().as_u16();
+        let body = response.text().await.unwrap_or_default();
+        let json = serde_json::from_str::<serde_json::Value>(&body).unwrap_or_default();
+        Error::Api {
+            status,
+            code: json["code"].as_str().map(str::to_string),
+            message: json["message"].as_str().map(str::to_string).unwrap_or(body),
+            retry_after: json["details"]["retry_after_ms"].as_u64().map(Duration::from_millis),
+            details: json.get("details").filter(|details| !details.is_null()).cloned(),
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(request: RequestBuilder) -> Result<T> {
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(Self::api_error(response).await);
+        }
+        Ok(response.json().await?)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        Self::send(self.request(Method::GET, path)).await
+    }
+
+    async fn post<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> Result<T> {
+        Self::send(self.request(Method::POST, path).json(body)).await
+    }
+
+    // POST without a body
+    async fn post_empty<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        Self::send(self.request(Method::POST, path)).await
+    }
+
+    // --- Input ---
+
+    pub async fn ping(&self) -> Result<ApiResponse> {
+        self.get("/ping").await
+    }
+
+    pub async fn mouse(&self, dx: f32, dy: f32) -> Result<ApiResponse> {
+        self.post("/mouse", &MouseMove { dx, dy }).await
+    }
+
+    pub async fn click(&self, click_type: &str) -> Result<ApiResponse> {
+        let request = ClickRequest {
+            click_type: click_type.to_string(),
+        };
+        self.post("/click", &request).await
+    }
+
+    pub async fn scroll(&self, request: &ScrollRequest) -> Result<ApiResponse> {
+        self.post("/scroll", request).await
+    }
+
+    pub async fn key(&self, key: &str, modifiers: &[&str]) -> Result<ApiResponse> {
+        let request = KeyRequest {
+            key: key.to_string(),
+            modifiers: (!modifiers.is_empty()).then(|| modifiers.iter().map(|m| m.to_string()).collect()),
+        };
+        self.post("/key", &request).await
+    }
+
+    // A batch where any step failed or did not run is an `Error::Api` with code
+    // "batch_failed" ("input_paused" if input was paused midway); `details.results`
+    // lists the steps that ran
+    pub async fn batch(&self, request: &BatchRequest) -> Result<BatchResponse> {
+        self.post("/batch", request).await
+    }
+
+    pub async fn input(&self) -> Result<InputResponse> {
+        self.get("/input").await
+    }
+
+    // Releases every key and button the server still holds down
+    pub async fn reset_input(&self) -> Result<InputResponse> {
+        self.post_empty("/reset-input").await
+    }
+
+    pub async fn voice(&self, command: &str) -> Result<ApiResponse> {
+        let request = VoiceRequest {
+            command: command.to_string(),
+        };
+        self.post("/voice", &request).await
+    }
+
+    // --- Desktop ---
+
+    pub async fn media(&self) -> Result<MediaResponse> {
+        self.get("/media").await
+    }
+
+    pub async fn media_control(&self, request: &MediaRequest) -> Result<MediaResponse> {
+        self.post("/media", request).await
+    }
+
+    pub async fn volume(&self) -> Result<VolumeResponse> {
+        self.get("/volume").await
+    }
+
+    pub async fn set_volume(&self, request: &VolumeRequest) -> Result<VolumeResponse> {
+        self.post("/volume", request).await
+    }
+
+    pub async fn windows(&self) -> Result<WindowsResponse> {
+        self.get("/windows").await
+    }
+
+    pub async fn window_action(&self, request: &WindowRequest) -> Result<WindowsResponse> {
+        self.post("/windows", request).await
+    }
+
+    pub async fn presentation(&self) -> Result<PresentationResponse> {
+        self.get("/presentation").await
+    }
+
+    pub async fn presentation_action(&self, request: &PresentationRequest) -> Result<PresentationResponse> {
+        self.post("/presentation", request).await
+    }
+
+    // One encoded frame and its content type (image/jpeg or image/png)
+    pub async fn screen(&self, query: &ScreenQuery) -> Result<(Vec<u8>, String)> {
+        let response = self.request(Method::GET, "/screen").query(query).send().await?;
+        if !response.status().is_success() {
+            return Err(Self::api_error(response).await);
+        }
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        Ok((response.bytes().await?.to_vec(), content_type))
+    }
+
+    // The multipart/x-mixed-replace response for `stream=true`, to read with
+    // `chunk()`; the client timeout does not apply to it
+    pub async fn screen_stream(&self, query: &ScreenQuery) -> Result<reqwest::Response> {
+        let query = ScreenQuery {
+            stream: Some(true),
+            ..query.clone()
+        };
+        let request = self
+            .request(Method::GET, "/screen")
+            .query(&query)
+            .timeout(Duration::from_secs(u32::MAX as u64));
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(Self::api_error(response).await);
+        }
+        Ok(response)
+    }
+
+    // --- Pairing and paired-device features ---
+
+    // Pairs with the code shown on the desktop; use the returned token with `with_token`
+    pub async fn pair(&self, code: &str, name: Option<&str>) -> Result<PairResponse> {
+        let request = PairRequest {
+            code: code.to_string(),
+            name: name.map(str::to_string),
+        };
+        self.post("/pair", &request).await
+    }
+
+    pub async fn udp_handshake(&self) -> Result<HandshakeResponse> {
+        self.post_empty("/udp/handshake").await
+    }
+
+    pub async fn clipboard(&self, selection: Selection) -> Result<ClipboardResponse> {
+        let request = self.request(Method::GET, "/clipboard").query(&[("selection", selection)]);
+        Self::send(request).await
+    }
+
+    pub async fn set_clipboard(&self, request: &ClipboardPut) -> Result<ClipboardResponse> {
+        Self::send(self.request(Method::PUT, "/clipboard").json(request)).await
+    }
+
+    // WebSocket URL for the /events stream, with the token in the query
+    pub fn events_url(&self) -> String {
+        let ws = self.base_url.replacen("http", "ws", 1);
+        match &self.token {
+            Some(token) => format!("{}/events?token={}", ws, token),
+            None => format!("{}/events", ws),
+        }
+    }
+
+    pub async fn upload_start(&self, request: &UploadInit) -> Result<UploadResponse> {
+        self.post("/upload", request).await
+    }
+
+    pub async fn upload_status(&self, upload_id: &str) -> Result<UploadResponse> {
+        self.get(&format!("/upload/{}", upload_id)).await
+    }
+
+    pub async fn upload_chunk(&self, upload_id: &str, offset: u64, chunk: Vec<u8>) -> Result<UploadResponse> {
+        let request = self
+            .request(Method::PUT, &format!("/upload/{}", upload_id))
+            .query(&[("offset", offset)])
+            .body(chunk);
+        Self::send(request).await
+    }
+
+    // Uploads a whole file in chunks, with a checksum the server verifies at the end
+    pub async fn upload_file(&self, path: &Path) -> Result<UploadResponse> {
+        let filename = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "upload".to_string());
+        // First pass for the checksum, so the file never has to fit in memory
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; 1024 * 1024];
+        loop {
+            let read = file.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        let init = UploadInit {
+            filename,
+            size: file.metadata().await?.len(),
+            sha256: Some(format!("{:x}", hasher.finalize())),
+        };
+        let started = self.upload_start(&init).await?;
+        let upload_id = started.upload_id.clone().unwrap_or_default();
+        let chunk_size = started.chunk_size.unwrap_or(4 * 1024 * 1024);
+
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut offset = 0u64;
+        let mut last = started;
+        let mut buffer = vec![0u8; chunk_size];
+        while offset < init.size {
+            let read = file.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            last = self.upload_chunk(&upload_id, offset, buffer[..read].to_vec()).await?;
+            offset += read as u64;
+        }
+        Ok(last)
+    }
+
+    // --- Administration: only from the server's own machine ---
+
+    // Stops all remote input until `resume`; the same as the desktop hotkey
+    pub async fn pause(&self) -> Result<ApiResponse> {
+        self.post_empty("/pause").await
+    }
+
+    pub async fn resume(&self) -> Result<ApiResponse> {
+        self.post_empty("/resume").await
+    }
+
+    pub async fn shutdown(&self) -> Result<ApiResponse> {
+        self.post_empty("/shutdown").await
+    }
+
+    pub async fn devices(&self) -> Result<DevicesResponse> {
+        self.get("/devices").await
+    }
+
+    pub async fn update_device(&self, id: &str, update: &DeviceUpdate) -> Result<DevicesResponse> {
+        Self::send(self.request(Method::PUT, &format!("/devices/{}", id)).json(update)).await
+    }
+
+    pub async fn delete_device(&self, id: &str) -> Result<DevicesResponse> {
+        Self::send(self.request(Method::DELETE, &format!("/devices/{}", id))).await
+    }
+
+    pub async fn latency(&self) -> Result<LatencyResponse> {
+        self.get("/latency").await
+    }
+
+    // Prometheus text format; public only with ASTRA_METRICS=on
+    pub async fn metrics(&self) -> Result<String> {
+        let response = self.request(Method::GET, "/metrics").send().await?;
+        if !response.status().is_success() {
+            return Err(Self::api_error(response).await);
+        }
+        Ok(response.text().await?)
+    }
+
+    pub async fn openapi(&self) -> Result<serde_json::Value> {
+        self.get("/openapi.json").await
+    }
+}
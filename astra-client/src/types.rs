@@ -0,0 +1,460 @@
+// Request and response bodies, matching the schemas in the server's /openapi.json
+//
+// With the `schema` feature every type derives JsonSchema; the server's tests
+// compare these schemas with its own, so a field added on one side only fails
+// the build there.
+use serde::{Deserialize, Serialize};
+
+// Generic {status, message} reply used by the input endpoints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ApiResponse {
+    pub status: String,
+    pub message: Option<String>,
+    // Only on input actions
+    #[serde(default)]
+    pub timing: Option<Timing>,
+}
+
+// Server-side timing of an input action; times in milliseconds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Timing {
+    // Since the Unix epoch, by the server's clock
+    pub received_at_ms: u64,
+    pub server_ms: f64,
+    pub injection_ms: f64,
+    // The X-Astra-Client-Time header of the request, echoed
+    pub client_time: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MouseMove {
+    pub dx: f32,
+    pub dy: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ClickRequest {
+    // left, right or double
+    #[serde(rename = "type")]
+    pub click_type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ScrollRequest {
+    // up, down, left, right or stop
+    pub direction: String,
+    pub amount: Option<f32>,
+    pub velocity: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct KeyRequest {
+    pub key: String,
+    pub modifiers: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct VoiceRequest {
+    pub command: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum BatchStep {
+    Mouse(MouseMove),
+    Click(ClickRequest),
+    Scroll(ScrollRequest),
+    Key(KeyRequest),
+    Delay { ms: u64 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BatchRequest {
+    pub steps: Vec<BatchStep>,
+    #[serde(default)]
+    pub stop_on_error: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StepResult {
+    pub index: usize,
+    pub action: String,
+    pub status: String,
+    pub code: Option<String>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BatchResponse {
+    pub status: String,
+    pub message: Option<String>,
+    #[serde(default)]
+    pub results: Vec<StepResult>,
+    #[serde(default)]
+    pub timing: Option<Timing>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct InputResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub backend: String,
+    // Keys and buttons the server still holds down
+    #[serde(default)]
+    pub held: Vec<String>,
+    // Only with the recording backend: latest events first
+    pub recorded: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PairRequest {
+    pub code: String,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PairResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub device_id: Option<String>,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HandshakeResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub port: Option<u16>,
+    pub session_id: Option<u32>,
+    pub key: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Selection {
+    #[default]
+    Clipboard,
+    Primary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ClipboardContent {
+    Text { text: String },
+    Image { png_base64: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ClipboardPut {
+    #[serde(flatten)]
+    pub content: ClipboardContent,
+    pub selection: Selection,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ClipboardResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub kind: Option<String>,
+    pub text: Option<String>,
+    pub png_base64: Option<String>,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct UploadInit {
+    pub filename: String,
+    pub size: u64,
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct UploadResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub upload_id: Option<String>,
+    pub offset: Option<u64>,
+    pub size: Option<u64>,
+    pub chunk_size: Option<usize>,
+    pub saved_as: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum MediaAction {
+    Play,
+    Pause,
+    PlayPause,
+    Stop,
+    Next,
+    Previous,
+    Seek,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MediaRequest {
+    pub action: MediaAction,
+    pub offset_seconds: Option<f64>,
+    pub player: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NowPlaying {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub length_seconds: Option<f64>,
+    pub position_seconds: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PlayerInfo {
+    pub id: String,
+    pub identity: Option<String>,
+    pub status: String,
+    pub now_playing: NowPlaying,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MediaResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub active: Option<PlayerInfo>,
+    #[serde(default)]
+    pub players: Vec<PlayerInfo>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct VolumeRequest {
+    pub volume: Option<u32>,
+    pub delta: Option<i32>,
+    pub muted: Option<bool>,
+    pub sink: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Sink {
+    pub name: String,
+    pub description: Option<String>,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct VolumeResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub volume: Option<u32>,
+    pub muted: Option<bool>,
+    pub sink: Option<String>,
+    #[serde(default)]
+    pub sinks: Vec<Sink>,
+}
+
+// Query for GET /screen; unset fields use the server defaults
+#[derive(Debug, Clone, Default, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ScreenQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monitor: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fps: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<bool>,
+    // multipart/x-mixed-replace until the connection closes; `Client::screen`
+    // reads a single frame, so use `Client::screen_stream` for this
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WindowInfo {
+    pub id: String,
+    pub title: String,
+    pub app_id: Option<String>,
+    pub workspace: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub focused: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum WindowAction {
+    Focus,
+    Minimize,
+    Maximize,
+    Close,
+    MoveToWorkspace,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WindowRequest {
+    pub id: String,
+    pub action: WindowAction,
+    pub workspace: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WindowsResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub backend: Option<String>,
+    #[serde(default)]
+    pub windows: Vec<WindowInfo>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum PresentationAction {
+    Next,
+    Previous,
+    Start,
+    End,
+    Blank,
+    Laser,
+    LaserOff,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PresentationRequest {
+    pub action: PresentationAction,
+    pub dx: f32,
+    pub dy: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PresentationResponse {
+    pub status: String,
+    pub message: Option<String>,
+    // impress, evince, okular, browser or generic
+    pub app: Option<String>,
+    pub slide: Option<u32>,
+    pub elapsed_seconds: Option<f64>,
+    pub blanked: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Pointer,
+    Keyboard,
+    Text,
+    Voice,
+    Launch,
+    Shell,
+    Clipboard,
+    Files,
+    Screen,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DeviceInfo {
+    pub id: String,
+    pub name: String,
+    pub paired_at: String,
+    pub scopes: Vec<Scope>,
+}
+
+// Unset fields stay as they are
+#[derive(Debug, Clone, Default, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DeviceUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<Scope>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DevicesResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub pairing_required: bool,
+    #[serde(default)]
+    pub devices: Vec<DeviceInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Percentiles {
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ClientLatency {
+    // Device name, or the IP address of an unpaired client
+    pub client: String,
+    pub samples: usize,
+    pub server_ms: Option<Percentiles>,
+    pub injection_ms: Option<Percentiles>,
+    pub rtt_ms: Option<Percentiles>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LatencyResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub window_seconds: u64,
+    #[serde(default)]
+    pub clients: Vec<ClientLatency>,
+}
@@ -25,10 +25,12 @@ mkdir -p "$ICON_DIR"
 echo "📋 Installing binaries..."
 cp target/release/astra-remote "$INSTALL_DIR/"
 cp target/release/astra-gui "$INSTALL_DIR/"
+cp target/release/astra-ctl "$INSTALL_DIR/"
 
 # Create symlinks in bin directory
 ln -sf "$INSTALL_DIR/astra-remote" "$BIN_DIR/astra-remote"
 ln -sf "$INSTALL_DIR/astra-gui" "$BIN_DIR/astra-gui"
+ln -sf "$INSTALL_DIR/astra-ctl" "$BIN_DIR/astra-ctl"
 
 # Copy icon
 echo "🎨 Installing icon..."
@@ -67,6 +69,7 @@ echo "🗑️  Uninstalling Astra Gesture Control..."
 # Remove binaries
 rm -f "$HOME/.local/bin/astra-remote"
 rm -f "$HOME/.local/bin/astra-gui"
+rm -f "$HOME/.local/bin/astra-ctl"
 
 # Remove installation directory
 rm -rf "$HOME/.local/share/astra-gesture-control"
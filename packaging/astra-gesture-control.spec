@@ -13,6 +13,8 @@ BuildRequires:  rust
 Requires:       glibc
 Requires:       libgcc
 Requires:       libstdc++
+# pactl, for /volume
+Requires:       pulseaudio-utils
 
 %description
 Astra Gesture Control allows you to control your desktop computer
@@ -0,0 +1,350 @@
+// Audit log: one JSON line per remote action, in ~/.config/astra/audit
+//
+// Each line records when, which client (paired device and IP), the route, the
+// parsed action and the result. Typed text, clipboard text and voice dictation are
+// redacted unless ASTRA_AUDIT_TEXT=full; pairing codes and file contents are never
+// written. Pointer movement is left out, it would drown everything else. audit.jsonl
+// is rotated once it reaches ASTRA_AUDIT_MAX_BYTES, and rotated files older than
+// ASTRA_AUDIT_RETENTION_DAYS are deleted. ASTRA_AUDIT=off disables the log.
+use crate::error::ErrorCode;
+use axum::{
+    body::{Body, Bytes},
+    extract::{ConnectInfo, Request},
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+pub const FILE_NAME: &str = "audit.jsonl";
+const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+const DEFAULT_RETENTION_DAYS: u64 = 30;
+// Request bodies larger than this are not parsed
+const MAX_PARSED_BODY: usize = 64 * 1024;
+// Batch summaries list this many steps, then "..."
+const MAX_SUMMARY_STEPS: usize = 20;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub timestamp: String,
+    pub device_id: Option<String>,
+    pub device_name: Option<String>,
+    pub ip: String,
+    pub method: String,
+    pub route: String,
+    pub action: String,
+    pub status: u16,
+    // "ok", or the error code of the response
+    pub result: String,
+}
+
+pub fn dir() -> PathBuf {
+    crate::config::config_dir().join("audit")
+}
+
+struct AuditLog {
+    redact_text: bool,
+    // Entries are written on their own thread, so a slow disk never holds up a request
+    writer: Mutex<Sender<Entry>>,
+}
+
+// Owned by the writer thread
+struct LogFile {
+    file: Option<File>,
+    size: u64,
+    max_bytes: u64,
+    retention: Duration,
+}
+
+lazy_static::lazy_static! {
+    static ref AUDIT: Option<AuditLog> = AuditLog::from_env();
+}
+
+impl AuditLog {
+    fn from_env() -> Option<Self> {
+        if !crate::config::env_flag("ASTRA_AUDIT", true) {
+            return None;
+        }
+        let redact_text = match std::env::var("ASTRA_AUDIT_TEXT").as_deref() {
+            Ok("full") => false,
+            Ok("redact") | Err(_) => true,
+            Ok(other) => {
+                warn!("📜 Unknown ASTRA_AUDIT_TEXT={:?}, redacting typed text", other);
+                true
+            }
+        };
+        let days = crate::config::env_parse("ASTRA_AUDIT_RETENTION_DAYS", DEFAULT_RETENTION_DAYS);
+        let mut log = LogFile {
+            file: None,
+            size: 0,
+            max_bytes: crate::config::env_parse("ASTRA_AUDIT_MAX_BYTES", DEFAULT_MAX_BYTES).max(1024),
+            retention: Duration::from_secs(days.saturating_mul(24 * 60 * 60)),
+        };
+        let (writer, entries) = mpsc::channel::<Entry>();
+        std::thread::Builder::new()
+            .name("audit".to_string())
+            .spawn(move || {
+                log.prune();
+                for entry in entries {
+                    if let Err(e) = log.write(&entry) {
+                        warn!("📜 Failed to write audit log: {}", e);
+                    }
+                }
+            })
+            .map_err(|e| warn!("📜 Audit log disabled, no writer thread: {}", e))
+            .ok()?;
+        Some(Self {
+            redact_text,
+            writer: Mutex::new(writer),
+        })
+    }
+}
+
+impl LogFile {
+    fn open(&mut self) -> Result<&mut File, String> {
+        if self.file.is_none() {
+            std::fs::create_dir_all(dir()).map_err(|e| e.to_string())?;
+            // May hold typed text (ASTRA_AUDIT_TEXT=full), so only the owner can read it
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .mode(0o600)
+                .open(dir().join(FILE_NAME))
+                .map_err(|e| e.to_string())?;
+            self.size = file.metadata().map(|m| m.len()).unwrap_or(0);
+            self.file = Some(file);
+        }
+        Ok(self.file.as_mut().expect("opened above"))
+    }
+
+    // audit.jsonl becomes audit-<time>.jsonl and a new file is started
+    fn rotate(&mut self) -> Result<(), String> {
+        self.file = None;
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+        let rotated = dir().join(format!("audit-{}.jsonl", stamp));
+        std::fs::rename(dir().join(FILE_NAME), &rotated).map_err(|e| e.to_string())?;
+        info!("📜 Audit log rotated to {}", rotated.display());
+        self.prune();
+        Ok(())
+    }
+
+    fn prune(&self) {
+        let Ok(entries) = std::fs::read_dir(dir()) else {
+            return;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name == FILE_NAME || !name.starts_with("audit-") || !name.ends_with(".jsonl") {
+                continue;
+            }
+            let age = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+            if age.is_some_and(|age| age > self.retention) && std::fs::remove_file(entry.path()).is_ok() {
+                info!("📜 Deleted expired audit log {}", name);
+            }
+        }
+    }
+
+    fn write(&mut self, entry: &Entry) -> Result<(), String> {
+        let mut line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+        line.push('\n');
+        self.open()?;
+        if self.size > 0 && self.size + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.open()?.write_all(line.as_bytes()).map_err(|e| e.to_string())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+// Reports where actions are recorded and starts the writer, which deletes expired
+// rotated logs; later expired logs are deleted on rotation
+pub fn start() {
+    let Some(log) = AUDIT.as_ref() else {
+        info!("📜 Audit log disabled");
+        return;
+    };
+    let text = if log.redact_text { "redacted" } else { "recorded in full" };
+    info!("📜 Audit log: {} (typed text {})", dir().join(FILE_NAME).display(), text);
+}
+
+fn redact(text: &str) -> String {
+    format!("[redacted {} chars]", text.chars().count())
+}
+
+fn text_of<'a>(body: &'a Value, key: &str) -> &'a str {
+    body[key].as_str().unwrap_or("")
+}
+
+fn describe_key(body: &Value, redact_text: bool) -> String {
+    let key = text_of(body, "key");
+    let modifiers: Vec<&str> = body["modifiers"]
+        .as_array()
+        .map(|m| m.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    if redact_text && crate::scopes::is_typing(key, &modifiers) {
+        return "key [redacted]".to_string();
+    }
+    let mut combo = modifiers;
+    combo.push(key);
+    format!("key {}", combo.join("+"))
+}
+
+fn describe_step(step: &Value, redact_text: bool) -> String {
+    match text_of(step, "action") {
+        "mouse" => "move".to_string(),
+        "click" => format!("click {}", text_of(step, "type")),
+        "scroll" => format!("scroll {}", text_of(step, "direction")),
+        "key" => describe_key(step, redact_text),
+        "delay" => format!("delay {}ms", step["ms"]),
+        other => other.to_string(),
+    }
+}
+
+// Consecutive redacted keys are counted rather than listed
+fn describe_batch(body: &Value, redact_text: bool) -> String {
+    let steps = body["steps"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let mut parts: Vec<String> = Vec::new();
+    let mut typed = 0;
+    for step in steps {
+        let described = describe_step(step, redact_text);
+        if described == "key [redacted]" {
+            typed += 1;
+            continue;
+        }
+        if typed > 0 {
+            parts.push(format!("[redacted {} keys]", typed));
+            typed = 0;
+        }
+        parts.push(described);
+    }
+    if typed > 0 {
+        parts.push(format!("[redacted {} keys]", typed));
+    }
+    if parts.len() > MAX_SUMMARY_STEPS {
+        parts.truncate(MAX_SUMMARY_STEPS);
+        parts.push("...".to_string());
+    }
+    format!("batch of {} steps: {}", steps.len(), parts.join(", "))
+}
+
+fn describe_voice(body: &Value, redact_text: bool) -> String {
+    let command = text_of(body, "command").trim();
+    match command.get(..5) {
+        Some(verb) if redact_text && verb.eq_ignore_ascii_case("type ") => {
+            format!("voice type {}", redact(command[5..].trim()))
+        }
+        _ => format!("voice {}", command),
+    }
+}
+
+fn describe_clipboard(body: &Value, redact_text: bool) -> String {
+    match text_of(body, "kind") {
+        "text" if redact_text => format!("clipboard set text {}", redact(text_of(body, "text"))),
+        "text" => format!("clipboard set text {:?}", text_of(body, "text")),
+        "" => "clipboard set".to_string(),
+        kind => format!("clipboard set {}", kind),
+    }
+}
+
+// What a request does, or None when it is not audited
+fn describe(method: &Method, path: &str, body: &Value, redact_text: bool) -> Option<String> {
+    let action = match (method.as_str(), path) {
+        // Pointer movement and upload chunks
+        ("POST", "/mouse") => return None,
+        ("PUT", _) if path.starts_with("/upload/") => return None,
+        ("POST", "/presentation") if matches!(text_of(body, "action"), "laser") => return None,
+        ("GET", "/screen") => "screen view".to_string(),
+        ("GET", _) | ("HEAD", _) | ("OPTIONS", _) => return None,
+        ("POST", "/click") => format!("click {}", text_of(body, "type")),
+        ("POST", "/scroll") => format!("scroll {} {}", text_of(body, "direction"), body["amount"]),
+        ("POST", "/key") => describe_key(body, redact_text),
+        ("POST", "/batch") => describe_batch(body, redact_text),
+        ("POST", "/voice") => describe_voice(body, redact_text),
+        ("PUT", "/clipboard") => describe_clipboard(body, redact_text),
+        // Never the code
+        ("POST", "/pair") => format!("pair as {:?}", text_of(body, "name")),
+        ("POST", "/upload") => format!("upload {:?} ({} bytes)", text_of(body, "filename"), body["size"]),
+        (_, path) if body.is_object() => format!("{} {}", path.trim_start_matches('/'), body),
+        (_, path) => path.trim_start_matches('/').to_string(),
+    };
+    Some(action)
+}
+
+async fn read_body(body: Body) -> Result<Bytes, Response> {
+    axum::body::to_bytes(body, MAX_PARSED_BODY)
+        .await
+        .map_err(|e| crate::error::ApiError::InvalidRequest(format!("Failed to read body: {}", e)).into_response())
+}
+
+// Records every audited request with its result. Runs outside the rate limiter, so
+// refused requests are recorded too.
+pub async fn middleware(ConnectInfo(addr): ConnectInfo<SocketAddr>, request: Request, next: Next) -> Response {
+    let Some(log) = AUDIT.as_ref() else {
+        return next.run(request).await;
+    };
+    let redact_text = log.redact_text;
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    // Large bodies (clipboard images) are described without their content
+    let parse_body = {
+        let header = |name| request.headers().get(name).and_then(|v| v.to_str().ok());
+        header(axum::http::header::CONTENT_TYPE).is_some_and(|v| v.starts_with("application/json"))
+            && header(axum::http::header::CONTENT_LENGTH)
+                .and_then(|v| v.parse::<usize>().ok())
+                .is_some_and(|length| length <= MAX_PARSED_BODY)
+    };
+    let device = crate::pairing::Caller::of(&request).cloned();
+
+    // JSON bodies are buffered to describe the action, then handed on unchanged
+    let (request, body) = if parse_body && describe(&method, &path, &Value::Null, redact_text).is_some() {
+        let (parts, body) = request.into_parts();
+        let bytes = match read_body(body).await {
+            Ok(bytes) => bytes,
+            Err(response) => return response,
+        };
+        let value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+        (Request::from_parts(parts, Body::from(bytes)), value)
+    } else {
+        (request, Value::Null)
+    };
+    let Some(action) = describe(&method, &path, &body, redact_text) else {
+        return next.run(request).await;
+    };
+
+    let response = next.run(request).await;
+    let status = response.status();
+    let result = match response.extensions().get::<ErrorCode>() {
+        Some(ErrorCode(code)) => code.to_string(),
+        None if status.is_success() => "ok".to_string(),
+        None => status.as_u16().to_string(),
+    };
+
+    let entry = Entry {
+        timestamp: chrono::Local::now().to_rfc3339(),
+        device_id: device.as_ref().map(|d| d.id.clone()),
+        device_name: device.map(|d| d.name),
+        ip: addr.ip().to_string(),
+        method: method.to_string(),
+        route: path,
+        action,
+        status: status.as_u16(),
+        result,
+    };
+    let _ = log.writer.lock().unwrap().send(entry);
+    response
+}
@@ -0,0 +1,207 @@
+// /batch: run an ordered list of input actions under the input lock, which is only
+// given up during delays
+use crate::error::ApiError;
+use crate::ratelimit::RouteClass;
+use crate::{ClickRequest, KeyRequest, MouseMove, ScrollRequest};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+// Keep a single batch from holding the input lock for too long
+const MAX_STEPS: usize = 256;
+const MAX_DELAY_MS: u64 = 5_000;
+const MAX_TOTAL_DELAY_MS: u64 = 30_000;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum BatchStep {
+    Mouse(MouseMove),
+    Click(ClickRequest),
+    Scroll(ScrollRequest),
+    Key(KeyRequest),
+    Delay { ms: u64 },
+}
+
+impl BatchStep {
+    fn name(&self) -> &'static str {
+        match self {
+            BatchStep::Mouse(_) => "mouse",
+            BatchStep::Click(_) => "click",
+            BatchStep::Scroll(_) => "scroll",
+            BatchStep::Key(_) => "key",
+            BatchStep::Delay { .. } => "delay",
+        }
+    }
+
+    pub fn scope(&self) -> Option<crate::scopes::Scope> {
+        use crate::scopes::Scope;
+        match self {
+            BatchStep::Mouse(_) | BatchStep::Click(_) | BatchStep::Scroll(_) => Some(Scope::Pointer),
+            BatchStep::Key(k) if crate::scopes::is_typing(&k.key, k.modifiers.as_deref().unwrap_or_default()) => {
+                Some(Scope::Text)
+            }
+            BatchStep::Key(_) => Some(Scope::Keyboard),
+            BatchStep::Delay { .. } => None,
+        }
+    }
+
+    // The limit the step counts against, as if it were sent on its own route
+    fn rate_class(&self) -> Option<RouteClass> {
+        match self {
+            BatchStep::Mouse(_) | BatchStep::Click(_) | BatchStep::Scroll(_) => Some(RouteClass::Pointer),
+            BatchStep::Key(_) => Some(RouteClass::Keys),
+            BatchStep::Delay { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BatchRequest {
+    pub steps: Vec<BatchStep>,
+    #[serde(default)]
+    pub stop_on_error: bool,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct StepResult {
+    pub index: usize,
+    pub action: &'static str,
+    pub status: String,
+    // Error code, as in error responses
+    pub code: Option<&'static str>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct BatchResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub results: Vec<StepResult>,
+    // Injection covers the steps, not the delays between them
+    pub timing: Option<crate::latency::Timing>,
+}
+
+// Checks limits before anything is executed, so a rejected batch has no side effects
+pub fn validate(request: &BatchRequest) -> Result<(), String> {
+    if request.steps.is_empty() {
+        return Err("Batch has no steps".to_string());
+    }
+    if request.steps.len() > MAX_STEPS {
+        return Err(format!("Batch has {} steps, the limit is {}", request.steps.len(), MAX_STEPS));
+    }
+    let mut total_delay = 0;
+    for (index, step) in request.steps.iter().enumerate() {
+        if let BatchStep::Delay { ms } = step {
+            if *ms > MAX_DELAY_MS {
+                return Err(format!("Step {}: delay of {}ms exceeds {}ms", index, ms, MAX_DELAY_MS));
+            }
+            total_delay += ms;
+        }
+    }
+    if total_delay > MAX_TOTAL_DELAY_MS {
+        return Err(format!("Total delay of {}ms exceeds {}ms", total_delay, MAX_TOTAL_DELAY_MS));
+    }
+    Ok(())
+}
+
+// Rate limit charges for the whole batch: one request per step, in the step's class
+pub fn charges(request: &BatchRequest) -> Vec<(RouteClass, usize)> {
+    let mut charges: Vec<(RouteClass, usize)> = Vec::new();
+    for class in request.steps.iter().filter_map(BatchStep::rate_class) {
+        match charges.iter_mut().find(|(charged, _)| *charged == class) {
+            Some((_, cost)) => *cost += 1,
+            None => charges.push((class, 1)),
+        }
+    }
+    charges
+}
+
+// Blocking: holds the input lock between delays, so run it off the async runtime.
+// Also returns the time spent injecting, delays excluded. A batch that did not run
+// every step successfully is an error carrying the per-step results
+pub fn execute(request: BatchRequest, client: &str) -> (Result<BatchResponse, ApiError>, Duration) {
+    let mut enigo = crate::input::lock_as(client);
+    crate::scroll::cancel_momentum();
+
+    let total = request.steps.len();
+    let mut results = Vec::with_capacity(total);
+    let mut failed = 0;
+    let mut injection = Duration::ZERO;
+    let mut paused = false;
+
+    for (index, step) in request.steps.into_iter().enumerate() {
+        if crate::killswitch::is_paused() {
+            info!("📦 Batch stopped at step {}: remote input paused", index);
+            paused = true;
+            break;
+        }
+        let action = step.name();
+        let started = Instant::now();
+        let outcome = match step {
+            BatchStep::Mouse(m) => crate::move_mouse(&mut enigo, m.dx as i32, m.dy as i32),
+            BatchStep::Click(c) => crate::execute_click(&mut enigo, &c.click_type),
+            BatchStep::Scroll(s) => crate::execute_scroll(&mut enigo, &s.direction, s.amount, s.velocity),
+            BatchStep::Key(k) => crate::execute_key(&mut enigo, &k.key, k.modifiers),
+            BatchStep::Delay { ms } => {
+                // Other clients and the UDP channel may act during a delay; the
+                // steps around it still run without anything in between
+                drop(enigo);
+                std::thread::sleep(Duration::from_millis(ms));
+                enigo = crate::input::lock_as(client);
+                Ok(())
+            }
+        };
+        if action != "delay" {
+            let elapsed = started.elapsed();
+            crate::metrics::record_injection(action, elapsed);
+            injection += elapsed;
+        }
+
+        match outcome {
+            Ok(()) => results.push(StepResult {
+                index,
+                action,
+                status: "success".to_string(),
+                code: None,
+                message: None,
+            }),
+            Err(e) => {
+                info!("📦 Batch step {} ({}) failed: {}", index, action, e);
+                failed += 1;
+                results.push(StepResult {
+                    index,
+                    action,
+                    status: "error".to_string(),
+                    code: Some(e.code()),
+                    message: Some(e.to_string()),
+                });
+                if request.stop_on_error {
+                    break;
+                }
+            }
+        }
+    }
+
+    let executed = results.len();
+    let message = if paused {
+        format!("Remote input paused after {} of {} steps", executed, total)
+    } else if executed < total {
+        format!("Stopped at step {} of {}", executed, total)
+    } else if failed == 0 {
+        let response = BatchResponse {
+            status: "success".to_string(),
+            message: Some(format!("{} steps executed", executed)),
+            results,
+            timing: None,
+        };
+        return (Ok(response), injection);
+    } else if failed == total {
+        format!("All {} steps failed", total)
+    } else {
+        format!("{} of {} steps failed", failed, total)
+    };
+
+    let results = serde_json::to_value(&results).unwrap_or_default();
+    (Err(ApiError::BatchFailed { message, results, paused }), injection)
+}
@@ -0,0 +1,362 @@
+// Clipboard sync between the phone and the desktop (X11 selections and Wayland data-control)
+use crate::events::{self, ServerEvent};
+use arboard::{Clipboard, GetExtLinux, ImageData, LinuxClipboardKind, SetExtLinux};
+use base64::Engine;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Cursor;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _, CreateWindowAux, Window, WindowClass};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+const DEFAULT_MAX_BYTES: usize = 8 * 1024 * 1024;
+const MAX_IMAGE_SIDE: u32 = 8192;
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+// Without a display the watcher retries ever more slowly, up to this
+const MAX_WATCH_BACKOFF: Duration = Duration::from_secs(60);
+const STAMP_TIMEOUT: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Selection {
+    #[default]
+    Clipboard,
+    // X11 primary selection (middle click paste)
+    Primary,
+}
+
+impl Selection {
+    fn kind(self) -> LinuxClipboardKind {
+        match self {
+            Selection::Clipboard => LinuxClipboardKind::Clipboard,
+            Selection::Primary => LinuxClipboardKind::Primary,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ClipboardQuery {
+    #[serde(default)]
+    pub selection: Selection,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ClipboardContent {
+    Text { text: String },
+    // PNG bytes, base64 encoded
+    Image { png_base64: String },
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ClipboardPut {
+    #[serde(flatten)]
+    pub content: ClipboardContent,
+    #[serde(default)]
+    pub selection: Selection,
+}
+
+#[derive(Debug, Serialize, Default, JsonSchema)]
+pub struct ClipboardResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub kind: Option<String>,
+    pub text: Option<String>,
+    pub png_base64: Option<String>,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+}
+
+lazy_static::lazy_static! {
+    // Kept alive for the whole run: on X11 the owner must stay around to serve pastes
+    static ref CLIPBOARD: Mutex<Option<Clipboard>> = Mutex::new(None);
+    // Hash of the last content seen or written, to avoid echoing our own writes
+    static ref LAST_SEEN: Mutex<Option<u64>> = Mutex::new(None);
+}
+
+pub fn max_bytes() -> usize {
+    crate::config::env_parse("ASTRA_CLIPBOARD_MAX_BYTES", DEFAULT_MAX_BYTES)
+}
+
+fn with_clipboard<T>(f: impl FnOnce(&mut Clipboard) -> Result<T, String>) -> Result<T, String> {
+    let mut guard = CLIPBOARD.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(Clipboard::new().map_err(|e| format!("Clipboard unavailable: {}", e))?);
+    }
+    f(guard.as_mut().unwrap())
+}
+
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn encode_png(image: &ImageData) -> Result<Vec<u8>, String> {
+    let rgba = image::RgbaImage::from_raw(image.width as u32, image.height as u32, image.bytes.to_vec())
+        .ok_or("Clipboard image has an unexpected size")?;
+    let mut png = Vec::new();
+    rgba.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+    Ok(png)
+}
+
+// Reads text if present, otherwise an image
+pub fn read(selection: Selection) -> Result<ClipboardResponse, String> {
+    with_clipboard(|clipboard| {
+        if let Ok(text) = clipboard.get().clipboard(selection.kind()).text() {
+            if text.len() > max_bytes() {
+                return Err(format!("Clipboard text is {} bytes, the limit is {}", text.len(), max_bytes()));
+            }
+            return Ok(ClipboardResponse {
+                status: "success".to_string(),
+                kind: Some("text".to_string()),
+                text: Some(text),
+                ..Default::default()
+            });
+        }
+
+        let image = clipboard
+            .get()
+            .clipboard(selection.kind())
+            .image()
+            .map_err(|e| format!("Clipboard is empty or unsupported: {}", e))?;
+        // Encoding a huge image is slow, so refuse it before trying
+        if image.width > MAX_IMAGE_SIDE as usize || image.height > MAX_IMAGE_SIDE as usize {
+            return Err(format!(
+                "Clipboard image is {}x{}, the limit is {}x{}",
+                image.width, image.height, MAX_IMAGE_SIDE, MAX_IMAGE_SIDE
+            ));
+        }
+        let png = encode_png(&image)?;
+        if png.len() > max_bytes() {
+            return Err(format!("Clipboard image is {} bytes, the limit is {}", png.len(), max_bytes()));
+        }
+        Ok(ClipboardResponse {
+            status: "success".to_string(),
+            kind: Some("image".to_string()),
+            png_base64: Some(base64::engine::general_purpose::STANDARD.encode(&png)),
+            width: Some(image.width),
+            height: Some(image.height),
+            ..Default::default()
+        })
+    })
+}
+
+pub fn write(put: ClipboardPut) -> Result<String, String> {
+    let limit = max_bytes();
+    match put.content {
+        ClipboardContent::Text { text } => {
+            if text.len() > limit {
+                return Err(format!("Text is {} bytes, the limit is {}", text.len(), limit));
+            }
+            if put.selection == Selection::Clipboard {
+                *LAST_SEEN.lock().unwrap() = Some(hash_bytes(text.as_bytes()));
+            }
+            let len = text.len();
+            with_clipboard(|clipboard| {
+                clipboard
+                    .set()
+                    .clipboard(put.selection.kind())
+                    .text(text)
+                    .map_err(|e| format!("Failed to set clipboard: {}", e))
+            })?;
+            Ok(format!("Copied {} bytes of text", len))
+        }
+        ClipboardContent::Image { png_base64 } => {
+            // base64 expands by 4/3, so check before decoding
+            if png_base64.len() / 4 * 3 > limit {
+                return Err(format!("Image exceeds the {} byte limit", limit));
+            }
+            let png = base64::engine::general_purpose::STANDARD
+                .decode(png_base64.trim())
+                .map_err(|e| format!("Invalid base64 image: {}", e))?;
+            // A small PNG can claim huge dimensions, so check them before decoding
+            let reader = || {
+                image::ImageReader::new(Cursor::new(png.as_slice()))
+                    .with_guessed_format()
+                    .map_err(|e| format!("Invalid image: {}", e))
+            };
+            let (width, height) = reader()?.into_dimensions().map_err(|e| format!("Invalid image: {}", e))?;
+            if width > MAX_IMAGE_SIDE || height > MAX_IMAGE_SIDE {
+                return Err(format!("Image is larger than {}x{}", MAX_IMAGE_SIDE, MAX_IMAGE_SIDE));
+            }
+            let mut limits = image::Limits::default();
+            limits.max_image_width = Some(MAX_IMAGE_SIDE);
+            limits.max_image_height = Some(MAX_IMAGE_SIDE);
+            let mut decoder = reader()?;
+            decoder.limits(limits);
+            let decoded = decoder.decode().map_err(|e| format!("Invalid image: {}", e))?;
+            let rgba = decoded.to_rgba8();
+            let (width, height) = (rgba.width() as usize, rgba.height() as usize);
+            let bytes = rgba.into_raw();
+            if put.selection == Selection::Clipboard {
+                *LAST_SEEN.lock().unwrap() = Some(hash_bytes(&bytes));
+            }
+            with_clipboard(|clipboard| {
+                clipboard
+                    .set()
+                    .clipboard(put.selection.kind())
+                    .image(ImageData {
+                        width,
+                        height,
+                        bytes: Cow::Owned(bytes),
+                    })
+                    .map_err(|e| format!("Failed to set clipboard: {}", e))
+            })?;
+            Ok(format!("Copied {}x{} image", width, height))
+        }
+    }
+}
+
+// X11 only: the owner of CLIPBOARD and the time it took the selection. Both change
+// on every copy, so the watcher only fetches (and for images decodes) the content
+// when they do
+struct SelectionStamp {
+    conn: RustConnection,
+    window: Window,
+    clipboard: u32,
+    timestamp: u32,
+    property: u32,
+}
+
+impl SelectionStamp {
+    fn connect() -> Option<Self> {
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() || std::env::var_os("DISPLAY").is_none() {
+            return None;
+        }
+        let (conn, screen_num) = x11rb::connect(None).ok()?;
+        let root = conn.setup().roots[screen_num].root;
+        let window = conn.generate_id().ok()?;
+        conn.create_window(
+            x11rb::COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_ONLY,
+            x11rb::COPY_FROM_PARENT,
+            &CreateWindowAux::new(),
+        )
+        .ok()?;
+        let atom = |name: &str| Some(conn.intern_atom(false, name.as_bytes()).ok()?.reply().ok()?.atom);
+        let (clipboard, timestamp, property) = (atom("CLIPBOARD")?, atom("TIMESTAMP")?, atom("ASTRA_STAMP")?);
+        Some(Self {
+            conn,
+            window,
+            clipboard,
+            timestamp,
+            property,
+        })
+    }
+
+    // None when the owner does not answer the TIMESTAMP target; the caller then
+    // reads the content instead
+    fn read(&self) -> Option<(Window, u32)> {
+        let owner = self.conn.get_selection_owner(self.clipboard).ok()?.reply().ok()?.owner;
+        if owner == x11rb::NONE {
+            return Some((owner, 0));
+        }
+        self.conn
+            .convert_selection(self.window, self.clipboard, self.timestamp, self.property, x11rb::CURRENT_TIME)
+            .ok()?;
+        self.conn.flush().ok()?;
+        let deadline = Instant::now() + STAMP_TIMEOUT;
+        loop {
+            match self.conn.poll_for_event().ok()? {
+                Some(Event::SelectionNotify(notify)) if notify.requestor == self.window => {
+                    if notify.property == x11rb::NONE {
+                        return None;
+                    }
+                    break;
+                }
+                Some(_) => {}
+                None if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(5)),
+                None => return None,
+            }
+        }
+        let reply = self
+            .conn
+            .get_property(true, self.window, self.property, AtomEnum::ANY, 0, 1)
+            .ok()?
+            .reply()
+            .ok()?;
+        let time = reply.value32()?.next()?;
+        Some((owner, time))
+    }
+}
+
+fn read_change(clipboard: &mut Clipboard) -> Result<(u64, ServerEvent), String> {
+    if let Ok(text) = clipboard.get().text() {
+        let hash = hash_bytes(text.as_bytes());
+        let bytes = text.len();
+        return Ok((hash, ServerEvent::ClipboardChanged {
+            kind: "text".to_string(),
+            bytes,
+            text: (bytes <= max_bytes()).then_some(text),
+            width: None,
+            height: None,
+        }));
+    }
+    let image = clipboard.get().image().map_err(|e| e.to_string())?;
+    Ok((hash_bytes(&image.bytes), ServerEvent::ClipboardChanged {
+        kind: "image".to_string(),
+        bytes: image.bytes.len(),
+        text: None,
+        width: Some(image.width),
+        height: Some(image.height),
+    }))
+}
+
+// Polls the desktop clipboard and pushes changes to /events subscribers
+pub fn spawn_watcher() {
+    std::thread::spawn(|| {
+        let mut delay = WATCH_INTERVAL;
+        let mut stamp: Option<Option<SelectionStamp>> = None;
+        let mut last_stamp = None;
+        loop {
+            std::thread::sleep(delay);
+            if let Err(e) = with_clipboard(|_| Ok(())) {
+                if delay == WATCH_INTERVAL {
+                    warn!("📋 Clipboard watcher paused: {}", e);
+                }
+                delay = (delay * 2).min(MAX_WATCH_BACKOFF);
+                continue;
+            }
+            if delay != WATCH_INTERVAL {
+                info!("📋 Clipboard available again, watching for changes");
+                delay = WATCH_INTERVAL;
+            }
+
+            let current = stamp.get_or_insert_with(SelectionStamp::connect).as_ref().and_then(SelectionStamp::read);
+            if current.is_some() && current == last_stamp {
+                continue;
+            }
+            last_stamp = current;
+
+            let Ok((hash, event)) = with_clipboard(read_change) else { continue };
+            let mut last = LAST_SEEN.lock().unwrap();
+            if *last == Some(hash) {
+                continue;
+            }
+            // The first read only establishes a baseline
+            let first = last.is_none();
+            *last = Some(hash);
+            drop(last);
+            if !first {
+                info!("📋 Desktop clipboard changed, notifying clients");
+                events::emit(event);
+            }
+        }
+    });
+}
@@ -0,0 +1,32 @@
+// Shared settings helpers: config directory and environment switches
+use std::path::PathBuf;
+
+// $XDG_CONFIG_HOME/astra, falling back to ~/.config/astra
+pub fn config_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."));
+    base.join("astra")
+}
+
+// Boolean environment switch: "1"/"true"/"on"/"yes" or "0"/"false"/"off"/"no"
+pub fn env_flag(name: &str, default: bool) -> bool {
+    match std::env::var(name) {
+        Ok(value) => match value.trim().to_lowercase().as_str() {
+            "1" | "true" | "on" | "yes" => true,
+            "0" | "false" | "off" | "no" => false,
+            _ => default,
+        },
+        Err(_) => default,
+    }
+}
+
+// Parsed environment value, falling back to `default` when unset or invalid
+pub fn env_parse<T: std::str::FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(default)
+}
@@ -0,0 +1,389 @@
+// astra-ctl: command line client for a running Astra server, for scripting and testing
+use astra_client::types::{BatchRequest, BatchResponse, BatchStep, KeyRequest, ScrollRequest};
+use astra_client::Client;
+use astra_remote::{config, mdns};
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+// The server rejects batches longer than this
+const MAX_BATCH_STEPS: usize = 256;
+// Rate-limited batches are retried this often before giving up
+const MAX_RATE_LIMIT_RETRIES: usize = 10;
+
+#[derive(Parser)]
+#[command(name = "astra-ctl", version, about = "Control a running Astra server")]
+struct Cli {
+    /// Server URL
+    #[arg(long, env = "ASTRA_URL", default_value = "http://127.0.0.1:44828")]
+    url: String,
+
+    /// Device token from `astra-ctl pair`
+    #[arg(long, env = "ASTRA_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+
+    /// Print the server's JSON response instead of a summary
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Press a key combination, e.g. ctrl+c or alt+f4
+    Key { combo: String },
+    /// Type text
+    Type { text: String },
+    /// Run a voice command, e.g. "open firefox"
+    Voice { command: String },
+    /// Move the pointer by a relative offset
+    #[command(allow_negative_numbers = true)]
+    Move { dx: f32, dy: f32 },
+    /// Click: left, right or double
+    Click {
+        #[arg(default_value = "left")]
+        button: String,
+    },
+    /// Scroll up, down, left or right by some detents
+    Scroll {
+        direction: String,
+        #[arg(default_value_t = 1.0)]
+        amount: f32,
+    },
+    /// Release every key and mouse button the server still holds down
+    ResetInput,
+    /// Run or list macros stored in ~/.config/astra/macros
+    Macro {
+        #[command(subcommand)]
+        action: MacroAction,
+    },
+    /// Pair with the code shown on the desktop and print the device token
+    Pair {
+        code: String,
+        #[arg(long, default_value = "astra-ctl")]
+        name: String,
+    },
+    /// List Astra servers on the local network (mDNS)
+    Browse {
+        /// Seconds to listen for answers
+        #[arg(long, default_value_t = 3.0)]
+        timeout: f64,
+    },
+    /// Measure round-trip latency to /ping and /mouse, and the server's share of /mouse
+    Bench {
+        #[arg(long, default_value_t = 100)]
+        count: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum MacroAction {
+    /// Run <name>.json as a batch
+    Run { name: String },
+    /// List available macros
+    List,
+}
+
+fn macros_dir() -> PathBuf {
+    config::config_dir().join("macros")
+}
+
+// A macro file is a /batch body, or just its list of steps
+fn load_macro(name: &str) -> Result<BatchRequest, String> {
+    let path = macros_dir().join(format!("{}.json", name));
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    serde_json::from_str::<BatchRequest>(&text)
+        .or_else(|_| {
+            serde_json::from_str::<Vec<BatchStep>>(&text).map(|steps| BatchRequest {
+                steps,
+                stop_on_error: true,
+            })
+        })
+        .map_err(|e| format!("Invalid macro {}: {}", path.display(), e))
+}
+
+fn key_step(key: &str, modifiers: &[&str]) -> BatchStep {
+    BatchStep::Key(KeyRequest {
+        key: key.to_string(),
+        modifiers: (!modifiers.is_empty()).then(|| modifiers.iter().map(|m| m.to_string()).collect()),
+    })
+}
+
+// One key step per character; single letters are case-insensitive key names on
+// the server, so capitals are sent with shift
+fn text_steps(text: &str) -> Vec<BatchStep> {
+    text.chars()
+        .map(|c| match c {
+            ' ' => key_step("space", &[]),
+            '\n' => key_step("enter", &[]),
+            '\t' => key_step("tab", &[]),
+            c if c.is_ascii_uppercase() => key_step(&c.to_ascii_lowercase().to_string(), &["shift"]),
+            c => key_step(&c.to_string(), &[]),
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+struct LatencyStats {
+    count: usize,
+    errors: usize,
+    min_ms: f64,
+    avg_ms: f64,
+    p50_ms: f64,
+    p95_ms: f64,
+    max_ms: f64,
+}
+
+impl LatencyStats {
+    fn from_samples(mut samples: Vec<Duration>, errors: usize) -> Self {
+        samples.sort();
+        let ms = |d: &Duration| d.as_secs_f64() * 1000.0;
+        let percentile = |p: f64| {
+            samples
+                .get(((samples.len() as f64 - 1.0) * p).round() as usize)
+                .map(ms)
+                .unwrap_or(0.0)
+        };
+        Self {
+            count: samples.len(),
+            errors,
+            min_ms: samples.first().map(ms).unwrap_or(0.0),
+            avg_ms: if samples.is_empty() {
+                0.0
+            } else {
+                samples.iter().map(ms).sum::<f64>() / samples.len() as f64
+            },
+            p50_ms: percentile(0.5),
+            p95_ms: percentile(0.95),
+            max_ms: samples.last().map(ms).unwrap_or(0.0),
+        }
+    }
+
+    fn describe(&self, name: &str) -> String {
+        format!(
+            "{:<6} n={} errors={}  min {:.2} ms  avg {:.2} ms  p50 {:.2} ms  p95 {:.2} ms  max {:.2} ms",
+            name, self.count, self.errors, self.min_ms, self.avg_ms, self.p50_ms, self.p95_ms, self.max_ms
+        )
+    }
+}
+
+#[derive(Serialize)]
+struct BenchReport {
+    ping: LatencyStats,
+    mouse: LatencyStats,
+    // Time the server spent on /mouse, from the response timing; the rest is network
+    mouse_server: LatencyStats,
+}
+
+// Long text outruns the key rate limit after the first chunks; a refused batch
+// ran no steps, so it is sent again once the server says the bucket has refilled
+async fn batch_with_retry(client: &Client, request: &BatchRequest) -> Result<BatchResponse, String> {
+    let mut attempts = 0;
+    loop {
+        match client.batch(request).await {
+            Err(astra_client::Error::Api {
+                code: Some(code),
+                retry_after: Some(retry_after),
+                ..
+            }) if code == "rate_limited" && attempts < MAX_RATE_LIMIT_RETRIES => {
+                attempts += 1;
+                tokio::time::sleep(retry_after).await;
+            }
+            result => return result.map_err(|e| e.to_string()),
+        }
+    }
+}
+
+// Prints `value` as JSON or as the human summary
+fn print<T: Serialize>(json: bool, value: &T, summary: impl FnOnce(&T) -> String) {
+    if json {
+        println!("{}", serde_json::to_string_pretty(value).expect("responses serialize"));
+    } else {
+        println!("{}", summary(value));
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), String> {
+    let mut client = Client::new(&cli.url);
+    if let Some(token) = &cli.token {
+        client = client.with_token(token);
+    }
+    let json = cli.json;
+    let message = |r: &astra_client::types::ApiResponse| r.message.clone().unwrap_or_else(|| r.status.clone());
+    let batch_summary = |r: &astra_client::types::BatchResponse| r.message.clone().unwrap_or_else(|| r.status.clone());
+
+    match cli.command {
+        Command::Key { combo } => {
+            let mut parts: Vec<&str> = combo.split('+').map(str::trim).collect();
+            let key = parts.pop().filter(|k| !k.is_empty()).ok_or("Empty key combination")?;
+            let response = client.key(key, &parts).await.map_err(|e| e.to_string())?;
+            print(json, &response, message);
+        }
+        Command::Type { text } => {
+            let steps = text_steps(&text);
+            for chunk in steps.chunks(MAX_BATCH_STEPS) {
+                let request = BatchRequest {
+                    steps: chunk.to_vec(),
+                    stop_on_error: true,
+                };
+                let response = batch_with_retry(&client, &request).await?;
+                if response.status != "success" {
+                    print(json, &response, batch_summary);
+                    return Err("Typing stopped early".to_string());
+                }
+            }
+            if json {
+                println!("{}", serde_json::json!({ "status": "success", "typed": text.chars().count() }));
+            } else {
+                println!("Typed {} characters", text.chars().count());
+            }
+        }
+        Command::Voice { command } => {
+            let response = client.voice(&command).await.map_err(|e| e.to_string())?;
+            print(json, &response, message);
+        }
+        Command::Move { dx, dy } => {
+            let response = client.mouse(dx, dy).await.map_err(|e| e.to_string())?;
+            print(json, &response, message);
+        }
+        Command::Click { button } => {
+            let response = client.click(&button).await.map_err(|e| e.to_string())?;
+            print(json, &response, message);
+        }
+        Command::Scroll { direction, amount } => {
+            let request = ScrollRequest {
+                direction,
+                amount: Some(amount),
+                velocity: None,
+            };
+            let response = client.scroll(&request).await.map_err(|e| e.to_string())?;
+            print(json, &response, message);
+        }
+        Command::ResetInput => {
+            let response = client.reset_input().await.map_err(|e| e.to_string())?;
+            print(json, &response, |r| r.message.clone().unwrap_or_else(|| r.status.clone()));
+        }
+        Command::Macro {
+            action: MacroAction::Run { name },
+        } => {
+            let request = load_macro(&name)?;
+            let response = client.batch(&request).await.map_err(|e| e.to_string())?;
+            let failed = response.status != "success";
+            print(json, &response, batch_summary);
+            if failed {
+                return Err(format!("Macro '{}' did not complete", name));
+            }
+        }
+        Command::Macro {
+            action: MacroAction::List,
+        } => {
+            let mut names: Vec<String> = std::fs::read_dir(macros_dir())
+                .map(|entries| {
+                    entries
+                        .filter_map(|e| e.ok())
+                        .filter_map(|e| e.file_name().to_str()?.strip_suffix(".json").map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default();
+            names.sort();
+            print(json, &names, |names| {
+                if names.is_empty() {
+                    format!("No macros in {}", macros_dir().display())
+                } else {
+                    names.join("\n")
+                }
+            });
+        }
+        Command::Pair { code, name } => {
+            let response = client.pair(&code, Some(&name)).await.map_err(|e| e.to_string())?;
+            print(json, &response, |r| match &r.token {
+                Some(token) => format!("Paired. Use --token {} or export ASTRA_TOKEN={}", token, token),
+                None => r.message.clone().unwrap_or_else(|| r.status.clone()),
+            });
+        }
+        Command::Browse { timeout } => {
+            let timeout = Duration::try_from_secs_f64(timeout).map_err(|_| "Invalid timeout")?;
+            let servers = tokio::task::spawn_blocking(move || mdns::browse(timeout))
+                .await
+                .map_err(|e| e.to_string())??;
+            print(json, &servers, |servers| {
+                if servers.is_empty() {
+                    return "No Astra servers found".to_string();
+                }
+                servers
+                    .iter()
+                    .map(|s| {
+                        format!(
+                            "{}  {}  version {}  pairing {}  tls {}",
+                            s.name,
+                            s.url().unwrap_or_else(|| format!("{}:{}", s.hostname, s.port)),
+                            s.version.as_deref().unwrap_or("?"),
+                            s.pairing.as_deref().unwrap_or("?"),
+                            s.tls_fp.as_deref().unwrap_or("none")
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            });
+        }
+        Command::Bench { count } => {
+            let mut report = Vec::new();
+            let mut server = Vec::with_capacity(count);
+            for endpoint in ["ping", "mouse"] {
+                let mut samples = Vec::with_capacity(count);
+                let mut errors = 0;
+                for _ in 0..count {
+                    let started = Instant::now();
+                    // A zero move exercises the input path without moving the pointer
+                    let result = match endpoint {
+                        "ping" => client.ping().await,
+                        _ => client.mouse(0.0, 0.0).await,
+                    };
+                    match result {
+                        Ok(response) => {
+                            samples.push(started.elapsed());
+                            if let Some(timing) = response.timing {
+                                server.push(Duration::from_secs_f64(timing.server_ms / 1000.0));
+                            }
+                        }
+                        Err(_) => errors += 1,
+                    }
+                }
+                report.push(LatencyStats::from_samples(samples, errors));
+            }
+            let mouse = report.pop().expect("two endpoints");
+            let ping = report.pop().expect("two endpoints");
+            let mouse_server = LatencyStats::from_samples(server, 0);
+            print(json, &BenchReport { ping, mouse, mouse_server }, |r| {
+                format!(
+                    "{}\n{}\n{}",
+                    r.ping.describe("/ping"),
+                    r.mouse.describe("/mouse"),
+                    r.mouse_server.describe("server")
+                )
+            });
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let json = cli.json;
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            if json {
+                println!("{}", serde_json::json!({ "status": "error", "message": e }));
+            } else {
+                eprintln!("astra-ctl: {}", e);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
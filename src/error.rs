@@ -0,0 +1,279 @@
+// API errors shared by every route, rendered as
+//   {"status": "error", "code": "unknown_click_type", "message": "...", "details": {...}}
+// with the matching HTTP status. Extractor rejections (malformed JSON, bad query
+// strings, oversized bodies, failed WebSocket handshakes) are converted too,
+// through ApiJson and ApiQuery.
+use axum::{
+    async_trait,
+    extract::{
+        rejection::{BytesRejection, JsonRejection, QueryRejection},
+        ws::rejection::WebSocketUpgradeRejection,
+        FromRequest, FromRequestParts, Request,
+    },
+    http::{header, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::json;
+use std::fmt;
+use std::time::Duration;
+
+pub const CLICK_TYPES: &[&str] = &["left", "right", "double"];
+pub const SCROLL_DIRECTIONS: &[&str] = &["up", "down", "left", "right", "stop"];
+
+#[derive(Debug, Clone)]
+pub enum ApiError {
+    // Body is not valid JSON
+    MalformedJson(String),
+    // Body is JSON but does not match the request type
+    InvalidField(String),
+    MissingContentType(String),
+    InvalidQuery(String),
+    // Well-formed request with values the server refuses
+    InvalidRequest(String),
+    UnknownKey(String),
+    UnknownClickType(String),
+    UnknownScrollDirection(String),
+    UnknownVoiceCommand(String),
+    Unauthorized,
+    PairingFailed(String),
+    FeatureDisabled(String),
+    // Admin routes only answer on loopback
+    Forbidden(String),
+    // The paired device is not allowed this kind of action
+    MissingScope { scope: &'static str, device: String },
+    NotFound(String),
+    Conflict(String),
+    PayloadTooLarge(String),
+    RateLimited { retry_after: Duration },
+    // Remote input was paused on the desktop (flood protection or kill switch)
+    InputPaused(String),
+    // Some steps of a batch failed or did not run; `results` lists the ones that ran
+    BatchFailed {
+        message: String,
+        results: serde_json::Value,
+        paused: bool,
+    },
+    // A desktop service (D-Bus, PulseAudio, X11, compositor IPC, ...) is missing or failed
+    BackendUnavailable(String),
+    Internal(String),
+}
+
+// Attached to every error response, so middleware can tell the code without
+// parsing the body
+#[derive(Debug, Clone, Copy)]
+pub struct ErrorCode(pub &'static str);
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ErrorResponse {
+    // Always "error"
+    pub status: String,
+    pub code: String,
+    pub message: String,
+    pub details: Option<serde_json::Value>,
+}
+
+impl ApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::MalformedJson(_) => "malformed_json",
+            ApiError::InvalidField(_) => "invalid_field",
+            ApiError::MissingContentType(_) => "missing_content_type",
+            ApiError::InvalidQuery(_) => "invalid_query",
+            ApiError::InvalidRequest(_) => "invalid_request",
+            ApiError::UnknownKey(_) => "unknown_key",
+            ApiError::UnknownClickType(_) => "unknown_click_type",
+            ApiError::UnknownScrollDirection(_) => "unknown_scroll_direction",
+            ApiError::UnknownVoiceCommand(_) => "unknown_voice_command",
+            ApiError::Unauthorized => "unauthorized",
+            ApiError::PairingFailed(_) => "pairing_failed",
+            ApiError::FeatureDisabled(_) => "feature_disabled",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::MissingScope { .. } => "missing_scope",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::PayloadTooLarge(_) => "payload_too_large",
+            ApiError::RateLimited { .. } => "rate_limited",
+            ApiError::InputPaused(_) | ApiError::BatchFailed { paused: true, .. } => "input_paused",
+            ApiError::BatchFailed { .. } => "batch_failed",
+            ApiError::BackendUnavailable(_) => "backend_unavailable",
+            ApiError::Internal(_) => "internal",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::MalformedJson(_)
+            | ApiError::InvalidQuery(_)
+            | ApiError::InvalidRequest(_)
+            | ApiError::UnknownKey(_)
+            | ApiError::UnknownClickType(_)
+            | ApiError::UnknownScrollDirection(_)
+            | ApiError::UnknownVoiceCommand(_) => StatusCode::BAD_REQUEST,
+            ApiError::InvalidField(_) | ApiError::BatchFailed { paused: false, .. } => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            ApiError::MissingContentType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ApiError::Unauthorized | ApiError::PairingFailed(_) => StatusCode::UNAUTHORIZED,
+            ApiError::FeatureDisabled(_) | ApiError::Forbidden(_) | ApiError::MissingScope { .. } => {
+                StatusCode::FORBIDDEN
+            }
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::InputPaused(_) | ApiError::BatchFailed { paused: true, .. } | ApiError::BackendUnavailable(_) => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    // Structured context for clients, e.g. the accepted values
+    fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            ApiError::UnknownKey(key) => Some(json!({ "key": key })),
+            ApiError::UnknownClickType(click_type) => Some(json!({ "click_type": click_type, "allowed": CLICK_TYPES })),
+            ApiError::UnknownScrollDirection(direction) => {
+                Some(json!({ "direction": direction, "allowed": SCROLL_DIRECTIONS }))
+            }
+            ApiError::UnknownVoiceCommand(command) => Some(json!({ "command": command })),
+            ApiError::MissingScope { scope, .. } => Some(json!({ "scope": scope })),
+            ApiError::RateLimited { retry_after } => Some(json!({ "retry_after_ms": retry_after.as_millis() as u64 })),
+            ApiError::BatchFailed { results, .. } => Some(json!({ "results": results })),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::UnknownKey(key) => write!(f, "Unknown key: {}", key),
+            ApiError::UnknownClickType(click_type) => write!(f, "Unknown click type: {}", click_type),
+            ApiError::UnknownScrollDirection(direction) => write!(f, "Unknown scroll direction: {}", direction),
+            ApiError::UnknownVoiceCommand(command) => write!(f, "Unknown voice command: {}", command),
+            ApiError::Unauthorized => write!(f, "A paired device token is required"),
+            ApiError::MissingScope { scope, device } => {
+                write!(f, "Device '{}' does not have the '{}' scope", device, scope)
+            }
+            ApiError::RateLimited { retry_after } => {
+                write!(f, "Too many requests, retry in {} ms", retry_after.as_millis())
+            }
+            ApiError::MalformedJson(message)
+            | ApiError::InvalidField(message)
+            | ApiError::MissingContentType(message)
+            | ApiError::InvalidQuery(message)
+            | ApiError::InvalidRequest(message)
+            | ApiError::PairingFailed(message)
+            | ApiError::FeatureDisabled(message)
+            | ApiError::Forbidden(message)
+            | ApiError::NotFound(message)
+            | ApiError::Conflict(message)
+            | ApiError::PayloadTooLarge(message)
+            | ApiError::InputPaused(message)
+            | ApiError::BatchFailed { message, .. }
+            | ApiError::BackendUnavailable(message)
+            | ApiError::Internal(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorResponse {
+            status: "error".to_string(),
+            code: self.code().to_string(),
+            message: self.to_string(),
+            details: self.details(),
+        };
+        let mut response = (self.status(), Json(body)).into_response();
+        response.extensions_mut().insert(ErrorCode(self.code()));
+        if let ApiError::RateLimited { retry_after } = self {
+            // Retry-After is in whole seconds; round up so clients never retry early
+            let seconds = (retry_after.as_millis() as u64).div_ceil(1000).max(1);
+            response.headers_mut().insert(header::RETRY_AFTER, seconds.into());
+        }
+        response
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        match rejection {
+            JsonRejection::JsonDataError(e) => ApiError::InvalidField(e.body_text()),
+            JsonRejection::JsonSyntaxError(e) => ApiError::MalformedJson(e.body_text()),
+            JsonRejection::MissingJsonContentType(e) => ApiError::MissingContentType(e.body_text()),
+            JsonRejection::BytesRejection(e) => e.into(),
+            other => ApiError::MalformedJson(other.body_text()),
+        }
+    }
+}
+
+impl From<BytesRejection> for ApiError {
+    fn from(rejection: BytesRejection) -> Self {
+        if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE {
+            ApiError::PayloadTooLarge(rejection.body_text())
+        } else {
+            ApiError::InvalidRequest(rejection.body_text())
+        }
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        ApiError::InvalidQuery(rejection.body_text())
+    }
+}
+
+// A plain GET to /events, or a handshake with a bad version or key
+impl From<WebSocketUpgradeRejection> for ApiError {
+    fn from(rejection: WebSocketUpgradeRejection) -> Self {
+        ApiError::InvalidRequest(rejection.body_text())
+    }
+}
+
+impl From<tokio::task::JoinError> for ApiError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        ApiError::Internal(e.to_string())
+    }
+}
+
+// axum's Json, with rejections reported as ApiError
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state).await?;
+        Ok(ApiJson(value))
+    }
+}
+
+// axum's Query, with rejections reported as ApiError
+pub struct ApiQuery<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequestParts<S> for ApiQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Query(value) = axum::extract::Query::<T>::from_request_parts(parts, state).await?;
+        Ok(ApiQuery(value))
+    }
+}
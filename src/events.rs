@@ -0,0 +1,146 @@
+// Server push events, streamed to paired clients over the /events WebSocket
+use axum::{
+    extract::ws::{rejection::WebSocketUpgradeRejection, Message, WebSocket, WebSocketUpgrade},
+    response::Response,
+};
+use crate::error::{ApiError, ApiQuery};
+use crate::pairing::Caller;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::info;
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerEvent {
+    ClipboardChanged {
+        kind: String,
+        // Inline for text under the size cap; images are fetched with GET /clipboard
+        text: Option<String>,
+        width: Option<usize>,
+        height: Option<usize>,
+        bytes: usize,
+    },
+    UploadProgress {
+        upload_id: String,
+        filename: String,
+        offset: u64,
+        size: u64,
+    },
+    // Last message before the server closes the stream on shutdown
+    Goodbye {
+        reason: String,
+    },
+}
+
+impl ServerEvent {
+    // Devices without this scope do not receive the event
+    fn scope(&self) -> Option<crate::scopes::Scope> {
+        match self {
+            ServerEvent::ClipboardChanged { .. } => Some(crate::scopes::Scope::Clipboard),
+            ServerEvent::UploadProgress { .. } => Some(crate::scopes::Scope::Files),
+            ServerEvent::Goodbye { .. } => None,
+        }
+    }
+}
+
+// Events for the desktop GUI, which reads them from the server's stdout
+// as single lines prefixed with GUI_PREFIX
+pub const GUI_PREFIX: &str = "ASTRA_EVENT ";
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GuiEvent {
+    Log { message: String },
+    // The HTTP server is accepting connections on `port`
+    Listening { port: u16 },
+    // The server could not start and is about to exit
+    StartupFailed { code: String, port: u16, message: String },
+    InputPaused { reason: String },
+    InputResumed,
+    // Median timings of the last second for a client that sent input in it
+    Latency {
+        client: String,
+        samples: usize,
+        server_ms: f64,
+        injection_ms: f64,
+        rtt_ms: Option<f64>,
+    },
+}
+
+pub fn gui_emit(event: GuiEvent) {
+    let json = serde_json::to_string(&event).expect("GUI events serialize");
+    println!("{}{}", GUI_PREFIX, json);
+}
+
+// Shows a line in the GUI activity log
+pub fn gui_log(message: impl Into<String>) {
+    gui_emit(GuiEvent::Log {
+        message: message.into(),
+    });
+}
+
+lazy_static::lazy_static! {
+    static ref EVENTS: broadcast::Sender<ServerEvent> = broadcast::channel(64).0;
+}
+
+pub fn emit(event: ServerEvent) {
+    // No subscribers is not an error
+    let _ = EVENTS.send(event);
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventsQuery {
+    // Browsers cannot set headers on WebSocket requests, so the token may come as ?token=
+    token: Option<String>,
+}
+
+pub async fn handle_events(
+    ws: Result<WebSocketUpgrade, WebSocketUpgradeRejection>,
+    Caller(device): Caller,
+    ApiQuery(query): ApiQuery<EventsQuery>,
+) -> Result<Response, ApiError> {
+    let device = device
+        .or_else(|| query.token.as_deref().and_then(crate::pairing::device_from_token))
+        .ok_or(ApiError::Unauthorized)?;
+    let ws = ws?;
+
+    info!("📡 Event stream opened for '{}'", device.name);
+    Ok(ws.on_upgrade(move |socket| stream_events(socket, device.id, device.name)))
+}
+
+async fn stream_events(mut socket: WebSocket, device_id: String, device_name: String) {
+    let _session = crate::metrics::session(crate::metrics::Session::Events);
+    let mut events = EVENTS.subscribe();
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) if event.scope().is_some_and(|scope| !crate::scopes::device_has(&device_id, scope)) => {}
+                Ok(event) => {
+                    let json = serde_json::to_string(&event).expect("events serialize");
+                    if socket.send(Message::Text(json)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    info!("📡 Event stream for '{}' skipped {} events", device_name, skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                Some(Ok(_)) => {}
+            },
+            reason = crate::shutdown::requested() => {
+                let json = serde_json::to_string(&ServerEvent::Goodbye { reason }).expect("events serialize");
+                let _ = socket.send(Message::Text(json)).await;
+                let _ = socket.send(Message::Close(None)).await;
+                break;
+            }
+        }
+    }
+    info!("📡 Event stream closed for '{}'", device_name);
+    let reason = format!("'{}' disconnected", device_name);
+    let client = crate::ratelimit::Client::device(&device_id);
+    tokio::task::spawn_blocking(move || crate::input::release_client(client.key(), &reason));
+}
@@ -1,29 +1,355 @@
 use eframe::egui;
 use std::sync::{Arc, Mutex};
-use std::process::{Command, Child};
-use std::collections::VecDeque;
+use std::process::{Command, Child, ExitStatus, Stdio};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+use std::collections::{BTreeMap, VecDeque};
 use qrcode::QrCode;
 use image::Luma;
 use local_ip_address::local_ip;
+use astra_remote::config;
 
 const APP_ICON: &[u8] = include_bytes!("../assets/icon.png");
 
+// The server announces GUI-relevant events on stdout with this prefix
+const SERVER_EVENT_PREFIX: &str = "ASTRA_EVENT ";
+const DEFAULT_PORT: u16 = 44828;
+// Following ports the server may fall back to when DEFAULT_PORT is taken
+const PORT_FALLBACK: u16 = 10;
+// The server drains requests for up to 5s; after this it is killed
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+// Last lines of server stderr shown when it crashes
+const STDERR_TAIL_LINES: usize = 20;
+// How often the server process is checked while it runs
+const HEALTH_POLL: Duration = Duration::from_millis(500);
+// Restart delays double from the first to the last; after more crashes than
+// RESTART_LIMIT within CRASH_WINDOW the server is left stopped
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+const RESTART_DELAY_MAX: Duration = Duration::from_secs(60);
+const RESTART_LIMIT: usize = 5;
+const CRASH_WINDOW: Duration = Duration::from_secs(300);
+// Newest audit entries loaded into the audit view, and how many matches it shows
+const AUDIT_VIEW_LIMIT: usize = 10_000;
+const AUDIT_VIEW_SHOWN: usize = 500;
+// The server reports latency once a second per client sending input; the sparkline
+// shows the last LATENCY_POINTS reports and clients idle for LATENCY_IDLE disappear
+const LATENCY_POINTS: usize = 60;
+const LATENCY_IDLE: Duration = Duration::from_secs(60);
+
 #[derive(Clone, Debug)]
 struct LogEntry {
     timestamp: String,
     command: String,
 }
 
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerEvent {
+    Log { message: String },
+    Listening { port: u16 },
+    StartupFailed { message: String },
+    InputPaused { reason: String },
+    InputResumed,
+    Latency {
+        client: String,
+        server_ms: f64,
+        injection_ms: f64,
+        rtt_ms: Option<f64>,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+// Recent latency of one client; times in milliseconds
+struct ClientLatency {
+    // Round trips when the client reports them, otherwise server time
+    points: VecDeque<f32>,
+    server_ms: f64,
+    injection_ms: f64,
+    rtt_ms: Option<f64>,
+    updated: Instant,
+}
+
+impl ClientLatency {
+    // The network share is what the round trip leaves after the server's time
+    fn describe(&self) -> String {
+        let server = format!("server {:.1} ms · injection {:.1} ms", self.server_ms, self.injection_ms);
+        match self.rtt_ms {
+            Some(rtt) => format!(
+                "round trip {:.1} ms · network {:.1} ms · {}",
+                rtt,
+                (rtt - self.server_ms).max(0.0),
+                server
+            ),
+            None => server,
+        }
+    }
+}
+
+fn record_latency(
+    latency: &Mutex<BTreeMap<String, ClientLatency>>,
+    client: String,
+    server_ms: f64,
+    injection_ms: f64,
+    rtt_ms: Option<f64>,
+) {
+    let mut latency = latency.lock().unwrap();
+    let entry = latency.entry(client).or_insert_with(|| ClientLatency {
+        points: VecDeque::with_capacity(LATENCY_POINTS),
+        server_ms,
+        injection_ms,
+        rtt_ms,
+        updated: Instant::now(),
+    });
+    if entry.points.len() == LATENCY_POINTS {
+        entry.points.pop_front();
+    }
+    entry.points.push_back(rtt_ms.unwrap_or(server_ms) as f32);
+    entry.server_ms = server_ms;
+    entry.injection_ms = injection_ms;
+    entry.rtt_ms = rtt_ms;
+    entry.updated = Instant::now();
+}
+
+// Draws `points` scaled to the largest, newest on the right
+fn sparkline(ui: &mut egui::Ui, points: &VecDeque<f32>, color: egui::Color32) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(160.0, 24.0), egui::Sense::hover());
+    ui.painter().rect_filled(rect, 3.0, egui::Color32::from_rgb(15, 15, 25));
+    let max = points.iter().copied().fold(1.0_f32, f32::max);
+    let step = rect.width() / (LATENCY_POINTS - 1) as f32;
+    let first = LATENCY_POINTS - points.len();
+    let line: Vec<egui::Pos2> = points
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| egui::pos2(rect.left() + (first + i) as f32 * step, rect.bottom() - value / max * rect.height()))
+        .collect();
+    ui.painter().add(egui::Shape::line(line, egui::Stroke::new(1.5, color)));
+}
+
+fn push_log(logs: &Mutex<VecDeque<LogEntry>>, message: &str) {
+    let mut logs = logs.lock().unwrap();
+    let timestamp = chrono::Local::now().format("%H:%M:%S").to_string();
+    
+    logs.push_front(LogEntry {
+        timestamp,
+        command: message.to_string(),
+    });
+    
+    if logs.len() > 10 {
+        logs.pop_back();
+    }
+}
+
+// Calls one of the server's loopback-only admin routes and returns the response
+// body; error responses become their message
+fn request_local(port: u16, method: &str, path: &str, body: Option<&str>) -> Result<String, String> {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(Duration::from_secs(2))).ok();
+    let body = body.unwrap_or("");
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: 127.0.0.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    )
+    .map_err(|e| e.to_string())?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).map_err(|e| e.to_string())?;
+    let content = response.split_once("\r\n\r\n").map(|(_, c)| c.to_string()).unwrap_or_default();
+    match response.split_whitespace().nth(1) {
+        Some(status) if status.starts_with('2') => Ok(content),
+        Some(status) => Err(serde_json::from_str::<serde_json::Value>(&content)
+            .ok()
+            .and_then(|v| v["message"].as_str().map(str::to_string))
+            .unwrap_or_else(|| format!("server answered {}", status))),
+        None => Err("no response".to_string()),
+    }
+}
+
+fn post_local(port: u16, path: &str) -> Result<(), String> {
+    request_local(port, "POST", path, None).map(|_| ())
+}
+
+// Scope names as the server spells them, in the order it lists them
+const SCOPES: [&str; 9] = [
+    "pointer", "keyboard", "text", "voice", "launch", "shell", "clipboard", "files", "screen",
+];
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct Device {
+    id: String,
+    name: String,
+    paired_at: String,
+    scopes: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct DevicesResponse {
+    message: Option<String>,
+    pairing_required: bool,
+    devices: Vec<Device>,
+}
+
+// Asks the server to stop cleanly and only kills it if it does not exit in time
+fn shutdown_child(mut child: Child, port: u16) -> String {
+    if let Err(e) = post_local(port, "/shutdown") {
+        let _ = child.kill();
+        let _ = child.wait();
+        return format!("Server killed, clean stop failed: {}", e);
+    }
+    let deadline = Instant::now() + STOP_TIMEOUT;
+    while Instant::now() < deadline {
+        match child.try_wait() {
+            Ok(Some(_)) => return "Server stopped".to_string(),
+            Ok(None) => std::thread::sleep(Duration::from_millis(100)),
+            Err(_) => break,
+        }
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+    "Server did not stop in time and was killed".to_string()
+}
+
+// The server's audit log, newest first: rotated audit-<time>.jsonl files, then
+// the current audit.jsonl
+fn load_audit() -> Result<Vec<String>, String> {
+    let dir = config::config_dir().join("audit");
+    let mut files: Vec<std::path::PathBuf> = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with("audit-") && n.ends_with(".jsonl"))
+            })
+            .collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Cannot read {}: {}", dir.display(), e)),
+    };
+    files.sort();
+    files.push(dir.join("audit.jsonl"));
+
+    let mut lines = VecDeque::with_capacity(AUDIT_VIEW_LIMIT);
+    for file in files {
+        let Ok(text) = std::fs::read_to_string(&file) else { continue };
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            if lines.len() == AUDIT_VIEW_LIMIT {
+                lines.pop_front();
+            }
+            lines.push_back(line.to_string());
+        }
+    }
+    Ok(lines.into_iter().rev().collect())
+}
+
+// Every word of the search must appear somewhere in the entry
+fn audit_matches(line: &str, search: &str) -> bool {
+    let line = line.to_lowercase();
+    search.to_lowercase().split_whitespace().all(|word| line.contains(word))
+}
+
+fn describe_audit(line: &str) -> String {
+    let Ok(entry) = serde_json::from_str::<serde_json::Value>(line) else {
+        return line.to_string();
+    };
+    let text = |key: &str| entry[key].as_str().unwrap_or("").to_string();
+    let time = chrono::DateTime::parse_from_rfc3339(&text("timestamp"))
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|_| text("timestamp"));
+    let client = match entry["device_name"].as_str() {
+        Some(name) => format!("{} ({})", name, text("ip")),
+        None => text("ip"),
+    };
+    format!("{}  {}  {}  → {}", time, client, text("action"), text("result"))
+}
+
+// Writes the entries, oldest first, to ~/astra-audit-<time>.jsonl
+fn export_audit(lines: &[&String]) -> Result<std::path::PathBuf, String> {
+    let home = std::env::var_os("HOME").ok_or("HOME is not set")?;
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let path = std::path::PathBuf::from(home).join(format!("astra-audit-{}.jsonl", stamp));
+    let mut text = String::new();
+    for line in lines.iter().rev() {
+        text.push_str(line);
+        text.push('\n');
+    }
+    std::fs::write(&path, text).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+// Why the server process ended. cargo exits with 101 both when the server panics
+// and when it is killed by a signal, and says which on stderr.
+fn describe_exit(status: ExitStatus) -> String {
+    use std::os::unix::process::ExitStatusExt;
+    if let Some(signal) = status.signal() {
+        let name = match signal {
+            6 => " (SIGABRT)",
+            9 => " (SIGKILL)",
+            11 => " (SIGSEGV)",
+            15 => " (SIGTERM)",
+            _ => "",
+        };
+        return format!("killed by signal {}{}", signal, name);
+    }
+    match status.code() {
+        Some(101) => "panicked or was killed (exit code 101)".to_string(),
+        Some(code) => format!("exited with code {}", code),
+        None => "exited".to_string(),
+    }
+}
+
+// What the server process is doing, as it reports on stdout
+#[derive(Clone, Debug, PartialEq)]
+enum ServerState {
+    Stopped,
+    // Spawned but not listening yet (cargo may still be building it)
+    Starting,
+    Running { port: u16 },
+    Stopping,
+    Failed { message: String },
+    // Exited while running, without being stopped from here
+    Crashed { reason: String },
+}
+
+impl ServerState {
+    fn is_active(&self) -> bool {
+        matches!(self, ServerState::Starting | ServerState::Running { .. })
+    }
+}
+
 struct AstraApp {
     server_process: Arc<Mutex<Option<Child>>>,
-    server_running: bool,
+    server_state: Arc<Mutex<ServerState>>,
     mouse_sensitivity: f32,
     scroll_sensitivity: f32,
     ip_address: String,
     port: u16,
     qr_texture: Option<egui::TextureHandle>,
     command_logs: Arc<Mutex<VecDeque<LogEntry>>>,
+    // Why the server paused remote input, while it is paused
+    input_paused: Arc<Mutex<Option<String>>>,
+    // Last lines the server wrote to stderr, for the crash report
+    stderr_tail: Arc<Mutex<VecDeque<String>>>,
+    // By client name
+    latency: Arc<Mutex<BTreeMap<String, ClientLatency>>>,
     auto_start: bool,
+    auto_restart: bool,
+    // Recent crashes, for the restart backoff
+    crashes: VecDeque<Instant>,
+    restart_at: Option<Instant>,
+    audit_open: bool,
+    audit_search: String,
+    // Raw JSON lines, newest first
+    audit_lines: Vec<String>,
+    audit_status: Option<String>,
+    devices_open: bool,
+    devices: Vec<Device>,
+    pairing_required: bool,
+    devices_status: Option<String>,
 }
 
 impl Default for AstraApp {
@@ -32,14 +358,28 @@ impl Default for AstraApp {
         
         Self {
             server_process: Arc::new(Mutex::new(None)),
-            server_running: false,
+            server_state: Arc::new(Mutex::new(ServerState::Stopped)),
             mouse_sensitivity: 1.0,
             scroll_sensitivity: 1.0,
             ip_address: ip,
-            port: 44828,
+            port: DEFAULT_PORT,
             qr_texture: None,
             command_logs: Arc::new(Mutex::new(VecDeque::with_capacity(10))),
+            input_paused: Arc::new(Mutex::new(None)),
+            stderr_tail: Arc::new(Mutex::new(VecDeque::with_capacity(STDERR_TAIL_LINES))),
+            latency: Arc::new(Mutex::new(BTreeMap::new())),
             auto_start: false,
+            auto_restart: true,
+            crashes: VecDeque::new(),
+            restart_at: None,
+            audit_open: false,
+            audit_search: String::new(),
+            audit_lines: Vec::new(),
+            audit_status: None,
+            devices_open: false,
+            devices: Vec::new(),
+            pairing_required: false,
+            devices_status: None,
         }
     }
 }
@@ -91,54 +431,364 @@ impl AstraApp {
         }
     }
 
-    fn start_server(&mut self) {
-        if self.server_running {
+    fn server_state(&self) -> ServerState {
+        self.server_state.lock().unwrap().clone()
+    }
+
+    fn set_server_state(&self, state: ServerState) {
+        *self.server_state.lock().unwrap() = state;
+    }
+
+    fn start_server(&mut self, ctx: &egui::Context) {
+        if !matches!(
+            self.server_state(),
+            ServerState::Stopped | ServerState::Failed { .. } | ServerState::Crashed { .. }
+        ) {
             return;
         }
+        self.restart_at = None;
+        // Reap a server that exited on its own, e.g. after a failed start
+        if let Some(mut old) = self.server_process.lock().unwrap().take() {
+            let _ = old.kill();
+            let _ = old.wait();
+        }
 
         let result = Command::new("cargo")
-            .args(&["run", "--release", "--bin", "astra-remote"])
+            .args(["run", "--release", "--bin", "astra-remote"])
             .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .env("ASTRA_PORT", DEFAULT_PORT.to_string())
+            .env("ASTRA_PORT_FALLBACK", PORT_FALLBACK.to_string())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
             .spawn();
 
         match result {
-            Ok(child) => {
+            Ok(mut child) => {
+                self.set_server_state(ServerState::Starting);
+                if let Some(stdout) = child.stdout.take() {
+                    self.watch_server_output(stdout, ctx.clone());
+                }
+                if let Some(stderr) = child.stderr.take() {
+                    self.watch_server_errors(stderr);
+                }
                 *self.server_process.lock().unwrap() = Some(child);
-                self.server_running = true;
-                self.add_log("Server started successfully");
+                self.add_log("Starting server...");
             }
             Err(e) => {
+                self.set_server_state(ServerState::Failed {
+                    message: e.to_string(),
+                });
                 self.add_log(&format!("Failed to start server: {}", e));
             }
         }
     }
 
     fn stop_server(&mut self) {
-        if !self.server_running {
+        // Stopping also cancels a pending restart
+        self.restart_at = None;
+        if !self.server_state().is_active() {
+            return;
+        }
+
+        let Some(child) = self.server_process.lock().unwrap().take() else {
+            return;
+        };
+        self.set_server_state(ServerState::Stopping);
+        *self.input_paused.lock().unwrap() = None;
+        self.add_log("Stopping server...");
+        let logs = Arc::clone(&self.command_logs);
+        let state = Arc::clone(&self.server_state);
+        let port = self.port;
+        std::thread::spawn(move || {
+            push_log(&logs, &shutdown_child(child, port));
+            *state.lock().unwrap() = ServerState::Stopped;
+        });
+    }
+
+    // Notices the server exiting on its own and restarts it when that is wanted.
+    // Stopping from here takes the child first, so only unexpected exits get here.
+    fn check_server(&mut self, ctx: &egui::Context) {
+        let exited = {
+            let mut process = self.server_process.lock().unwrap();
+            match process.as_mut().map(|child| child.try_wait()) {
+                Some(Ok(Some(status))) => {
+                    process.take();
+                    Some(Ok(status))
+                }
+                Some(Err(e)) => {
+                    process.take();
+                    Some(Err(format!("could not be checked: {}", e)))
+                }
+                _ => None,
+            }
+        };
+        match exited {
+            // Stopped cleanly from elsewhere, e.g. astra-ctl or SIGTERM
+            Some(Ok(status)) if status.success() => {
+                *self.input_paused.lock().unwrap() = None;
+                self.add_log("Server stopped");
+                self.set_server_state(ServerState::Stopped);
+            }
+            Some(Ok(status)) => self.server_exited(describe_exit(status)),
+            Some(Err(reason)) => self.server_exited(reason),
+            None => {}
+        }
+
+        if let Some(at) = self.restart_at {
+            if Instant::now() >= at {
+                self.add_log("Restarting server...");
+                self.start_server(ctx);
+            } else {
+                ctx.request_repaint_after(at - Instant::now());
+            }
+        }
+        if self.server_state().is_active() {
+            ctx.request_repaint_after(HEALTH_POLL);
+        }
+    }
+
+    fn server_exited(&mut self, reason: String) {
+        *self.input_paused.lock().unwrap() = None;
+        let was_running = match self.server_state() {
+            // Already reported by the server, the exit adds nothing
+            ServerState::Failed { .. } => return,
+            state => matches!(state, ServerState::Running { .. }),
+        };
+        self.add_log(&format!("Server {}", reason));
+        self.set_server_state(ServerState::Crashed { reason });
+        // A failed start (build error, bad config) would fail the same way again
+        if !self.auto_restart || !was_running {
             return;
         }
 
-        let mut process = self.server_process.lock().unwrap();
-        if let Some(mut child) = process.take() {
-            let _ = child.kill();
-            self.server_running = false;
-            self.add_log("Server stopped");
+        let now = Instant::now();
+        self.crashes.retain(|&at| now.duration_since(at) < CRASH_WINDOW);
+        self.crashes.push_back(now);
+        if self.crashes.len() > RESTART_LIMIT {
+            self.add_log(&format!(
+                "Server crashed {} times in {} minutes, not restarting",
+                self.crashes.len(),
+                CRASH_WINDOW.as_secs() / 60
+            ));
+            return;
         }
+        let delay = (RESTART_DELAY * 2u32.pow(self.crashes.len() as u32 - 1)).min(RESTART_DELAY_MAX);
+        self.add_log(&format!("Restarting server in {}s", delay.as_secs()));
+        self.restart_at = Some(now + delay);
     }
 
-    fn add_log(&self, message: &str) {
-        let mut logs = self.command_logs.lock().unwrap();
-        let timestamp = chrono::Local::now().format("%H:%M:%S").to_string();
-        
-        logs.push_front(LogEntry {
-            timestamp,
-            command: message.to_string(),
+    // Forwards server stderr to ours and keeps its tail for the crash report
+    fn watch_server_errors(&self, stderr: std::process::ChildStderr) {
+        let tail = Arc::clone(&self.stderr_tail);
+        tail.lock().unwrap().clear();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stderr).lines() {
+                let Ok(line) = line else { break };
+                eprintln!("{}", line);
+                let mut tail = tail.lock().unwrap();
+                if tail.len() == STDERR_TAIL_LINES {
+                    tail.pop_front();
+                }
+                tail.push_back(line);
+            }
         });
-        
-        if logs.len() > 10 {
-            logs.pop_back();
+    }
+
+    // Forwards server output to our stdout and picks out events for the activity
+    // log and the server state
+    fn watch_server_output(&self, stdout: std::process::ChildStdout, ctx: egui::Context) {
+        let logs = Arc::clone(&self.command_logs);
+        let input_paused = Arc::clone(&self.input_paused);
+        let state = Arc::clone(&self.server_state);
+        let latency = Arc::clone(&self.latency);
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                let Some(json) = line.strip_prefix(SERVER_EVENT_PREFIX) else {
+                    println!("{}", line);
+                    continue;
+                };
+                match serde_json::from_str::<ServerEvent>(json) {
+                    Ok(ServerEvent::Log { message }) => push_log(&logs, &message),
+                    Ok(ServerEvent::Listening { port }) => {
+                        push_log(&logs, &format!("Server listening on port {}", port));
+                        *state.lock().unwrap() = ServerState::Running { port };
+                    }
+                    Ok(ServerEvent::StartupFailed { message }) => {
+                        push_log(&logs, &format!("Server failed to start: {}", message));
+                        *state.lock().unwrap() = ServerState::Failed { message };
+                    }
+                    Ok(ServerEvent::InputPaused { reason }) => {
+                        push_log(&logs, &format!("⛔ Remote input paused: {}", reason));
+                        *input_paused.lock().unwrap() = Some(reason);
+                    }
+                    Ok(ServerEvent::InputResumed) => {
+                        push_log(&logs, "▶ Remote input resumed");
+                        *input_paused.lock().unwrap() = None;
+                    }
+                    Ok(ServerEvent::Latency {
+                        client,
+                        server_ms,
+                        injection_ms,
+                        rtt_ms,
+                    }) => record_latency(&latency, client, server_ms, injection_ms, rtt_ms),
+                    Ok(ServerEvent::Unknown) | Err(_) => {}
+                }
+                ctx.request_repaint();
+            }
+            // The exit itself is picked up by check_server
+            ctx.request_repaint();
+        });
+    }
+
+    fn pause_input(&self) {
+        if let Err(e) = post_local(self.port, "/pause") {
+            self.add_log(&format!("Failed to pause input: {}", e));
+        }
+    }
+
+    fn resume_input(&self) {
+        if let Err(e) = post_local(self.port, "/resume") {
+            self.add_log(&format!("Failed to resume input: {}", e));
+        }
+    }
+
+    fn add_log(&self, message: &str) {
+        push_log(&self.command_logs, message);
+    }
+
+    // Runs a /devices call and shows the resulting list
+    fn devices_request(&mut self, method: &str, path: &str, body: Option<String>) {
+        let result = request_local(self.port, method, path, body.as_deref())
+            .and_then(|content| serde_json::from_str::<DevicesResponse>(&content).map_err(|e| e.to_string()));
+        match result {
+            Ok(response) => {
+                self.devices = response.devices;
+                self.pairing_required = response.pairing_required;
+                self.devices_status = response.message;
+            }
+            Err(e) => self.devices_status = Some(format!("Failed: {}", e)),
+        }
+    }
+
+    fn show_devices_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.devices_open;
+        let mut action: Option<(&str, String, Option<String>)> = None;
+        egui::Window::new("📱 Devices")
+            .open(&mut open)
+            .default_size([640.0, 360.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("🔄 Refresh").clicked() {
+                        action = Some(("GET", "/devices".to_string(), None));
+                    }
+                    if self.pairing_required {
+                        ui.label("Pairing required: unpaired clients are refused");
+                    } else {
+                        ui.label(
+                            egui::RichText::new("⚠ Pairing optional: unpaired clients may do everything")
+                                .color(egui::Color32::from_rgb(255, 180, 0))
+                                .strong(),
+                        )
+                        .on_hover_text("Narrowing any device's scopes makes pairing required");
+                    }
+                });
+                if let Some(status) = &self.devices_status {
+                    ui.label(egui::RichText::new(status).color(egui::Color32::LIGHT_GRAY));
+                }
+                ui.separator();
+                if self.devices.is_empty() {
+                    ui.label(egui::RichText::new("No paired devices").italics().color(egui::Color32::DARK_GRAY));
+                }
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for device in &self.devices {
+                        ui.horizontal(|ui| {
+                            ui.label(egui::RichText::new(&device.name).strong());
+                            ui.label(egui::RichText::new(&device.id).monospace().color(egui::Color32::GRAY))
+                                .on_hover_text(format!("Paired {}", device.paired_at));
+                            if ui.small_button("Unpair").clicked() {
+                                action = Some(("DELETE", format!("/devices/{}", device.id), None));
+                            }
+                        });
+                        ui.horizontal_wrapped(|ui| {
+                            for scope in SCOPES {
+                                let mut enabled = device.scopes.iter().any(|s| s == scope);
+                                if ui.checkbox(&mut enabled, scope).changed() {
+                                    let scopes: Vec<&str> = SCOPES
+                                        .into_iter()
+                                        .filter(|s| if *s == scope { enabled } else { device.scopes.iter().any(|d| d == s) })
+                                        .collect();
+                                    let body = serde_json::json!({ "scopes": scopes }).to_string();
+                                    action = Some(("PUT", format!("/devices/{}", device.id), Some(body)));
+                                }
+                            }
+                        });
+                        ui.separator();
+                    }
+                });
+            });
+        self.devices_open = open;
+        if let Some((method, path, body)) = action {
+            self.devices_request(method, &path, body);
+        }
+    }
+
+    fn reload_audit(&mut self) {
+        match load_audit() {
+            Ok(lines) => {
+                self.audit_lines = lines;
+                self.audit_status = None;
+            }
+            Err(e) => self.audit_status = Some(e),
         }
     }
+
+    fn show_audit_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.audit_open;
+        egui::Window::new("📜 Audit Log")
+            .open(&mut open)
+            .default_size([700.0, 450.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Search:");
+                    ui.add(egui::TextEdit::singleline(&mut self.audit_search).hint_text("device, route, action, result..."));
+                    if ui.button("🔄 Refresh").clicked() {
+                        self.reload_audit();
+                    }
+                });
+                let matches: Vec<&String> = self
+                    .audit_lines
+                    .iter()
+                    .filter(|line| audit_matches(line, &self.audit_search))
+                    .collect();
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} of {} entries", matches.len(), self.audit_lines.len()));
+                    if ui.button("💾 Export").clicked() {
+                        self.audit_status = Some(match export_audit(&matches) {
+                            Ok(path) => format!("Exported {} entries to {}", matches.len(), path.display()),
+                            Err(e) => e,
+                        });
+                    }
+                });
+                if let Some(status) = &self.audit_status {
+                    ui.label(egui::RichText::new(status).color(egui::Color32::LIGHT_GRAY));
+                }
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    if matches.is_empty() {
+                        ui.label(egui::RichText::new("No entries").italics().color(egui::Color32::DARK_GRAY));
+                    }
+                    for line in matches.iter().take(AUDIT_VIEW_SHOWN) {
+                        ui.label(egui::RichText::new(describe_audit(line)).monospace().size(12.0))
+                            .on_hover_text(line.as_str());
+                    }
+                    if matches.len() > AUDIT_VIEW_SHOWN {
+                        ui.label(format!("... {} more, narrow the search or export", matches.len() - AUDIT_VIEW_SHOWN));
+                    }
+                });
+            });
+        self.audit_open = open;
+    }
 }
 
 impl eframe::App for AstraApp {
@@ -147,6 +797,24 @@ impl eframe::App for AstraApp {
         let bg_color = egui::Color32::from_rgb(15, 15, 25);
         let panel_color = egui::Color32::from_rgb(25, 25, 40);
         let accent_color = egui::Color32::from_rgb(100, 150, 255);
+
+        self.check_server(ctx);
+
+        // The server may have fallen back to another port; the QR code must match
+        if let ServerState::Running { port } = self.server_state() {
+            if port != self.port {
+                self.port = port;
+                self.generate_qr_code(ctx);
+            }
+        }
+
+        // Pause only: keys typed remotely can land in this window too, and must
+        // not be able to resume
+        let pause_pressed = ctx.input(|i| i.modifiers.ctrl && i.modifiers.shift && i.key_pressed(egui::Key::P));
+        let state = self.server_state();
+        if pause_pressed && matches!(state, ServerState::Running { .. }) {
+            self.pause_input();
+        }
         
         egui::CentralPanel::default()
             .frame(egui::Frame::none().fill(bg_color))
@@ -245,31 +913,86 @@ impl eframe::App for AstraApp {
                                     .min_size(egui::vec2(180.0, 45.0))
                                     .rounding(8.0);
                                     
-                                    if self.server_running {
+                                    if state.is_active() {
                                         if ui.add(stop_btn).clicked() {
                                             self.stop_server();
                                         }
                                         ui.add_space(5.0);
+                                        let (status, color) = match &state {
+                                            ServerState::Running { .. } => ("🟢 Server Running", egui::Color32::from_rgb(100, 255, 100)),
+                                            _ => ("🟡 Server Starting...", egui::Color32::from_rgb(255, 220, 100)),
+                                        };
+                                        ui.label(egui::RichText::new(status).size(14.0).color(color));
+
+                                        let paused = self.input_paused.lock().unwrap().clone();
+                                        if let Some(reason) = paused {
+                                            ui.add_space(5.0);
+                                            ui.label(
+                                                egui::RichText::new(format!("⛔ Remote input paused: {}", reason))
+                                                    .size(14.0)
+                                                    .color(egui::Color32::from_rgb(255, 180, 80))
+                                            );
+                                            if ui.button("▶ Resume Input").clicked() {
+                                                self.resume_input();
+                                            }
+                                        } else if ui.button("⏸ Pause Remote Input (Ctrl+Shift+P)").clicked() {
+                                            self.pause_input();
+                                        }
+                                    } else if state == ServerState::Stopping {
                                         ui.label(
-                                            egui::RichText::new("🟢 Server Running")
+                                            egui::RichText::new("🟡 Server Stopping...")
                                                 .size(14.0)
-                                                .color(egui::Color32::from_rgb(100, 255, 100))
+                                                .color(egui::Color32::from_rgb(255, 220, 100))
                                         );
                                     } else {
                                         if ui.add(start_btn).clicked() {
-                                            self.start_server();
+                                            self.start_server(ctx);
                                         }
                                         ui.add_space(5.0);
+                                        let status = match &state {
+                                            ServerState::Failed { message } => format!("🔴 Failed to start: {}", message),
+                                            ServerState::Crashed { reason } => format!("🔴 Server {}", reason),
+                                            _ => "🔴 Server Stopped".to_string(),
+                                        };
                                         ui.label(
-                                            egui::RichText::new("🔴 Server Stopped")
+                                            egui::RichText::new(status)
                                                 .size(14.0)
                                                 .color(egui::Color32::from_rgb(255, 100, 100))
                                         );
+                                        if let Some(at) = self.restart_at {
+                                            let secs = at.saturating_duration_since(Instant::now()).as_secs() + 1;
+                                            ui.horizontal(|ui| {
+                                                ui.label(format!("Restarting in {}s", secs));
+                                                if ui.small_button("Cancel").clicked() {
+                                                    self.restart_at = None;
+                                                }
+                                            });
+                                        }
+                                        if matches!(state, ServerState::Failed { .. } | ServerState::Crashed { .. }) {
+                                            let tail = self.stderr_tail.lock().unwrap();
+                                            if !tail.is_empty() {
+                                                egui::CollapsingHeader::new("Server stderr")
+                                                    .default_open(true)
+                                                    .show(ui, |ui| {
+                                                        egui::ScrollArea::vertical()
+                                                            .max_height(150.0)
+                                                            .stick_to_bottom(true)
+                                                            .show(ui, |ui| {
+                                                                for line in tail.iter() {
+                                                                    ui.label(egui::RichText::new(line).monospace().size(11.0));
+                                                                }
+                                                            });
+                                                    });
+                                            }
+                                        }
                                     }
                                 });
                                 
                                 ui.add_space(10.0);
                                 ui.checkbox(&mut self.auto_start, "Auto-start server on launch");
+                                if ui.checkbox(&mut self.auto_restart, "Restart server after a crash").changed() && !self.auto_restart {
+                                    self.restart_at = None;
+                                }
                             });
                     });
                     
@@ -346,22 +1069,70 @@ impl eframe::App for AstraApp {
                             });
                         
                         ui.add_space(20.0);
-                        
-                        // Logs section
+
+                        // Latency section
                         egui::Frame::none()
                             .fill(panel_color)
                             .rounding(10.0)
                             .inner_margin(20.0)
                             .show(ui, |ui| {
                                 ui.set_min_width(400.0);
-                                ui.set_min_height(250.0);
-                                
+
                                 ui.label(
-                                    egui::RichText::new("📝 Activity Log (Last 10 Commands)")
+                                    egui::RichText::new("⏱ Latency")
                                         .size(20.0)
                                         .color(egui::Color32::WHITE)
                                 );
                                 ui.add_space(10.0);
+
+                                let mut latency = self.latency.lock().unwrap();
+                                latency.retain(|_, client| client.updated.elapsed() < LATENCY_IDLE);
+                                if latency.is_empty() {
+                                    ui.label(
+                                        egui::RichText::new("No input from clients in the last minute")
+                                            .size(14.0)
+                                            .color(egui::Color32::DARK_GRAY)
+                                            .italics()
+                                    );
+                                }
+                                for (name, client) in latency.iter() {
+                                    ui.horizontal(|ui| {
+                                        ui.label(egui::RichText::new(name).size(13.0).color(egui::Color32::LIGHT_GRAY));
+                                        sparkline(ui, &client.points, accent_color);
+                                    });
+                                    ui.label(egui::RichText::new(client.describe()).size(11.0).color(egui::Color32::GRAY));
+                                    ui.add_space(3.0);
+                                }
+                            });
+
+                        ui.add_space(20.0);
+                        
+                        // Logs section
+                        egui::Frame::none()
+                            .fill(panel_color)
+                            .rounding(10.0)
+                            .inner_margin(20.0)
+                            .show(ui, |ui| {
+                                ui.set_min_width(400.0);
+                                ui.set_min_height(250.0);
+                                
+                                ui.horizontal(|ui| {
+                                    ui.label(
+                                        egui::RichText::new("📝 Activity Log (Last 10 Commands)")
+                                            .size(20.0)
+                                            .color(egui::Color32::WHITE)
+                                    );
+                                    if ui.button("📜 Audit Log").clicked() {
+                                        self.audit_open = true;
+                                        self.reload_audit();
+                                    }
+                                    let running = matches!(self.server_state(), ServerState::Running { .. });
+                                    if ui.add_enabled(running, egui::Button::new("📱 Devices")).clicked() {
+                                        self.devices_open = true;
+                                        self.devices_request("GET", "/devices", None);
+                                    }
+                                });
+                                ui.add_space(10.0);
                                 
                                 egui::ScrollArea::vertical()
                                     .max_height(200.0)
@@ -402,10 +1173,20 @@ impl eframe::App for AstraApp {
                 
                 ui.add_space(20.0);
             });
+
+        if self.audit_open {
+            self.show_audit_window(ctx);
+        }
+        if self.devices_open {
+            self.show_devices_window(ctx);
+        }
     }
 
+    // Blocks so the server is gone before the GUI exits
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
-        self.stop_server();
+        if let Some(child) = self.server_process.lock().unwrap().take() {
+            println!("{}", shutdown_child(child, self.port));
+        }
     }
 }
 
@@ -0,0 +1,427 @@
+// The input device behind every route, with a record of what is held down
+//
+// All key and button presses go through `Input`, which remembers what is pressed,
+// and by which client, until it is released. What a client still holds is released
+// when it disconnects; everything is released when the server stops or a thread
+// panics, so Ctrl or a mouse button can never stay stuck system-wide. The backend
+// is enigo, or with ASTRA_INPUT_BACKEND=recording a backend that only logs and
+// remembers the events (headless runs, CI, debugging).
+use enigo::{Enigo, Key, KeyboardControllable, MouseButton, MouseControllable};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use tracing::{info, warn};
+
+// Events kept by the recording backend
+const RECORDING_CAPACITY: usize = 1000;
+
+pub trait InputBackend: Send {
+    fn name(&self) -> &'static str;
+    fn mouse_move_relative(&mut self, dx: i32, dy: i32);
+    fn mouse_down(&mut self, button: MouseButton);
+    fn mouse_up(&mut self, button: MouseButton);
+    fn mouse_scroll_x(&mut self, steps: i32);
+    fn mouse_scroll_y(&mut self, steps: i32);
+    fn key_down(&mut self, key: Key);
+    fn key_up(&mut self, key: Key);
+    fn key_sequence(&mut self, text: &str);
+    // Most recent events first, for backends that keep them
+    fn recorded(&self) -> Option<Vec<String>> {
+        None
+    }
+}
+
+struct EnigoBackend(Enigo);
+
+impl InputBackend for EnigoBackend {
+    fn name(&self) -> &'static str {
+        "enigo"
+    }
+
+    fn mouse_move_relative(&mut self, dx: i32, dy: i32) {
+        self.0.mouse_move_relative(dx, dy);
+    }
+
+    fn mouse_down(&mut self, button: MouseButton) {
+        self.0.mouse_down(button);
+    }
+
+    fn mouse_up(&mut self, button: MouseButton) {
+        self.0.mouse_up(button);
+    }
+
+    fn mouse_scroll_x(&mut self, steps: i32) {
+        self.0.mouse_scroll_x(steps);
+    }
+
+    fn mouse_scroll_y(&mut self, steps: i32) {
+        self.0.mouse_scroll_y(steps);
+    }
+
+    fn key_down(&mut self, key: Key) {
+        self.0.key_down(key);
+    }
+
+    fn key_up(&mut self, key: Key) {
+        self.0.key_up(key);
+    }
+
+    fn key_sequence(&mut self, text: &str) {
+        self.0.key_sequence(text);
+    }
+}
+
+#[derive(Default)]
+struct RecordingBackend {
+    events: VecDeque<String>,
+}
+
+impl RecordingBackend {
+    fn record(&mut self, event: String) {
+        info!("🎬 {}", event);
+        if self.events.len() == RECORDING_CAPACITY {
+            self.events.pop_back();
+        }
+        self.events.push_front(event);
+    }
+}
+
+impl InputBackend for RecordingBackend {
+    fn name(&self) -> &'static str {
+        "recording"
+    }
+
+    fn mouse_move_relative(&mut self, dx: i32, dy: i32) {
+        self.record(format!("move {} {}", dx, dy));
+    }
+
+    fn mouse_down(&mut self, button: MouseButton) {
+        self.record(format!("button down {:?}", button));
+    }
+
+    fn mouse_up(&mut self, button: MouseButton) {
+        self.record(format!("button up {:?}", button));
+    }
+
+    fn mouse_scroll_x(&mut self, steps: i32) {
+        self.record(format!("scroll x {}", steps));
+    }
+
+    fn mouse_scroll_y(&mut self, steps: i32) {
+        self.record(format!("scroll y {}", steps));
+    }
+
+    fn key_down(&mut self, key: Key) {
+        self.record(format!("key down {:?}", key));
+    }
+
+    fn key_up(&mut self, key: Key) {
+        self.record(format!("key up {:?}", key));
+    }
+
+    fn key_sequence(&mut self, text: &str) {
+        self.record(format!("type {:?}", text));
+    }
+
+    fn recorded(&self) -> Option<Vec<String>> {
+        Some(self.events.iter().cloned().collect())
+    }
+}
+
+pub struct Input {
+    backend: Box<dyn InputBackend>,
+    // The client holding the lock (see `lock_as`), recorded with what it presses
+    client: Option<String>,
+    // In press order, so they are released in reverse
+    held_keys: Vec<(Key, Option<String>)>,
+    held_buttons: Vec<(MouseButton, Option<String>)>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct InputResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub backend: String,
+    // Keys and buttons still held down, e.g. "Control" or "Left"
+    pub held: Vec<String>,
+    // Only with the recording backend: latest events first
+    pub recorded: Option<Vec<String>>,
+}
+
+impl Input {
+    fn new(backend: Box<dyn InputBackend>) -> Self {
+        Self {
+            backend,
+            client: None,
+            held_keys: Vec::new(),
+            held_buttons: Vec::new(),
+        }
+    }
+
+    fn from_env() -> Self {
+        let backend: Box<dyn InputBackend> = match std::env::var("ASTRA_INPUT_BACKEND").as_deref() {
+            Ok("recording") => Box::new(RecordingBackend::default()),
+            Ok("enigo") | Err(_) => Box::new(EnigoBackend(Enigo::new())),
+            Ok(other) => {
+                warn!("🎮 Unknown ASTRA_INPUT_BACKEND={:?}, using enigo", other);
+                Box::new(EnigoBackend(Enigo::new()))
+            }
+        };
+        info!("🎮 Input backend: {}", backend.name());
+        Self::new(backend)
+    }
+
+    pub fn mouse_move_relative(&mut self, dx: i32, dy: i32) {
+        self.backend.mouse_move_relative(dx, dy);
+    }
+
+    // Tracked before the backend call, so a press that panics halfway is still released
+    pub fn mouse_down(&mut self, button: MouseButton) {
+        if !self.held_buttons.iter().any(|(b, _)| *b == button) {
+            self.held_buttons.push((button, self.client.clone()));
+        }
+        self.backend.mouse_down(button);
+    }
+
+    pub fn mouse_up(&mut self, button: MouseButton) {
+        self.backend.mouse_up(button);
+        self.held_buttons.retain(|(b, _)| *b != button);
+    }
+
+    pub fn mouse_click(&mut self, button: MouseButton) {
+        self.mouse_down(button);
+        self.mouse_up(button);
+    }
+
+    pub fn mouse_scroll_x(&mut self, steps: i32) {
+        self.backend.mouse_scroll_x(steps);
+    }
+
+    pub fn mouse_scroll_y(&mut self, steps: i32) {
+        self.backend.mouse_scroll_y(steps);
+    }
+
+    pub fn key_down(&mut self, key: Key) {
+        if !self.held_keys.iter().any(|(k, _)| *k == key) {
+            self.held_keys.push((key, self.client.clone()));
+        }
+        self.backend.key_down(key);
+    }
+
+    pub fn key_up(&mut self, key: Key) {
+        self.backend.key_up(key);
+        self.held_keys.retain(|(k, _)| *k != key);
+    }
+
+    pub fn key_click(&mut self, key: Key) {
+        self.key_down(key);
+        self.key_up(key);
+    }
+
+    pub fn key_sequence(&mut self, text: &str) {
+        self.backend.key_sequence(text);
+    }
+
+    pub fn held(&self) -> Vec<String> {
+        let keys = self.held_keys.iter().map(|(k, _)| format!("{:?}", k));
+        keys.chain(self.held_buttons.iter().map(|(b, _)| format!("{:?}", b))).collect()
+    }
+
+    // Releases everything still held, newest first; returns what was released
+    pub fn release_all(&mut self) -> Vec<String> {
+        self.release_where(|_| true)
+    }
+
+    // Releases only what `client` pressed, leaving other clients' keys down
+    pub fn release_client(&mut self, client: &str) -> Vec<String> {
+        self.release_where(|owner| owner.as_deref() == Some(client))
+    }
+
+    fn release_where(&mut self, pressed_by: impl Fn(&Option<String>) -> bool) -> Vec<String> {
+        let mut released = Vec::new();
+        let (keys, kept): (Vec<_>, Vec<_>) = self.held_keys.drain(..).partition(|(_, owner)| pressed_by(owner));
+        self.held_keys = kept;
+        for (key, _) in keys.into_iter().rev() {
+            self.backend.key_up(key);
+            released.push(format!("{:?}", key));
+        }
+        let (buttons, kept): (Vec<_>, Vec<_>) = self.held_buttons.drain(..).partition(|(_, owner)| pressed_by(owner));
+        self.held_buttons = kept;
+        for (button, _) in buttons.into_iter().rev() {
+            self.backend.mouse_up(button);
+            released.push(format!("{:?}", button));
+        }
+        released
+    }
+
+    pub fn status(&self, message: Option<String>) -> InputResponse {
+        InputResponse {
+            status: "success".to_string(),
+            message,
+            backend: self.backend.name().to_string(),
+            held: self.held(),
+            recorded: self.backend.recorded(),
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref INPUT: Mutex<Input> = Mutex::new(Input::from_env());
+}
+
+// A thread that panicked while holding the lock may have left keys down, so a
+// poisoned lock releases everything before it is handed out
+fn acquire(client: Option<&str>) -> MutexGuard<'static, Input> {
+    let mut input = INPUT.lock().unwrap_or_else(|poisoned| {
+        let mut input = poisoned.into_inner();
+        let released = input.release_all();
+        warn!("🎮 Input lock recovered after a panic, released {:?}", released);
+        INPUT.clear_poison();
+        input
+    });
+    input.client = client.map(str::to_string);
+    input
+}
+
+// Locks the input device for the server itself
+pub fn lock() -> MutexGuard<'static, Input> {
+    acquire(None)
+}
+
+// Locks the input device for a client (a ratelimit::Client key such as
+// "device:<id>"); what it presses is released when that client disconnects
+pub fn lock_as(client: &str) -> MutexGuard<'static, Input> {
+    acquire(Some(client))
+}
+
+// Releases everything held and logs why; used on shutdown and pause
+pub fn release_all(reason: &str) {
+    log_released(lock().release_all(), reason);
+}
+
+// Releases what one client holds and logs why; used on disconnects
+pub fn release_client(client: &str, reason: &str) {
+    log_released(lock().release_client(client), reason);
+}
+
+fn log_released(released: Vec<String>, reason: &str) {
+    if !released.is_empty() {
+        info!("🎮 Released {} ({})", released.join(", "), reason);
+    }
+}
+
+static RELEASING_AFTER_PANIC: AtomicBool = AtomicBool::new(false);
+
+// After any panic, release held input once the panicking thread has unwound
+// (it may hold the input lock, so this cannot happen on the panicking thread)
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        if RELEASING_AFTER_PANIC.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        std::thread::spawn(|| {
+            release_all("after a panic");
+            RELEASING_AFTER_PANIC.store(false, Ordering::SeqCst);
+        });
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording() -> Input {
+        Input::new(Box::new(RecordingBackend::default()))
+    }
+
+    // Oldest first, which reads more naturally in assertions
+    fn events(input: &Input) -> Vec<String> {
+        let mut events = input.backend.recorded().unwrap();
+        events.reverse();
+        events
+    }
+
+    #[test]
+    fn presses_are_tracked_until_released() {
+        let mut input = recording();
+        input.key_down(Key::Control);
+        input.mouse_down(MouseButton::Left);
+        // Pressing again does not track it twice
+        input.key_down(Key::Control);
+        assert_eq!(input.held(), vec!["Control", "Left"]);
+
+        input.key_up(Key::Control);
+        input.mouse_up(MouseButton::Left);
+        assert!(input.held().is_empty());
+        assert!(input.release_all().is_empty());
+    }
+
+    #[test]
+    fn clicks_leave_nothing_held() {
+        let mut input = recording();
+        input.key_click(Key::Return);
+        input.mouse_click(MouseButton::Right);
+        input.mouse_move_relative(4, -2);
+        input.key_sequence("hi");
+        assert!(input.held().is_empty());
+        assert_eq!(
+            events(&input),
+            vec![
+                "key down Return",
+                "key up Return",
+                "button down Right",
+                "button up Right",
+                "move 4 -2",
+                "type \"hi\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn release_all_goes_newest_first() {
+        let mut input = recording();
+        input.key_down(Key::Control);
+        input.key_down(Key::Shift);
+        input.mouse_down(MouseButton::Left);
+
+        assert_eq!(input.release_all(), vec!["Shift", "Control", "Left"]);
+        assert!(input.held().is_empty());
+        assert_eq!(
+            events(&input)[3..],
+            ["key up Shift", "key up Control", "button up Left"]
+        );
+    }
+
+    #[test]
+    fn release_client_leaves_other_clients_alone() {
+        let mut input = recording();
+        input.client = Some("device:phone".to_string());
+        input.key_down(Key::Alt);
+        input.mouse_down(MouseButton::Left);
+        input.client = Some("device:tablet".to_string());
+        input.key_down(Key::Control);
+        input.client = None;
+        input.key_down(Key::Shift);
+
+        assert_eq!(input.release_client("device:phone"), vec!["Alt", "Left"]);
+        assert_eq!(input.held(), vec!["Control", "Shift"]);
+        assert!(input.release_client("device:unknown").is_empty());
+        assert_eq!(input.release_all(), vec!["Shift", "Control"]);
+    }
+
+    #[test]
+    fn recording_keeps_the_latest_events() {
+        let mut input = recording();
+        for step in 0..RECORDING_CAPACITY as i32 + 5 {
+            input.mouse_move_relative(step, 0);
+        }
+        let recorded = input.status(None).recorded.unwrap();
+        assert_eq!(recorded.len(), RECORDING_CAPACITY);
+        assert_eq!(recorded[0], format!("move {} 0", RECORDING_CAPACITY + 4));
+        assert_eq!(input.status(None).backend, "recording");
+    }
+}
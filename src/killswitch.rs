@@ -0,0 +1,228 @@
+// Emergency stop for remote input
+//
+// While paused, every route that acts on the desktop answers 503 input_paused,
+// batches stop at the next step and the UDP pointer channel drops packets. Only
+// the routes in `allowed_while_paused` keep working. Pausing releases whatever a client may
+// be holding down. Only this machine can toggle it: the GUI, POST /pause and
+// /resume over loopback, or a key combination on a physical keyboard
+// (ASTRA_KILL_COMBO, default ctrl+alt+pause). Remote clients cannot produce the
+// combination: injected keys go through XTEST or the compositor, not /dev/input,
+// and the one device the server adds there (the uinput scroll wheel) has no keys
+// and is skipped by name. Keyboards plugged in later are picked up by a rescan.
+use crate::error::ApiError;
+use crate::events::{gui_emit, GuiEvent};
+use axum::{
+    extract::Request,
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use evdev::{EventType, Key};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+
+const DEFAULT_COMBO: &str = "ctrl+alt+pause";
+// How often /dev/input is scanned for newly plugged keyboards
+const RESCAN_INTERVAL: Duration = Duration::from_secs(5);
+
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+pub fn is_paused() -> bool {
+    PAUSED.load(Ordering::SeqCst)
+}
+
+pub fn ensure_active() -> Result<(), ApiError> {
+    if is_paused() {
+        Err(ApiError::InputPaused(
+            "Remote input is paused, resume it on the desktop".to_string(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+// Reads, the admin routes, pairing and releasing stuck input; anything not
+// listed here is refused while paused, including routes added later
+fn allowed_while_paused(method: &Method, path: &str) -> bool {
+    if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+        return true;
+    }
+    matches!(path, "/reset-input" | "/pause" | "/resume" | "/shutdown" | "/pair") || path.starts_with("/devices/")
+}
+
+pub async fn middleware(request: Request, next: Next) -> Response {
+    if !allowed_while_paused(request.method(), request.uri().path()) {
+        if let Err(e) = ensure_active() {
+            return e.into_response();
+        }
+    }
+    next.run(request).await
+}
+
+pub fn pause(reason: impl Into<String>) {
+    if PAUSED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let reason = reason.into();
+    warn!("⛔ Remote input paused: {}", reason);
+    release_held_input();
+    gui_emit(GuiEvent::InputPaused { reason });
+}
+
+pub fn resume() {
+    if !PAUSED.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    info!("▶️  Remote input resumed");
+    gui_emit(GuiEvent::InputResumed);
+}
+
+fn toggle(reason: &str) {
+    if is_paused() {
+        resume();
+    } else {
+        pause(reason);
+    }
+}
+
+// A running batch holds the input lock until its next step, so release from a
+// separate thread instead of blocking the caller
+fn release_held_input() {
+    crate::scroll::cancel_momentum();
+    std::thread::spawn(|| crate::input::release_all("remote input paused"));
+}
+
+// Each combo part lists the physical keys that satisfy it (left or right Ctrl, ...)
+fn combo_part(name: &str) -> Option<Vec<Key>> {
+    let keys = match name.trim().to_lowercase().as_str() {
+        "ctrl" | "control" => vec![Key::KEY_LEFTCTRL, Key::KEY_RIGHTCTRL],
+        "alt" => vec![Key::KEY_LEFTALT, Key::KEY_RIGHTALT],
+        "shift" => vec![Key::KEY_LEFTSHIFT, Key::KEY_RIGHTSHIFT],
+        "win" | "super" | "meta" => vec![Key::KEY_LEFTMETA, Key::KEY_RIGHTMETA],
+        "pause" | "break" => vec![Key::KEY_PAUSE],
+        "scrolllock" => vec![Key::KEY_SCROLLLOCK],
+        "escape" | "esc" => vec![Key::KEY_ESC],
+        "backspace" => vec![Key::KEY_BACKSPACE],
+        "delete" | "del" => vec![Key::KEY_DELETE],
+        "space" => vec![Key::KEY_SPACE],
+        "f1" => vec![Key::KEY_F1],
+        "f2" => vec![Key::KEY_F2],
+        "f3" => vec![Key::KEY_F3],
+        "f4" => vec![Key::KEY_F4],
+        "f5" => vec![Key::KEY_F5],
+        "f6" => vec![Key::KEY_F6],
+        "f7" => vec![Key::KEY_F7],
+        "f8" => vec![Key::KEY_F8],
+        "f9" => vec![Key::KEY_F9],
+        "f10" => vec![Key::KEY_F10],
+        "f11" => vec![Key::KEY_F11],
+        "f12" => vec![Key::KEY_F12],
+        _ => return None,
+    };
+    Some(keys)
+}
+
+fn parse_combo(combo: &str) -> Result<Vec<Vec<Key>>, String> {
+    combo
+        .split('+')
+        .map(|name| combo_part(name).ok_or_else(|| format!("unknown key '{}'", name.trim())))
+        .collect()
+}
+
+fn watch_keyboard(mut device: evdev::Device, combo: Arc<Vec<Vec<Key>>>, combo_name: Arc<str>) {
+    let name = device.name().unwrap_or("keyboard").to_string();
+    let mut held = HashSet::new();
+    loop {
+        let events = match device.fetch_events() {
+            Ok(events) => events,
+            Err(e) => {
+                info!("⛔ Stopped watching {} for the kill switch: {}", name, e);
+                return;
+            }
+        };
+        for event in events {
+            if event.event_type() != EventType::KEY {
+                continue;
+            }
+            let key = Key::new(event.code());
+            match event.value() {
+                // Press; repeats (2) do not toggle again
+                1 => {
+                    held.insert(key);
+                    let completes = combo.iter().any(|part| part.contains(&key))
+                        && combo.iter().all(|part| part.iter().any(|k| held.contains(k)));
+                    if completes {
+                        toggle(&format!("{} pressed on {}", combo_name, name));
+                    }
+                }
+                0 => {
+                    held.remove(&key);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// Starts a watcher for every readable keyboard not watched yet; a watcher drops
+// its path when the device goes away, so plugging it back in arms it again
+fn scan_keyboards(watched: &Arc<Mutex<HashSet<PathBuf>>>, combo: &Arc<Vec<Vec<Key>>>, combo_name: &Arc<str>) -> usize {
+    let mut started = 0;
+    for (path, device) in evdev::enumerate() {
+        if device.name() == Some(crate::scroll::WHEEL_DEVICE_NAME) || watched.lock().unwrap().contains(&path) {
+            continue;
+        }
+        let is_keyboard = device.supported_keys().is_some_and(|keys| {
+            keys.contains(Key::KEY_ENTER) && combo.iter().all(|part| part.iter().any(|k| keys.contains(*k)))
+        });
+        if !is_keyboard {
+            continue;
+        }
+        watched.lock().unwrap().insert(path.clone());
+        let (watched, combo, combo_name) = (Arc::clone(watched), Arc::clone(combo), Arc::clone(combo_name));
+        std::thread::spawn(move || {
+            watch_keyboard(device, combo, combo_name);
+            watched.lock().unwrap().remove(&path);
+        });
+        started += 1;
+    }
+    started
+}
+
+// Watches every readable keyboard for the kill switch combination. Reading
+// /dev/input needs the input group; without it the GUI and /pause still work.
+pub fn spawn_hotkey_watcher() {
+    let combo_name = std::env::var("ASTRA_KILL_COMBO").unwrap_or_else(|_| DEFAULT_COMBO.to_string());
+    if combo_name.trim().is_empty() || combo_name.trim() == "off" {
+        info!("⛔ Kill switch key combination disabled");
+        return;
+    }
+    let combo = match parse_combo(&combo_name) {
+        Ok(combo) => Arc::new(combo),
+        Err(e) => {
+            warn!("⛔ Invalid ASTRA_KILL_COMBO={:?}: {}", combo_name, e);
+            return;
+        }
+    };
+    let combo_name: Arc<str> = Arc::from(combo_name.trim());
+
+    let watched = Arc::new(Mutex::new(HashSet::new()));
+    let found = scan_keyboards(&watched, &combo, &combo_name);
+    if found == 0 {
+        info!("⛔ Kill switch combination unavailable: no readable keyboard in /dev/input (join the input group)");
+    } else {
+        info!("⛔ Kill switch: press {} to pause or resume remote input ({} keyboards)", combo_name, found);
+    }
+
+    std::thread::spawn(move || loop {
+        std::thread::sleep(RESCAN_INTERVAL);
+        let found = scan_keyboards(&watched, &combo, &combo_name);
+        if found > 0 {
+            info!("⛔ Kill switch now also watches {} newly connected keyboard(s)", found);
+        }
+    });
+}
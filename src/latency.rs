@@ -0,0 +1,240 @@
+// End-to-end timing for input actions, to tell network lag from injection lag
+//
+// Every request is stamped when it arrives. Input responses (/mouse, /click,
+// /scroll, /key, /voice, /batch) carry a `timing` object: when the server received
+// the request, how long it spent on it and how much of that was input injection,
+// plus the client's X-Astra-Client-Time header echoed back. A client subtracting
+// server_ms from its own round trip gets the network share. Clients may report
+// their previous round trip in X-Astra-Rtt-Ms so it is aggregated with the server
+// numbers. Percentiles per client are served at GET /latency (this machine only)
+// and the GUI gets a summary of the last second for each active client.
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub const CLIENT_TIME_HEADER: &str = "x-astra-client-time";
+pub const RTT_HEADER: &str = "x-astra-rtt-ms";
+// Samples kept per client for the percentiles
+const WINDOW: Duration = Duration::from_secs(300);
+const MAX_SAMPLES: usize = 1_000;
+const GUI_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Timing {
+    // Milliseconds since the Unix epoch, by the server's clock
+    pub received_at_ms: u64,
+    // From arrival until the response was built
+    pub server_ms: f64,
+    // Part of server_ms spent injecting input, waiting for the input device included
+    pub injection_ms: f64,
+    // X-Astra-Client-Time as sent, so clients need not keep their own bookkeeping
+    pub client_time: Option<f64>,
+}
+
+// Stamped on every request by the middleware, for handlers as an Extension<Received>
+#[derive(Debug, Clone)]
+pub struct Received {
+    at: SystemTime,
+    instant: Instant,
+    client: String,
+    client_time: Option<f64>,
+    rtt_ms: Option<f64>,
+}
+
+fn header_number(headers: &HeaderMap, name: &str) -> Option<f64> {
+    headers
+        .get(name)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite())
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl Received {
+    // The response timing for an action that spent `injection` injecting input;
+    // also records it as a sample for the client
+    pub fn finish(&self, injection: Duration) -> Timing {
+        let server = self.instant.elapsed();
+        record(
+            &self.client,
+            Sample {
+                at: Instant::now(),
+                server_ms: millis(server),
+                injection_ms: millis(injection),
+                rtt_ms: self.rtt_ms,
+            },
+        );
+        Timing {
+            received_at_ms: self.at.duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+            server_ms: millis(server),
+            injection_ms: millis(injection),
+            client_time: self.client_time,
+        }
+    }
+}
+
+pub async fn middleware(ConnectInfo(addr): ConnectInfo<SocketAddr>, mut request: Request, next: Next) -> Response {
+    let headers = request.headers();
+    let received = Received {
+        at: SystemTime::now(),
+        instant: Instant::now(),
+        client: crate::pairing::Caller::of(&request)
+            .map(|device| device.name.clone())
+            .unwrap_or_else(|| addr.ip().to_string()),
+        client_time: header_number(headers, CLIENT_TIME_HEADER),
+        rtt_ms: header_number(headers, RTT_HEADER).filter(|rtt| *rtt >= 0.0),
+    };
+    request.extensions_mut().insert(received);
+    next.run(request).await
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    at: Instant,
+    server_ms: f64,
+    injection_ms: f64,
+    rtt_ms: Option<f64>,
+}
+
+lazy_static::lazy_static! {
+    static ref SAMPLES: Mutex<HashMap<String, VecDeque<Sample>>> = Mutex::new(HashMap::new());
+}
+
+fn record(client: &str, sample: Sample) {
+    let mut samples = SAMPLES.lock().unwrap();
+    let history = samples.entry(client.to_string()).or_default();
+    if history.len() == MAX_SAMPLES {
+        history.pop_front();
+    }
+    history.push_back(sample);
+}
+
+// UDP pointer packets have no response; their timing only goes into the samples
+pub fn record_udp(device_name: &str, received: Instant, injection: Duration) {
+    record(
+        device_name,
+        Sample {
+            at: Instant::now(),
+            server_ms: millis(received.elapsed()),
+            injection_ms: millis(injection),
+            rtt_ms: None,
+        },
+    );
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Percentiles {
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+// Nearest rank; None without values
+fn percentiles(mut values: Vec<f64>) -> Option<Percentiles> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let rank = |p: f64| values[((p * values.len() as f64).ceil() as usize).clamp(1, values.len()) - 1];
+    Some(Percentiles {
+        p50: rank(0.50),
+        p95: rank(0.95),
+        p99: rank(0.99),
+    })
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ClientLatency {
+    // Device name, or the IP address of an unpaired client
+    pub client: String,
+    pub samples: usize,
+    pub server_ms: Option<Percentiles>,
+    pub injection_ms: Option<Percentiles>,
+    // Only for clients that send X-Astra-Rtt-Ms
+    pub rtt_ms: Option<Percentiles>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct LatencyResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub window_seconds: u64,
+    pub clients: Vec<ClientLatency>,
+}
+
+fn summarize(client: &str, samples: &[Sample]) -> ClientLatency {
+    ClientLatency {
+        client: client.to_string(),
+        samples: samples.len(),
+        server_ms: percentiles(samples.iter().map(|s| s.server_ms).collect()),
+        injection_ms: percentiles(samples.iter().map(|s| s.injection_ms).collect()),
+        rtt_ms: percentiles(samples.iter().filter_map(|s| s.rtt_ms).collect()),
+    }
+}
+
+// Samples newer than `age`, per client, dropping what has left the window
+fn recent(age: Duration) -> Vec<(String, Vec<Sample>)> {
+    let mut samples = SAMPLES.lock().unwrap();
+    samples.retain(|_, history| {
+        while history.front().is_some_and(|s| s.at.elapsed() > WINDOW) {
+            history.pop_front();
+        }
+        !history.is_empty()
+    });
+    let mut clients: Vec<(String, Vec<Sample>)> = samples
+        .iter()
+        .filter_map(|(client, history)| {
+            let recent: Vec<Sample> = history.iter().filter(|s| s.at.elapsed() <= age).copied().collect();
+            (!recent.is_empty()).then(|| (client.clone(), recent))
+        })
+        .collect();
+    clients.sort_by(|a, b| a.0.cmp(&b.0));
+    clients
+}
+
+pub fn report() -> LatencyResponse {
+    LatencyResponse {
+        status: "success".to_string(),
+        message: None,
+        window_seconds: WINDOW.as_secs(),
+        clients: recent(WINDOW)
+            .iter()
+            .map(|(client, samples)| summarize(client, samples))
+            .collect(),
+    }
+}
+
+// Sends the GUI the median of the last second for every client that sent input in it
+pub fn spawn_reporter() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(GUI_INTERVAL);
+        loop {
+            interval.tick().await;
+            for (client, samples) in recent(GUI_INTERVAL) {
+                let summary = summarize(&client, &samples);
+                crate::events::gui_emit(crate::events::GuiEvent::Latency {
+                    client,
+                    samples: summary.samples,
+                    server_ms: summary.server_ms.map_or(0.0, |p| p.p50),
+                    injection_ms: summary.injection_ms.map_or(0.0, |p| p.p50),
+                    rtt_ms: summary.rtt_ms.map(|p| p.p50),
+                });
+            }
+        }
+    });
+}
@@ -0,0 +1,3 @@
+// Code shared by the server, astra-gui and astra-ctl
+pub mod config;
+pub mod mdns;
@@ -1,89 +1,131 @@
 use axum::{
-    extract::Json,
-    http::{StatusCode, Method},
-    response::IntoResponse,
-    routing::{get, post},
+    body::Bytes,
+    extract::{rejection::BytesRejection, ConnectInfo, DefaultBodyLimit, Extension, Json, Path},
+    http::Method,
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
     Router,
 };
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::future::IntoFuture;
 use std::net::SocketAddr;
 use std::process::Command;
-use tower_http::cors::{CorsLayer, Any};
-use tracing::info;
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tracing::{error, info, warn};
+use error::{ApiError, ApiJson, ApiQuery};
+use astra_remote::{config, mdns};
 
-#[derive(Debug, Deserialize)]
+mod audit;
+mod batch;
+mod clipboard;
+mod error;
+mod events;
+mod input;
+mod killswitch;
+mod latency;
+mod media;
+mod metrics;
+mod openapi;
+mod pairing;
+mod presentation;
+mod ratelimit;
+mod scopes;
+mod screen;
+mod scroll;
+mod shutdown;
+mod udp;
+mod upload;
+mod volume;
+mod web;
+mod windows;
+
+const DEFAULT_PORT: u16 = 44828;
+// How long in-flight requests may take to finish once shutdown starts
+const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Deserialize, JsonSchema)]
 struct MouseMove {
     dx: f32,
     dy: f32,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 struct ScrollRequest {
     direction: String,
-    amount: Option<i32>,
+    // Wheel detents, fractional values allowed
+    amount: Option<f32>,
+    // Fling velocity in detents per second, continued as kinetic momentum
+    velocity: Option<f32>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 struct ClickRequest {
     #[serde(rename = "type")]
     click_type: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 struct KeyRequest {
     key: String,
     modifiers: Option<Vec<String>>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 struct ApiResponse {
     status: String,
     message: Option<String>,
+    // Only on input actions
+    timing: Option<latency::Timing>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 struct VoiceRequest {
     command: String,
 }
 
-async fn handle_voice(Json(payload): Json<VoiceRequest>) -> impl IntoResponse {
+async fn handle_voice(
+    Extension(received): Extension<latency::Received>,
+    Extension(client): Extension<ratelimit::Client>,
+    Extension(grant): Extension<scopes::Grant>,
+    ApiJson(payload): ApiJson<VoiceRequest>,
+) -> Result<Json<ApiResponse>, ApiError> {
     info!("🗣️  /voice endpoint hit. Payload: {:?}", payload);
-    match execute_voice_command(&payload.command) {
+    let command = payload.command.trim().to_lowercase();
+    if command.starts_with("open ") {
+        grant.require(scopes::Scope::Launch)?;
+        ratelimit::check(&client, ratelimit::RouteClass::Launch, 1)?;
+    } else if command.starts_with("type ") {
+        grant.require(scopes::Scope::Text)?;
+    } else if voice_window_request(&command).is_some() {
+        // The same scope as POST /windows
+        grant.require(scopes::Scope::Pointer)?;
+    }
+    // Carrying out the command counts as injection, whatever it involves
+    let started = std::time::Instant::now();
+    match execute_voice_command(&payload.command).await {
         Ok(msg) => {
             info!("🗣️  Voice command executed successfully: {}", msg);
-            (
-                StatusCode::OK,
-                Json(ApiResponse {
-                    status: "success".to_string(),
-                    message: Some(msg),
-                })
-            )
+            Ok(timed(msg, received.finish(started.elapsed())))
         },
         Err(e) => {
             info!("🗣️  Voice command failed: {}", e);
-            (
-                StatusCode::BAD_REQUEST,
-                Json(ApiResponse {
-                    status: "error".to_string(),
-                    message: Some(e),
-                })
-            )
+            Err(e)
         },
     }
 }
 
-fn execute_voice_command(cmd: &str) -> Result<String, String> {
+async fn execute_voice_command(cmd: &str) -> Result<String, ApiError> {
     let cmd = cmd.trim().to_lowercase();
     info!("🗣️  Parsing voice command: {}", cmd);
     // Simple command parsing, extend as needed
-    if cmd.starts_with("open ") {
-        let app = cmd[5..].trim();
+    if let Some(app) = cmd.strip_prefix("open ") {
+        let app = app.trim();
         info!("🗣️  Trying to open app: {}", app);
-        // Try to open app using xdg-open or known commands
-        let status = Command::new("sh")
-            .arg("-c")
-            .arg(format!("xdg-open {}", app))
-            .status();
+        // Passed as a single argument, never through a shell
+        let status = Command::new("xdg-open").arg(app).status();
         match status {
             Ok(status) if status.success() => {
                 info!("🗣️  Opened app: {}", app);
@@ -92,54 +134,114 @@ fn execute_voice_command(cmd: &str) -> Result<String, String> {
             Ok(status) => {
                 let msg = format!("Failed to open {} (exit code: {:?})", app, status.code());
                 info!("🗣️  {}", msg);
-                Err(msg)
+                Err(ApiError::NotFound(msg))
             },
             Err(e) => {
                 let msg = format!("Failed to run open command: {}", e);
                 info!("🗣️  {}", msg);
-                Err(msg)
+                Err(ApiError::BackendUnavailable(msg))
             }
         }
-    } else if cmd.starts_with("type ") {
-        let text = cmd[5..].trim();
+    } else if let Some(text) = cmd.strip_prefix("type ") {
+        let text = text.trim();
         info!("🗣️  Typing text: {}", text);
-        let mut enigo = ENIGO.lock().unwrap();
-        enigo.key_sequence(text);
+        metrics::injection("type", || input::lock().key_sequence(text));
         Ok(format!("Typed: {}", text))
-    } else if cmd == "play" || cmd == "pause" {
-        info!("🗣️  Sending play/pause (space)");
-        let mut enigo = ENIGO.lock().unwrap();
-        enigo.key_click(enigo::Key::Space);
-        Ok("Toggled play/pause".to_string())
-    } else if cmd == "next" {
-        info!("🗣️  Sending next track (F9)");
-        let mut enigo = ENIGO.lock().unwrap();
-        enigo.key_click(enigo::Key::F9);
-        Ok("Next track".to_string())
-    } else if cmd == "previous" || cmd == "prev" {
-        info!("🗣️  Sending previous track (F7)");
-        let mut enigo = ENIGO.lock().unwrap();
-        enigo.key_click(enigo::Key::F7);
-        Ok("Previous track".to_string())
-    } else if cmd == "volume up" {
-        info!("🗣️  Sending volume up");
-        let mut enigo = ENIGO.lock().unwrap();
-        enigo.key_click(enigo::Key::VolumeUp);
-        Ok("Volume up".to_string())
-    } else if cmd == "volume down" {
-        info!("🗣️  Sending volume down");
-        let mut enigo = ENIGO.lock().unwrap();
-        enigo.key_click(enigo::Key::VolumeDown);
-        Ok("Volume down".to_string())
-    } else if cmd == "mute" {
-        info!("🗣️  Sending mute");
-        let mut enigo = ENIGO.lock().unwrap();
-        enigo.key_click(enigo::Key::VolumeMute);
-        Ok("Muted".to_string())
+    } else if let Some((action, offset, verb)) = voice_media_action(&cmd) {
+        info!("🗣️  Media command: {:?}", action);
+        let request = media::MediaRequest {
+            action,
+            offset_seconds: offset,
+            player: None,
+        };
+        let controller = media::controller().await?;
+        let player = controller.control(&request).await?;
+        Ok(match player.now_playing.describe() {
+            Some(track) => format!("{}: {}", verb, track),
+            None => verb.to_string(),
+        })
+    } else if cmd == "what's playing" || cmd == "what is playing" || cmd == "now playing" {
+        let controller = media::controller().await?;
+        let player = controller.active_player().await?;
+        Ok(match player.now_playing.describe() {
+            Some(track) => format!("{} ({}): {}", player.identity.unwrap_or(player.id), player.status, track),
+            None => format!("{} is {}", player.identity.unwrap_or(player.id), player.status.to_lowercase()),
+        })
+    } else if let Some(request) = voice_volume_request(&cmd) {
+        info!("🗣️  Volume command: {:?}", request);
+        let status = tokio::task::spawn_blocking(move || volume::apply(volume::backend(), &request)).await??;
+        Ok(if status.muted {
+            format!("Volume {}% (muted)", status.volume)
+        } else {
+            format!("Volume {}%", status.volume)
+        })
+    } else if let Some((name, action, workspace)) = voice_window_request(&cmd) {
+        info!("🗣️  Window command: {:?} {:?}", action, name);
+        tokio::task::spawn_blocking(move || windows::act_by_name(name.as_deref(), action, workspace))
+            .await?
+            .map_err(ApiError::BackendUnavailable)
     } else {
-        let msg = format!("Unknown voice command: {}", cmd);
-        info!("🗣️  {}", msg);
-        Err(msg)
+        Err(ApiError::UnknownVoiceCommand(cmd))
+    }
+}
+
+// "volume up", "mute", "set volume to 40", "volume 40 percent", ...
+fn voice_volume_request(cmd: &str) -> Option<volume::VolumeRequest> {
+    let request = |volume, delta, muted| volume::VolumeRequest {
+        volume,
+        delta,
+        muted,
+        sink: None,
+    };
+    match cmd {
+        "volume up" | "louder" => return Some(request(None, Some(5), Some(false))),
+        "volume down" | "quieter" => return Some(request(None, Some(-5), None)),
+        "mute" => return Some(request(None, None, Some(true))),
+        "unmute" => return Some(request(None, None, Some(false))),
+        _ => {}
+    }
+    let rest = cmd.strip_prefix("set volume").or_else(|| cmd.strip_prefix("volume"))?;
+    let level = rest
+        .split_whitespace()
+        .find_map(|word| word.trim_end_matches('%').parse::<u32>().ok())?;
+    Some(request(Some(level), None, Some(false)))
+}
+
+// "switch to firefox", "close window", "move terminal to workspace 2", ...
+// A `None` name targets the focused window
+fn voice_window_request(cmd: &str) -> Option<(Option<String>, windows::WindowAction, Option<String>)> {
+    use windows::WindowAction;
+    match cmd {
+        "close window" => return Some((None, WindowAction::Close, None)),
+        "minimize window" | "minimise window" => return Some((None, WindowAction::Minimize, None)),
+        "maximize window" | "maximise window" => return Some((None, WindowAction::Maximize, None)),
+        _ => {}
+    }
+    if let Some(name) = cmd.strip_prefix("switch to ").or_else(|| cmd.strip_prefix("focus ")) {
+        return Some((Some(name.trim().to_string()), WindowAction::Focus, None));
+    }
+    let rest = cmd.strip_prefix("move ")?;
+    let (name, workspace) = rest.split_once(" to workspace ")?;
+    let name = match name.trim() {
+        "window" | "this window" => None,
+        name => Some(name.to_string()),
+    };
+    Some((name, WindowAction::MoveToWorkspace, Some(workspace.trim().to_string())))
+}
+
+// Maps voice phrases to MPRIS actions, with the verb used in the reply
+fn voice_media_action(cmd: &str) -> Option<(media::MediaAction, Option<f64>, &'static str)> {
+    use media::MediaAction;
+    match cmd {
+        "play" | "resume" => Some((MediaAction::Play, None, "Playing")),
+        "pause" => Some((MediaAction::Pause, None, "Paused")),
+        "play pause" | "toggle" => Some((MediaAction::PlayPause, None, "Toggled")),
+        "stop" => Some((MediaAction::Stop, None, "Stopped")),
+        "next" | "skip" => Some((MediaAction::Next, None, "Next track")),
+        "previous" | "prev" => Some((MediaAction::Previous, None, "Previous track")),
+        "forward" | "fast forward" => Some((MediaAction::Seek, Some(10.0), "Skipped ahead")),
+        "rewind" | "back" => Some((MediaAction::Seek, Some(-10.0), "Rewound")),
+        _ => None,
     }
 }
 
@@ -147,142 +249,683 @@ fn execute_voice_command(cmd: &str) -> Result<String, String> {
 async fn main() {
     // Initialize tracing
     tracing_subscriber::fmt::init();
+    input::install_panic_hook();
 
-    // Build CORS layer
+    // Build CORS layer; web pages never get to read or preflight the admin routes
     let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
+        .allow_origin(AllowOrigin::predicate(|_, request| !scopes::is_admin(request.uri.path())))
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::OPTIONS])
         .allow_headers(Any);
 
     // Build our application with routes
     let app = Router::new()
-        .route("/", get(health_check))
+        .merge(web::routes())
         .route("/ping", get(health_check))
+        .route("/openapi.json", get(|| async { Json(openapi::document()) }))
+        .route("/metrics", get(handle_metrics))
+        .route("/latency", get(handle_latency))
         .route("/mouse", post(handle_mouse))
         .route("/click", post(handle_click))
         .route("/scroll", post(handle_scroll))
         .route("/key", post(handle_key))
         .route("/voice", post(handle_voice))
+        .route("/media", get(handle_media_get).post(handle_media_post))
+        .route("/volume", get(handle_volume_get).post(handle_volume_post))
+        .route("/screen", get(handle_screen))
+        .route("/presentation", get(handle_presentation_get).post(handle_presentation_post))
+        .route("/windows", get(handle_windows_get).post(handle_windows_post))
+        .route("/batch", post(handle_batch))
+        .route("/input", get(handle_input_get))
+        .route("/reset-input", post(handle_reset_input))
+        .route("/pair", post(handle_pair))
+        .route("/udp/handshake", post(handle_udp_handshake))
+        .route("/clipboard", get(handle_clipboard_get).put(handle_clipboard_put))
+        .route("/events", get(events::handle_events))
+        .route("/upload", post(handle_upload_start))
+        .route(
+            "/upload/:id",
+            get(handle_upload_status)
+                .put(handle_upload_chunk)
+                .layer(DefaultBodyLimit::max(upload::MAX_CHUNK_BYTES)),
+        )
+        .route("/pause", post(handle_pause))
+        .route("/resume", post(handle_resume))
+        .route("/shutdown", post(handle_shutdown))
+        .route("/devices", get(handle_devices_get))
+        .route("/devices/:id", put(handle_device_put).delete(handle_device_delete))
+        .fallback(|| async { ApiError::NotFound("No such endpoint".to_string()) })
+        .layer(middleware::from_fn(ratelimit::middleware))
+        .layer(middleware::from_fn(killswitch::middleware))
+        .layer(middleware::from_fn(scopes::middleware))
+        .layer(middleware::from_fn(audit::middleware))
+        .layer(middleware::from_fn(metrics::middleware))
+        .layer(middleware::from_fn(latency::middleware))
+        .layer(middleware::from_fn(pairing::identify))
         .layer(cors);
 
-    // Bind to all interfaces on port 44828
-    let addr = SocketAddr::from(([0, 0, 0, 0], 44828));
+    // Bind to all interfaces, on the next free port if ASTRA_PORT_FALLBACK allows
+    let port = config::env_parse("ASTRA_PORT", DEFAULT_PORT);
+    let fallback = config::env_parse("ASTRA_PORT_FALLBACK", 0u16);
+    let listener = match bind_listener(port, fallback).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("❌ Cannot start: {}", e.message);
+            events::gui_emit(events::GuiEvent::StartupFailed {
+                code: e.code.to_string(),
+                port: e.port,
+                message: e.message,
+            });
+            std::process::exit(2);
+        }
+    };
+    let addr = listener.local_addr().expect("bound listener has an address");
     info!("🚀 Astra Remote Control Server starting on {}", addr);
+    info!("🔐 Pairing code: {}", pairing::PAIRING.lock().unwrap().code());
+    if scopes::pairing_required() {
+        info!("🔐 Pairing required: unpaired clients can only pair");
+    }
+    ratelimit::log_limits();
+    audit::start();
+    latency::spawn_reporter();
+
+    // Optional low-latency UDP pointer channel
+    if config::env_flag("ASTRA_UDP", false) {
+        let port = config::env_parse("ASTRA_UDP_PORT", udp::DEFAULT_PORT);
+        match udp::start(port).await {
+            Ok(channel) => {
+                udp::log_loopback_latency(&channel).await;
+                udp::install(channel);
+            }
+            Err(e) => info!("📡 UDP pointer channel disabled, bind to port {} failed: {}", port, e),
+        }
+    }
+
+    scroll::init();
+    clipboard::spawn_watcher();
+    killswitch::spawn_hotkey_watcher();
+
+    let advertisement = if config::env_flag("ASTRA_MDNS", true) {
+        match mdns::advertise(addr.port(), scopes::pairing_required()) {
+            Ok(advertisement) => Some(tokio::spawn(follow_pairing_state(advertisement))),
+            Err(e) => {
+                warn!("📣 mDNS advertisement unavailable: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    upload::load_state();
+    upload::spawn_sweeper();
+    tokio::spawn(shutdown::listen_for_signals());
+
     info!("Waiting for mobile client connection...");
 
-    // Start server
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    // Start server; on shutdown stop accepting and let in-flight requests finish
+    events::gui_emit(events::GuiEvent::Listening { port: addr.port() });
+    let server = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async {
+            shutdown::requested().await;
+        });
+    tokio::select! {
+        result = server.into_future() => {
+            if let Err(e) = result {
+                warn!("Server error: {}", e);
+            }
+        }
+        _ = async {
+            shutdown::requested().await;
+            tokio::time::sleep(SHUTDOWN_DRAIN_TIMEOUT).await;
+        } => warn!("👋 Requests still running after {}s, stopping anyway", SHUTDOWN_DRAIN_TIMEOUT.as_secs()),
+    }
+
+    // Never exit with keys held down, and keep unfinished uploads resumable
+    let advertisement = match advertisement {
+        Some(task) => task.await.ok(),
+        None => None,
+    };
+    tokio::task::spawn_blocking(move || {
+        if let Some(advertisement) = advertisement {
+            advertisement.withdraw();
+        }
+        input::release_all("server shutting down");
+        match upload::save_state() {
+            Ok(0) => {}
+            Ok(count) => info!("📁 Saved {} unfinished uploads", count),
+            Err(e) => warn!("📁 Failed to save unfinished uploads: {}", e),
+        }
+    })
+    .await
+    .ok();
+    info!("👋 Astra server stopped");
+}
+
+// Why the listener could not be bound, e.g. {code: "port_in_use", port: 44828}
+struct StartupError {
+    code: &'static str,
+    port: u16,
+    message: String,
+}
+
+impl StartupError {
+    fn from_io(port: u16, e: &std::io::Error) -> Self {
+        let (code, message) = match e.kind() {
+            std::io::ErrorKind::AddrInUse => ("port_in_use", format!("Port {} is already in use", port)),
+            std::io::ErrorKind::PermissionDenied => ("permission_denied", format!("No permission to listen on port {}", port)),
+            _ => ("bind_failed", format!("Cannot listen on port {}: {}", port, e)),
+        };
+        Self { code, port, message }
+    }
+}
+
+// Tries `port`, then up to `fallback` following ports if it is taken
+async fn bind_listener(port: u16, fallback: u16) -> Result<tokio::net::TcpListener, StartupError> {
+    let last = port.saturating_add(fallback);
+    for candidate in port..=last {
+        match tokio::net::TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], candidate))).await {
+            Ok(listener) => {
+                if candidate != port {
+                    warn!("🔌 Port {} is in use, listening on {} instead", port, candidate);
+                }
+                return Ok(listener);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse && candidate < last => continue,
+            Err(e) => {
+                let mut error = StartupError::from_io(candidate, &e);
+                if error.code == "port_in_use" && fallback > 0 {
+                    error.port = port;
+                    error.message = format!("Ports {} to {} are all in use", port, last);
+                }
+                return Err(error);
+            }
+        }
+    }
+    unreachable!("the port range is never empty")
+}
+
+// Keeps the mDNS pairing record in step with the paired devices until shutdown,
+// then hands the advertisement back to be withdrawn
+async fn follow_pairing_state(mut advertisement: mdns::Advertisement) -> mdns::Advertisement {
+    let mut pairing_required = scopes::watch_pairing_required();
+    loop {
+        tokio::select! {
+            changed = pairing_required.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                let required = *pairing_required.borrow_and_update();
+                advertisement.set_pairing_required(required);
+            }
+            _ = shutdown::requested() => break,
+        }
+    }
+    advertisement
 }
 
 async fn health_check() -> impl IntoResponse {
     Json(ApiResponse {
         status: "ok".to_string(),
         message: Some("Astra Gesture Control Server - Rust Edition".to_string()),
+        timing: None,
+    })
+}
+
+fn success(message: impl Into<String>) -> Json<ApiResponse> {
+    Json(ApiResponse {
+        status: "success".to_string(),
+        message: Some(message.into()),
+        timing: None,
+    })
+}
+
+// Success for an input action, with its timing
+fn timed(message: impl Into<String>, timing: latency::Timing) -> Json<ApiResponse> {
+    Json(ApiResponse {
+        status: "success".to_string(),
+        message: Some(message.into()),
+        timing: Some(timing),
     })
 }
 
-async fn handle_mouse(Json(payload): Json<MouseMove>) -> impl IntoResponse {
+async fn handle_mouse(
+    Extension(received): Extension<latency::Received>,
+    ApiJson(payload): ApiJson<MouseMove>,
+) -> Result<Json<ApiResponse>, ApiError> {
     info!("🖱️  Mouse move: dx={}, dy={}", payload.dx, payload.dy);
-    
-    match move_mouse(payload.dx as i32, payload.dy as i32) {
-        Ok(_) => (
-            StatusCode::OK,
-            Json(ApiResponse {
-                status: "success".to_string(),
-                message: Some("Mouse moved".to_string()),
-            }),
-        ),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse {
-                status: "error".to_string(),
-                message: Some(e),
-            }),
-        ),
-    }
+    scroll::cancel_momentum();
+
+    let (result, injection) =
+        metrics::injection("mouse", || move_mouse(&mut input::lock(), payload.dx as i32, payload.dy as i32));
+    result?;
+    Ok(timed("Mouse moved", received.finish(injection)))
 }
 
-async fn handle_click(Json(payload): Json<ClickRequest>) -> impl IntoResponse {
+async fn handle_click(
+    Extension(received): Extension<latency::Received>,
+    Extension(client): Extension<ratelimit::Client>,
+    ApiJson(payload): ApiJson<ClickRequest>,
+) -> Result<Json<ApiResponse>, ApiError> {
     info!("🖱️  Click: type={}", payload.click_type);
-    
-    match execute_click(&payload.click_type) {
-        Ok(_) => (
-            StatusCode::OK,
-            Json(ApiResponse {
-                status: "success".to_string(),
-                message: Some(format!("{} performed", payload.click_type)),
-            }),
-        ),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse {
-                status: "error".to_string(),
-                message: Some(e),
-            }),
-        ),
+    scroll::cancel_momentum();
+
+    let (result, injection) = metrics::injection("click", || execute_click(&mut input::lock_as(client.key()), &payload.click_type));
+    result?;
+    Ok(timed(format!("{} performed", payload.click_type), received.finish(injection)))
+}
+
+async fn handle_scroll(
+    Extension(received): Extension<latency::Received>,
+    ApiJson(payload): ApiJson<ScrollRequest>,
+) -> Result<Json<ApiResponse>, ApiError> {
+    info!(
+        "📜 Scroll: direction={}, amount={:?}, velocity={:?}",
+        payload.direction, payload.amount, payload.velocity
+    );
+
+    let (result, injection) = metrics::injection("scroll", || {
+        execute_scroll(&mut input::lock(), &payload.direction, payload.amount, payload.velocity)
+    });
+    result?;
+    Ok(timed("Scrolled", received.finish(injection)))
+}
+
+async fn handle_key(
+    Extension(received): Extension<latency::Received>,
+    Extension(client): Extension<ratelimit::Client>,
+    Extension(grant): Extension<scopes::Grant>,
+    ApiJson(payload): ApiJson<KeyRequest>,
+) -> Result<Json<ApiResponse>, ApiError> {
+    info!("⌨️  Key: key={}, modifiers={:?}", payload.key, payload.modifiers);
+    // Single characters are typing, which keyboard alone does not allow
+    if scopes::is_typing(&payload.key, payload.modifiers.as_deref().unwrap_or_default()) {
+        grant.require(scopes::Scope::Text)?;
     }
+
+    let (result, injection) =
+        metrics::injection("key", || execute_key(&mut input::lock_as(client.key()), &payload.key, payload.modifiers));
+    result?;
+    Ok(timed(format!("Key '{}' pressed", payload.key), received.finish(injection)))
 }
 
-async fn handle_scroll(Json(payload): Json<ScrollRequest>) -> impl IntoResponse {
-    info!("📜 Scroll: direction={}, amount={:?}", payload.direction, payload.amount);
-    
-    match execute_scroll(&payload.direction, payload.amount.unwrap_or(1)) {
-        Ok(_) => (
-            StatusCode::OK,
-            Json(ApiResponse {
-                status: "success".to_string(),
-                message: Some("Scrolled".to_string()),
-            }),
-        ),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse {
-                status: "error".to_string(),
-                message: Some(e),
-            }),
-        ),
+async fn handle_batch(
+    Extension(received): Extension<latency::Received>,
+    Extension(client): Extension<ratelimit::Client>,
+    Extension(grant): Extension<scopes::Grant>,
+    ApiJson(payload): ApiJson<batch::BatchRequest>,
+) -> Result<Json<batch::BatchResponse>, ApiError> {
+    info!(
+        "📦 Batch: {} steps, stop_on_error={}",
+        payload.steps.len(),
+        payload.stop_on_error
+    );
+
+    batch::validate(&payload).map_err(ApiError::InvalidRequest)?;
+    // All or nothing, like the other checks before execution
+    for scope in payload.steps.iter().filter_map(batch::BatchStep::scope) {
+        grant.require(scope)?;
     }
+    ratelimit::check_all(&client, &batch::charges(&payload))?;
+    let (result, injection) = tokio::task::spawn_blocking(move || batch::execute(payload, client.key())).await?;
+    let mut response = result?;
+    response.timing = Some(received.finish(injection));
+    Ok(Json(response))
 }
 
-async fn handle_key(Json(payload): Json<KeyRequest>) -> impl IntoResponse {
-    info!("⌨️  Key: key={}, modifiers={:?}", payload.key, payload.modifiers);
-    
-    match execute_key(&payload.key, payload.modifiers) {
-        Ok(_) => (
-            StatusCode::OK,
-            Json(ApiResponse {
-                status: "success".to_string(),
-                message: Some(format!("Key '{}' pressed", payload.key)),
-            }),
-        ),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse {
-                status: "error".to_string(),
-                message: Some(e),
-            }),
-        ),
+async fn handle_input_get() -> Json<input::InputResponse> {
+    Json(input::lock().status(None))
+}
+
+// Releases every key and button the server still holds, e.g. after a client crashed mid-drag
+async fn handle_reset_input() -> Result<Json<input::InputResponse>, ApiError> {
+    let response = tokio::task::spawn_blocking(|| {
+        let mut input = input::lock();
+        let released = input.release_all();
+        info!("🎮 /reset-input released {:?}", released);
+        let message = if released.is_empty() {
+            "Nothing was held".to_string()
+        } else {
+            format!("Released {}", released.join(", "))
+        };
+        input.status(Some(message))
+    })
+    .await?;
+    Ok(Json(response))
+}
+
+async fn handle_pair(ApiJson(payload): ApiJson<pairing::PairRequest>) -> Result<Json<pairing::PairResponse>, ApiError> {
+    info!("🔐 Pairing request from '{}'", payload.name.as_deref().unwrap_or("unnamed"));
+
+    let device = pairing::PAIRING
+        .lock()
+        .unwrap()
+        .pair(&payload.code, payload.name)
+        .map_err(ApiError::PairingFailed)?;
+    Ok(Json(pairing::PairResponse {
+        status: "success".to_string(),
+        message: Some(format!("Paired as '{}'", device.name)),
+        device_id: Some(device.id),
+        token: Some(device.token),
+    }))
+}
+
+// Admin routes are for the desktop itself (the GUI), never for remote clients
+fn require_local(addr: SocketAddr) -> Result<(), ApiError> {
+    if addr.ip().is_loopback() {
+        Ok(())
+    } else {
+        Err(ApiError::Forbidden("Only available from this machine".to_string()))
+    }
+}
+
+async fn handle_resume(ConnectInfo(addr): ConnectInfo<SocketAddr>) -> Result<Json<ApiResponse>, ApiError> {
+    require_local(addr)?;
+    killswitch::resume();
+    Ok(success("Remote input resumed"))
+}
+
+async fn handle_shutdown(ConnectInfo(addr): ConnectInfo<SocketAddr>) -> Result<Json<ApiResponse>, ApiError> {
+    require_local(addr)?;
+    shutdown::request("requested from the desktop");
+    Ok(success("Shutting down"))
+}
+
+async fn handle_pause(ConnectInfo(addr): ConnectInfo<SocketAddr>) -> Result<Json<ApiResponse>, ApiError> {
+    require_local(addr)?;
+    killswitch::pause("paused from the desktop");
+    Ok(success("Remote input paused"))
+}
+
+async fn handle_metrics(ConnectInfo(addr): ConnectInfo<SocketAddr>) -> Result<Response, ApiError> {
+    match metrics::access() {
+        metrics::Access::Off => {
+            return Err(ApiError::FeatureDisabled("Metrics are disabled (set ASTRA_METRICS=local or on)".to_string()))
+        }
+        metrics::Access::Local => require_local(addr)?,
+        metrics::Access::Public => {}
+    }
+    let content_type = [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")];
+    Ok((content_type, metrics::render()).into_response())
+}
+
+async fn handle_latency(ConnectInfo(addr): ConnectInfo<SocketAddr>) -> Result<Json<latency::LatencyResponse>, ApiError> {
+    require_local(addr)?;
+    Ok(Json(latency::report()))
+}
+
+async fn handle_devices_get(ConnectInfo(addr): ConnectInfo<SocketAddr>) -> Result<Json<pairing::DevicesResponse>, ApiError> {
+    require_local(addr)?;
+    Ok(Json(pairing::DevicesResponse {
+        status: "success".to_string(),
+        message: None,
+        pairing_required: scopes::pairing_required(),
+        devices: pairing::PAIRING.lock().unwrap().devices(),
+    }))
+}
+
+async fn handle_device_put(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(id): Path<String>,
+    ApiJson(update): ApiJson<pairing::DeviceUpdate>,
+) -> Result<Json<pairing::DevicesResponse>, ApiError> {
+    require_local(addr)?;
+    let mut store = pairing::PAIRING.lock().unwrap();
+    if store.find(&id).is_none() {
+        return Err(ApiError::NotFound(format!("No paired device {}", id)));
+    }
+    let device = store.update(&id, update).map_err(ApiError::Internal)?;
+    Ok(Json(pairing::DevicesResponse {
+        status: "success".to_string(),
+        message: Some(format!("Updated '{}'", device.name)),
+        pairing_required: scopes::pairing_required_with(&store),
+        devices: store.devices(),
+    }))
+}
+
+async fn handle_device_delete(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(id): Path<String>,
+) -> Result<Json<pairing::DevicesResponse>, ApiError> {
+    require_local(addr)?;
+    let mut store = pairing::PAIRING.lock().unwrap();
+    if store.find(&id).is_none() {
+        return Err(ApiError::NotFound(format!("No paired device {}", id)));
     }
+    let device = store.remove(&id).map_err(ApiError::Internal)?;
+    Ok(Json(pairing::DevicesResponse {
+        status: "success".to_string(),
+        message: Some(format!("Unpaired '{}'", device.name)),
+        pairing_required: scopes::pairing_required_with(&store),
+        devices: store.devices(),
+    }))
+}
+
+// The paired device making the request, or 401
+fn require_device(caller: pairing::Caller) -> Result<pairing::Device, ApiError> {
+    caller.0.ok_or(ApiError::Unauthorized)
+}
+
+async fn handle_udp_handshake(caller: pairing::Caller) -> Result<Json<udp::HandshakeResponse>, ApiError> {
+    let device = require_device(caller)?;
+    let channel = udp::channel()
+        .ok_or_else(|| ApiError::FeatureDisabled("UDP pointer channel is disabled (set ASTRA_UDP=1)".to_string()))?;
+
+    let (session_id, key) = channel.open_session(&device.id, &device.name);
+    info!("📡 UDP session {} opened for '{}'", session_id, device.name);
+    Ok(Json(udp::HandshakeResponse {
+        status: "success".to_string(),
+        message: Some("UDP session established".to_string()),
+        port: Some(channel.port()),
+        session_id: Some(session_id),
+        key: Some(key),
+    }))
+}
+
+async fn handle_clipboard_get(
+    caller: pairing::Caller,
+    ApiQuery(query): ApiQuery<clipboard::ClipboardQuery>,
+) -> Result<Json<clipboard::ClipboardResponse>, ApiError> {
+    let device = require_device(caller)?;
+    info!("📋 Clipboard read by '{}' ({:?})", device.name, query.selection);
+
+    let response = tokio::task::spawn_blocking(move || clipboard::read(query.selection))
+        .await?
+        .map_err(ApiError::BackendUnavailable)?;
+    Ok(Json(response))
+}
+
+async fn handle_clipboard_put(
+    caller: pairing::Caller,
+    ApiJson(payload): ApiJson<clipboard::ClipboardPut>,
+) -> Result<Json<clipboard::ClipboardResponse>, ApiError> {
+    let device = require_device(caller)?;
+    info!("📋 Clipboard write by '{}' ({:?})", device.name, payload.selection);
+
+    let message = tokio::task::spawn_blocking(move || clipboard::write(payload))
+        .await?
+        .map_err(ApiError::InvalidRequest)?;
+    Ok(Json(clipboard::ClipboardResponse {
+        status: "success".to_string(),
+        message: Some(message),
+        ..Default::default()
+    }))
+}
+
+async fn handle_upload_start(
+    caller: pairing::Caller,
+    ApiJson(payload): ApiJson<upload::UploadInit>,
+) -> Result<Json<upload::UploadResponse>, ApiError> {
+    let device = require_device(caller)?;
+    Ok(Json(upload::start(&device, payload)?))
+}
+
+async fn handle_upload_status(caller: pairing::Caller, Path(id): Path<String>) -> Result<Json<upload::UploadResponse>, ApiError> {
+    let device = require_device(caller)?;
+    Ok(Json(upload::status(&device, &id)?))
+}
+
+async fn handle_upload_chunk(
+    caller: pairing::Caller,
+    Path(id): Path<String>,
+    ApiQuery(query): ApiQuery<upload::ChunkQuery>,
+    body: Result<Bytes, BytesRejection>,
+) -> Result<Json<upload::UploadResponse>, ApiError> {
+    let device = require_device(caller)?;
+    let body = body?;
+    let response = tokio::task::spawn_blocking(move || upload::write_chunk(&device, &id, query.offset, &body)).await??;
+    Ok(Json(response))
+}
+
+async fn handle_media_get() -> Result<Json<media::MediaResponse>, ApiError> {
+    let controller = media::controller().await?;
+    let players = controller.players().await?;
+    Ok(Json(media::MediaResponse {
+        status: "success".to_string(),
+        message: None,
+        active: players.first().cloned(),
+        players,
+    }))
+}
+
+async fn handle_media_post(ApiJson(payload): ApiJson<media::MediaRequest>) -> Result<Json<media::MediaResponse>, ApiError> {
+    info!("🎵 Media: {:?}", payload);
+    let controller = media::controller().await?;
+    let player = controller.control(&payload).await?;
+    Ok(Json(media::MediaResponse {
+        status: "success".to_string(),
+        message: player.now_playing.describe(),
+        active: Some(player),
+        players: Vec::new(),
+    }))
+}
+
+fn volume_response((status, sinks): (volume::VolumeStatus, Vec<volume::Sink>)) -> Json<volume::VolumeResponse> {
+    Json(volume::VolumeResponse {
+        status: "success".to_string(),
+        message: None,
+        volume: Some(status.volume),
+        muted: Some(status.muted),
+        sink: Some(status.sink),
+        sinks,
+    })
+}
+
+async fn handle_volume_get() -> Result<Json<volume::VolumeResponse>, ApiError> {
+    let result = tokio::task::spawn_blocking(|| {
+        let backend = volume::backend();
+        Ok::<_, volume::VolumeError>((backend.status()?, backend.sinks()?))
+    })
+    .await??;
+    Ok(volume_response(result))
+}
+
+async fn handle_volume_post(ApiJson(payload): ApiJson<volume::VolumeRequest>) -> Result<Json<volume::VolumeResponse>, ApiError> {
+    info!("🔊 Volume: {:?}", payload);
+    let result = tokio::task::spawn_blocking(move || {
+        let backend = volume::backend();
+        Ok::<_, volume::VolumeError>((volume::apply(backend, &payload)?, backend.sinks()?))
+    })
+    .await??;
+    Ok(volume_response(result))
+}
+
+async fn handle_presentation_get() -> impl IntoResponse {
+    let app = tokio::task::spawn_blocking(presentation::focused_app).await.ok();
+    Json(presentation::status(app))
+}
+
+async fn handle_presentation_post(
+    Extension(client): Extension<ratelimit::Client>,
+    ApiJson(payload): ApiJson<presentation::PresentationRequest>,
+) -> Result<Json<presentation::PresentationResponse>, ApiError> {
+    scroll::cancel_momentum();
+    let response = tokio::task::spawn_blocking(move || presentation::execute(&payload, client.key()))
+        .await?
+        .map_err(ApiError::InvalidRequest)?;
+    Ok(Json(response))
+}
+
+fn windows_response((backend, windows): (&'static str, Vec<windows::WindowInfo>)) -> Json<windows::WindowsResponse> {
+    Json(windows::WindowsResponse {
+        status: "success".to_string(),
+        message: None,
+        backend: Some(backend.to_string()),
+        windows,
+    })
+}
+
+async fn handle_windows_get() -> Result<Json<windows::WindowsResponse>, ApiError> {
+    let result = tokio::task::spawn_blocking(|| {
+        let backend = windows::backend()?;
+        Ok::<_, String>((backend.name(), backend.list()?))
+    })
+    .await?;
+    Ok(windows_response(result.map_err(ApiError::BackendUnavailable)?))
+}
+
+async fn handle_windows_post(ApiJson(payload): ApiJson<windows::WindowRequest>) -> Result<Json<windows::WindowsResponse>, ApiError> {
+    info!("🪟 Window: {:?}", payload);
+    let result = tokio::task::spawn_blocking(move || {
+        let backend = windows::backend().map_err(ApiError::BackendUnavailable)?;
+        backend.validate(&payload).map_err(ApiError::InvalidRequest)?;
+        let windows = backend.list().map_err(ApiError::BackendUnavailable)?;
+        if !windows.iter().any(|w| w.id == payload.id) {
+            return Err(ApiError::NotFound(format!("No window {}", payload.id)));
+        }
+        backend.act(&payload).map_err(ApiError::BackendUnavailable)?;
+        Ok::<_, ApiError>((backend.name(), backend.list().map_err(ApiError::BackendUnavailable)?))
+    })
+    .await?;
+    Ok(windows_response(result?))
 }
 
-// Input control functions using ydotool (Wayland compatible)
-use enigo::*;
-use std::sync::Mutex;
+async fn handle_screen(
+    caller: pairing::Caller,
+    ApiQuery(query): ApiQuery<screen::ScreenQuery>,
+) -> Result<Response, ApiError> {
+    if !screen::enabled() {
+        return Err(ApiError::FeatureDisabled("Screen preview is disabled (set ASTRA_SCREEN=1)".to_string()));
+    }
+    // NaN and infinity parse as f32 but make no frame interval
+    if !query.fps.is_finite() {
+        return Err(ApiError::InvalidRequest(format!("fps must be a finite number, got {}", query.fps)));
+    }
+    let device = require_device(caller)?;
+    info!("🖥️  Screen {} requested by '{}': {:?}", if query.stream { "stream" } else { "frame" }, device.name, query);
 
-lazy_static::lazy_static! {
-    static ref ENIGO: Mutex<Enigo> = Mutex::new(Enigo::new());
+    if query.stream {
+        let body = tokio::task::spawn_blocking(move || screen::stream(query))
+            .await?
+            .map_err(ApiError::BackendUnavailable)?;
+        Ok((
+            [(
+                axum::http::header::CONTENT_TYPE,
+                format!("multipart/x-mixed-replace; boundary={}", screen::BOUNDARY),
+            )],
+            body,
+        )
+            .into_response())
+    } else {
+        let (data, content_type) = tokio::task::spawn_blocking(move || screen::snapshot(&query))
+            .await?
+            .map_err(ApiError::BackendUnavailable)?;
+        Ok(([(axum::http::header::CONTENT_TYPE, content_type)], data).into_response())
+    }
 }
 
-fn move_mouse(dx: i32, dy: i32) -> Result<(), String> {
+// Input control functions
+use enigo::MouseButton;
+use input::Input;
+
+// The functions below take the locked Input so that callers (e.g. /batch) can
+// hold the input lock across several actions
+fn move_mouse(enigo: &mut Input, dx: i32, dy: i32) -> Result<(), ApiError> {
     // Move mouse relative to current position
-    let mut enigo = ENIGO.lock().unwrap();
     enigo.mouse_move_relative(dx, dy);
     Ok(())
 }
 
-fn execute_click(click_type: &str) -> Result<(), String> {
-    let mut enigo = ENIGO.lock().unwrap();
+fn execute_click(enigo: &mut Input, click_type: &str) -> Result<(), ApiError> {
     match click_type {
         "left" | "left_click" => enigo.mouse_click(MouseButton::Left),
         "right" | "right_click" => enigo.mouse_click(MouseButton::Right),
@@ -291,47 +934,59 @@ fn execute_click(click_type: &str) -> Result<(), String> {
             std::thread::sleep(std::time::Duration::from_millis(50));
             enigo.mouse_click(MouseButton::Left);
         }
-        _ => return Err(format!("Unknown click type: {}", click_type)),
+        _ => return Err(ApiError::UnknownClickType(click_type.to_string())),
     }
     Ok(())
 }
 
-fn execute_scroll(direction: &str, amount: i32) -> Result<(), String> {
-    let mut enigo = ENIGO.lock().unwrap();
-    let scroll_amount = amount * 10; // scale for visibility
-    match direction {
-        "up" => enigo.mouse_scroll_y(scroll_amount),
-        "down" => enigo.mouse_scroll_y(-scroll_amount),
-        "left" => enigo.mouse_scroll_x(-scroll_amount),
-        "right" => enigo.mouse_scroll_x(scroll_amount),
-        _ => return Err(format!("Unknown scroll direction: {}", direction)),
+fn execute_scroll(
+    enigo: &mut Input,
+    direction: &str,
+    amount: Option<f32>,
+    velocity: Option<f32>,
+) -> Result<(), ApiError> {
+    // A new touch always stops a running fling
+    scroll::cancel_momentum();
+    if direction == "stop" {
+        return Ok(());
+    }
+
+    let (axis, sign) = scroll::parse_direction(direction)
+        .ok_or_else(|| ApiError::UnknownScrollDirection(direction.to_string()))?;
+    // Without a fling, a bare request scrolls one detent
+    let default_amount = if velocity.is_some() { 0.0 } else { 1.0 };
+    scroll::scroll_with(enigo, axis, sign * amount.unwrap_or(default_amount)).map_err(ApiError::BackendUnavailable)?;
+    if let Some(velocity) = velocity {
+        scroll::start_momentum(axis, sign * velocity.abs());
     }
     Ok(())
 }
 
-fn execute_key(key_name: &str, modifiers: Option<Vec<String>>) -> Result<(), String> {
-    let mut enigo = ENIGO.lock().unwrap();
+fn execute_key(enigo: &mut Input, key_name: &str, modifiers: Option<Vec<String>>) -> Result<(), ApiError> {
+    // Resolve everything first so an unknown name presses nothing. Single
+    // characters without a key name (punctuation, accents) are typed as text.
+    let modifier_keys = modifiers
+        .iter()
+        .flatten()
+        .map(|m| str_to_enigo_key(m).ok_or_else(|| ApiError::UnknownKey(m.clone())))
+        .collect::<Result<Vec<_>, _>>()?;
+    let key = str_to_enigo_key(key_name);
+    if key.is_none() && key_name.chars().count() != 1 {
+        return Err(ApiError::UnknownKey(key_name.to_string()));
+    }
+
     // Press modifiers
-    if let Some(mods) = &modifiers {
-        for modifier in mods {
-            if let Some(key) = str_to_enigo_key(modifier) {
-                enigo.key_down(key);
-            }
-        }
+    for modifier in &modifier_keys {
+        enigo.key_down(*modifier);
     }
     // Press main key
-    if let Some(key) = str_to_enigo_key(key_name) {
-        enigo.key_click(key);
-    } else {
-        enigo.key_sequence(key_name);
+    match key {
+        Some(key) => enigo.key_click(key),
+        None => enigo.key_sequence(key_name),
     }
     // Release modifiers
-    if let Some(mods) = &modifiers {
-        for modifier in mods {
-            if let Some(key) = str_to_enigo_key(modifier) {
-                enigo.key_up(key);
-            }
-        }
+    for modifier in modifier_keys.iter().rev() {
+        enigo.key_up(*modifier);
     }
     Ok(())
 }
@@ -356,6 +1011,8 @@ fn str_to_enigo_key(s: &str) -> Option<enigo::Key> {
         "right" => Some(Key::RightArrow),
         "pageup" | "page_up" => Some(Key::PageUp),
         "pagedown" | "page_down" => Some(Key::PageDown),
+        "home" => Some(Key::Home),
+        "end" => Some(Key::End),
         // Function keys
         "f1" => Some(Key::F1), "f2" => Some(Key::F2), "f3" => Some(Key::F3), "f4" => Some(Key::F4),
         "f5" => Some(Key::F5), "f6" => Some(Key::F6), "f7" => Some(Key::F7), "f8" => Some(Key::F8),
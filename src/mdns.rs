@@ -0,0 +1,203 @@
+// Service discovery: the server advertises itself as _astra._tcp over mDNS, so
+// clients can find it without scanning the QR code or typing the address
+//
+// TXT records: version (server version), hostname, pairing ("required" or
+// "optional") and tls_fp (SHA-256 certificate fingerprint, only present when the
+// server serves TLS; it currently serves plain HTTP, so the record is left out).
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+pub const SERVICE_TYPE: &str = "_astra._tcp.local.";
+// How long to wait for the goodbye packet when withdrawing
+const WITHDRAW_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|name| name.trim().to_string())
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "astra".to_string())
+}
+
+// mDNS host names are DNS labels under .local.
+fn host_label(hostname: &str) -> String {
+    let label: String = hostname
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    label.trim_matches('-').to_string()
+}
+
+pub struct Advertisement {
+    daemon: ServiceDaemon,
+    fullname: String,
+    port: u16,
+    pairing_required: bool,
+}
+
+fn service_info(port: u16, pairing_required: bool) -> Result<ServiceInfo, String> {
+    let hostname = hostname();
+    let host = format!("{}.local.", host_label(&hostname));
+    let pairing = if pairing_required { "required" } else { "optional" };
+    let properties = [
+        ("version", env!("CARGO_PKG_VERSION")),
+        ("hostname", hostname.as_str()),
+        ("pairing", pairing),
+    ];
+    Ok(ServiceInfo::new(SERVICE_TYPE, &hostname, &host, "", port, &properties[..])
+        .map_err(|e| e.to_string())?
+        .enable_addr_auto())
+}
+
+// Announces the server on every interface; addresses follow interface changes
+pub fn advertise(port: u16, pairing_required: bool) -> Result<Advertisement, String> {
+    let service = service_info(port, pairing_required)?;
+    let fullname = service.get_fullname().to_string();
+
+    let daemon = ServiceDaemon::new().map_err(|e| e.to_string())?;
+    daemon.register(service).map_err(|e| e.to_string())?;
+    info!("📣 Advertising {} on port {} over mDNS", fullname, port);
+    Ok(Advertisement {
+        daemon,
+        fullname,
+        port,
+        pairing_required,
+    })
+}
+
+impl Advertisement {
+    // Re-announces the service when the pairing TXT record would change, so clients
+    // browsing later see whether they need to pair
+    pub fn set_pairing_required(&mut self, pairing_required: bool) {
+        if pairing_required == self.pairing_required {
+            return;
+        }
+        let registered = service_info(self.port, pairing_required)
+            .and_then(|service| self.daemon.register(service).map_err(|e| e.to_string()));
+        match registered {
+            Ok(()) => {
+                self.pairing_required = pairing_required;
+                let pairing = if pairing_required { "required" } else { "optional" };
+                info!("📣 mDNS advertisement updated: pairing {}", pairing);
+            }
+            Err(e) => warn!("📣 Failed to update mDNS advertisement: {}", e),
+        }
+    }
+
+    // Sends a goodbye so browsers drop the server right away instead of at TTL expiry
+    pub fn withdraw(self) {
+        match self.daemon.unregister(&self.fullname) {
+            Ok(status) => {
+                let _ = status.recv_timeout(WITHDRAW_TIMEOUT);
+            }
+            Err(e) => warn!("📣 Failed to withdraw mDNS advertisement: {}", e),
+        }
+        let _ = self.daemon.shutdown();
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Server {
+    pub name: String,
+    pub hostname: String,
+    pub addresses: Vec<IpAddr>,
+    pub port: u16,
+    pub version: Option<String>,
+    pub pairing: Option<String>,
+    pub tls_fp: Option<String>,
+}
+
+impl Server {
+    // IPv4 first: link-local IPv6 addresses need a scope to be usable in a URL
+    pub fn url(&self) -> Option<String> {
+        let address = self
+            .addresses
+            .iter()
+            .find(|a| a.is_ipv4())
+            .or_else(|| self.addresses.first())?;
+        Some(match address {
+            IpAddr::V4(ip) => format!("http://{}:{}", ip, self.port),
+            IpAddr::V6(ip) => format!("http://[{}]:{}", ip, self.port),
+        })
+    }
+}
+
+// Lists the servers that answer within `timeout`; blocks for the whole timeout
+pub fn browse(timeout: Duration) -> Result<Vec<Server>, String> {
+    let daemon = ServiceDaemon::new().map_err(|e| e.to_string())?;
+    let events = daemon.browse(SERVICE_TYPE).map_err(|e| e.to_string())?;
+    let deadline = Instant::now() + timeout;
+    let mut servers = BTreeMap::new();
+
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        match events.recv_timeout(left) {
+            Ok(ServiceEvent::ServiceResolved(info)) => {
+                let text = |key: &str| info.get_property_val_str(key).map(str::to_string);
+                let instance = info.get_fullname().trim_end_matches(SERVICE_TYPE).trim_end_matches('.');
+                let mut addresses: Vec<IpAddr> = info.get_addresses().iter().copied().collect();
+                addresses.sort();
+                let server = Server {
+                    name: instance.to_string(),
+                    hostname: text("hostname").unwrap_or_else(|| info.get_hostname().to_string()),
+                    addresses,
+                    port: info.get_port(),
+                    version: text("version"),
+                    pairing: text("pairing"),
+                    tls_fp: text("tls_fp").filter(|fp| !fp.is_empty()),
+                };
+                servers.insert(info.get_fullname().to_string(), server);
+            }
+            Ok(ServiceEvent::ServiceRemoved(_, fullname)) => {
+                servers.remove(&fullname);
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    let _ = daemon.shutdown();
+    Ok(servers.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_labels_are_dns_safe() {
+        assert_eq!(host_label("my laptop"), "my-laptop");
+        assert_eq!(host_label("_desk.top_"), "desk-top");
+    }
+
+    #[test]
+    fn url_prefers_ipv4() {
+        let server = Server {
+            name: "desk".to_string(),
+            hostname: "desk".to_string(),
+            addresses: vec!["fe80::1".parse().unwrap(), "192.168.1.20".parse().unwrap()],
+            port: 44828,
+            version: None,
+            pairing: None,
+            tls_fp: None,
+        };
+        assert_eq!(server.url().as_deref(), Some("http://192.168.1.20:44828"));
+    }
+
+    #[test]
+    fn txt_records_follow_the_pairing_state() {
+        let text = |service: &ServiceInfo, key: &str| service.get_property_val_str(key).map(str::to_string);
+        let service = service_info(44828, true).unwrap();
+        assert_eq!(service.get_port(), 44828);
+        assert_eq!(text(&service, "hostname"), Some(hostname()));
+        assert_eq!(text(&service, "version").as_deref(), Some(env!("CARGO_PKG_VERSION")));
+        assert_eq!(text(&service, "pairing").as_deref(), Some("required"));
+        assert_eq!(text(&service, "tls_fp"), None);
+
+        let updated = service_info(44828, false).unwrap();
+        assert_eq!(updated.get_fullname(), service.get_fullname());
+        assert_eq!(text(&updated, "pairing").as_deref(), Some("optional"));
+    }
+}
@@ -0,0 +1,400 @@
+// Media control through MPRIS players on the D-Bus session bus
+//
+// Bus and player call failures are BackendUnavailable; naming a player that is
+// not on the bus, or asking for one when none runs, is an invalid request.
+use crate::error::ApiError;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::OnceCell;
+use tracing::info;
+use zbus::zvariant::{OwnedValue, Value};
+use zbus::Connection;
+
+const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+#[zbus::proxy(interface = "org.mpris.MediaPlayer2", default_path = "/org/mpris/MediaPlayer2")]
+trait MediaPlayer2 {
+    #[zbus(property)]
+    fn identity(&self) -> zbus::Result<String>;
+}
+
+#[zbus::proxy(interface = "org.mpris.MediaPlayer2.Player", default_path = "/org/mpris/MediaPlayer2")]
+trait Player {
+    fn play(&self) -> zbus::Result<()>;
+    fn pause(&self) -> zbus::Result<()>;
+    fn play_pause(&self) -> zbus::Result<()>;
+    fn stop(&self) -> zbus::Result<()>;
+    fn next(&self) -> zbus::Result<()>;
+    fn previous(&self) -> zbus::Result<()>;
+    fn seek(&self, offset: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> zbus::Result<String>;
+    #[zbus(property)]
+    fn metadata(&self) -> zbus::Result<HashMap<String, OwnedValue>>;
+    #[zbus(property)]
+    fn position(&self) -> zbus::Result<i64>;
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaAction {
+    Play,
+    Pause,
+    PlayPause,
+    Stop,
+    Next,
+    Previous,
+    Seek,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct MediaRequest {
+    pub action: MediaAction,
+    // Relative seek in seconds, negative to rewind
+    pub offset_seconds: Option<f64>,
+    // MPRIS bus name suffix (e.g. "spotify"); defaults to the active player
+    pub player: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Default, JsonSchema)]
+pub struct NowPlaying {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub length_seconds: Option<f64>,
+    pub position_seconds: Option<f64>,
+}
+
+impl NowPlaying {
+    // "Title — Artist" for voice replies
+    pub fn describe(&self) -> Option<String> {
+        match (&self.title, &self.artist) {
+            (Some(title), Some(artist)) => Some(format!("{} — {}", title, artist)),
+            (Some(title), None) => Some(title.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct PlayerInfo {
+    // Bus name without the org.mpris.MediaPlayer2. prefix
+    pub id: String,
+    pub identity: Option<String>,
+    pub status: String,
+    pub now_playing: NowPlaying,
+}
+
+#[derive(Debug, Serialize, Default, JsonSchema)]
+pub struct MediaResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub active: Option<PlayerInfo>,
+    pub players: Vec<PlayerInfo>,
+}
+
+pub struct MediaController {
+    conn: Connection,
+}
+
+static CONTROLLER: OnceCell<MediaController> = OnceCell::const_new();
+
+// The controller on the user's session bus, connected on first use
+pub async fn controller() -> Result<&'static MediaController, ApiError> {
+    CONTROLLER
+        .get_or_try_init(|| async {
+            let conn = Connection::session()
+                .await
+                .map_err(|e| ApiError::BackendUnavailable(format!("D-Bus session bus unavailable: {}", e)))?;
+            Ok(MediaController::with_connection(conn))
+        })
+        .await
+}
+
+fn value_str(value: &Value) -> Option<String> {
+    match value {
+        Value::Str(s) => Some(s.as_str().to_string()),
+        Value::Value(inner) => value_str(inner),
+        Value::Array(items) => {
+            let parts: Vec<String> = items.iter().filter_map(value_str).collect();
+            (!parts.is_empty()).then(|| parts.join(", "))
+        }
+        _ => None,
+    }
+}
+
+fn value_micros(value: &Value) -> Option<i64> {
+    match value {
+        Value::I64(v) => Some(*v),
+        Value::U64(v) => i64::try_from(*v).ok(),
+        Value::I32(v) => Some(i64::from(*v)),
+        Value::Value(inner) => value_micros(inner),
+        _ => None,
+    }
+}
+
+// Playing players first, then paused, then anything else
+fn status_rank(status: &str) -> u8 {
+    match status {
+        "Playing" => 0,
+        "Paused" => 1,
+        _ => 2,
+    }
+}
+
+impl MediaController {
+    // Any connection works, e.g. a private bus for testing
+    pub fn with_connection(conn: Connection) -> Self {
+        Self { conn }
+    }
+
+    async fn player_proxy(&self, id: &str) -> Result<PlayerProxy<'static>, ApiError> {
+        PlayerProxy::builder(&self.conn)
+            .destination(format!("{}{}", MPRIS_PREFIX, id))
+            .map_err(|e| ApiError::InvalidRequest(format!("Invalid player name {:?}: {}", id, e)))?
+            .build()
+            .await
+            .map_err(|e| ApiError::BackendUnavailable(format!("Failed to reach player {}: {}", id, e)))
+    }
+
+    async fn player_info(&self, id: &str) -> Result<PlayerInfo, ApiError> {
+        let player = self.player_proxy(id).await?;
+        let identity = match MediaPlayer2Proxy::builder(&self.conn).destination(format!("{}{}", MPRIS_PREFIX, id)) {
+            Ok(builder) => match builder.build().await {
+                Ok(root) => root.identity().await.ok(),
+                Err(_) => None,
+            },
+            Err(_) => None,
+        };
+        let status = player.playback_status().await.unwrap_or_else(|_| "Unknown".to_string());
+        let metadata = player.metadata().await.unwrap_or_default();
+        let field = |key: &str| metadata.get(key).and_then(|v| value_str(v));
+        let now_playing = NowPlaying {
+            title: field("xesam:title"),
+            artist: field("xesam:artist"),
+            album: field("xesam:album"),
+            length_seconds: metadata
+                .get("mpris:length")
+                .and_then(|v| value_micros(v))
+                .map(|us| us as f64 / 1e6),
+            position_seconds: player.position().await.ok().map(|us| us as f64 / 1e6),
+        };
+        Ok(PlayerInfo {
+            id: id.to_string(),
+            identity,
+            status,
+            now_playing,
+        })
+    }
+
+    // Ids of the MPRIS players on the bus, e.g. "spotify"
+    async fn player_ids(&self) -> Result<Vec<String>, ApiError> {
+        let bus_error = |e: zbus::fdo::Error| ApiError::BackendUnavailable(format!("D-Bus call failed: {}", e));
+        let dbus = zbus::fdo::DBusProxy::new(&self.conn)
+            .await
+            .map_err(|e| bus_error(e.into()))?;
+        let names = dbus.list_names().await.map_err(bus_error)?;
+        Ok(names
+            .iter()
+            .filter_map(|name| name.as_str().strip_prefix(MPRIS_PREFIX).map(str::to_string))
+            .collect())
+    }
+
+    pub async fn players(&self) -> Result<Vec<PlayerInfo>, ApiError> {
+        let mut players = Vec::new();
+        for id in self.player_ids().await? {
+            if let Ok(info) = self.player_info(&id).await {
+                players.push(info);
+            }
+        }
+        players.sort_by_key(|p| status_rank(&p.status));
+        Ok(players)
+    }
+
+    pub async fn active_player(&self) -> Result<PlayerInfo, ApiError> {
+        self.players()
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| ApiError::InvalidRequest("No media player is running".to_string()))
+    }
+
+    pub async fn control(&self, request: &MediaRequest) -> Result<PlayerInfo, ApiError> {
+        let id = match &request.player {
+            Some(id) if !self.player_ids().await?.contains(id) => {
+                return Err(ApiError::InvalidRequest(format!("Unknown player: {}", id)))
+            }
+            Some(id) => id.clone(),
+            None => self.active_player().await?.id,
+        };
+        let player = self.player_proxy(&id).await?;
+        info!("🎵 MPRIS {:?} on {}", request.action, id);
+
+        let result = match request.action {
+            MediaAction::Play => player.play().await,
+            MediaAction::Pause => player.pause().await,
+            MediaAction::PlayPause => player.play_pause().await,
+            MediaAction::Stop => player.stop().await,
+            MediaAction::Next => player.next().await,
+            MediaAction::Previous => player.previous().await,
+            MediaAction::Seek => {
+                let seconds = request
+                    .offset_seconds
+                    .ok_or_else(|| ApiError::InvalidRequest("Seek needs offset_seconds".to_string()))?;
+                player.seek((seconds * 1e6) as i64).await
+            }
+        };
+        result.map_err(|e| ApiError::BackendUnavailable(format!("{} rejected {:?}: {}", id, request.action, e)))?;
+        self.player_info(&id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+
+    struct FakeRoot;
+
+    #[zbus::interface(name = "org.mpris.MediaPlayer2")]
+    impl FakeRoot {
+        #[zbus(property)]
+        fn identity(&self) -> String {
+            "Fake Player".to_string()
+        }
+    }
+
+    struct FakePlayer {
+        status: String,
+    }
+
+    #[zbus::interface(name = "org.mpris.MediaPlayer2.Player")]
+    impl FakePlayer {
+        fn play(&mut self) {
+            self.status = "Playing".to_string();
+        }
+        fn pause(&mut self) {
+            self.status = "Paused".to_string();
+        }
+        fn play_pause(&mut self) {
+            self.status = if self.status == "Playing" { "Paused" } else { "Playing" }.to_string();
+        }
+        fn stop(&mut self) {
+            self.status = "Stopped".to_string();
+        }
+        fn next(&self) {}
+        fn previous(&self) {}
+        fn seek(&self, _offset: i64) {}
+
+        #[zbus(property)]
+        fn playback_status(&self) -> String {
+            self.status.clone()
+        }
+        #[zbus(property)]
+        fn metadata(&self) -> HashMap<String, OwnedValue> {
+            let title = OwnedValue::try_from(Value::from("Test Song")).unwrap();
+            HashMap::from([("xesam:title".to_string(), title)])
+        }
+        #[zbus(property)]
+        fn position(&self) -> i64 {
+            42_000_000
+        }
+    }
+
+    // A bus of our own, so the test neither needs nor disturbs a desktop session
+    struct PrivateBus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl PrivateBus {
+        fn start() -> Self {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address=1"])
+                .stdout(Stdio::piped())
+                .spawn()
+                .expect("dbus-daemon is installed");
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take().unwrap()).read_line(&mut address).unwrap();
+            Self {
+                daemon,
+                address: address.trim().to_string(),
+            }
+        }
+
+        async fn connect(&self) -> Connection {
+            zbus::connection::Builder::address(self.address.as_str()).unwrap().build().await.unwrap()
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    fn request(action: MediaAction, player: Option<&str>) -> MediaRequest {
+        MediaRequest {
+            action,
+            offset_seconds: None,
+            player: player.map(str::to_string),
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs dbus-daemon"]
+    async fn controls_a_player_on_a_private_bus() {
+        let bus = PrivateBus::start();
+        let _player = zbus::connection::Builder::address(bus.address.as_str())
+            .unwrap()
+            .name("org.mpris.MediaPlayer2.fake")
+            .unwrap()
+            .serve_at("/org/mpris/MediaPlayer2", FakeRoot)
+            .unwrap()
+            .serve_at(
+                "/org/mpris/MediaPlayer2",
+                FakePlayer {
+                    status: "Stopped".to_string(),
+                },
+            )
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+        let controller = MediaController::with_connection(bus.connect().await);
+
+        let players = controller.players().await.unwrap();
+        assert_eq!(players.len(), 1);
+        let player = &players[0];
+        assert_eq!(player.id, "fake");
+        assert_eq!(player.identity.as_deref(), Some("Fake Player"));
+        assert_eq!(player.status, "Stopped");
+        assert_eq!(player.now_playing.title.as_deref(), Some("Test Song"));
+        assert_eq!(player.now_playing.position_seconds, Some(42.0));
+
+        // Without a player named, the active one is used
+        let player = controller.control(&request(MediaAction::Play, None)).await.unwrap();
+        assert_eq!(player.status, "Playing");
+        let player = controller.control(&request(MediaAction::PlayPause, Some("fake"))).await.unwrap();
+        assert_eq!(player.status, "Paused");
+
+        let error = controller.control(&request(MediaAction::Seek, Some("fake"))).await.unwrap_err();
+        assert!(matches!(&error, ApiError::InvalidRequest(m) if m.contains("offset_seconds")), "{}", error);
+        let error = controller.control(&request(MediaAction::Play, Some("missing"))).await.unwrap_err();
+        assert!(matches!(&error, ApiError::InvalidRequest(m) if m == "Unknown player: missing"), "{}", error);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs dbus-daemon"]
+    async fn no_player_on_an_empty_bus() {
+        let bus = PrivateBus::start();
+        let controller = MediaController::with_connection(bus.connect().await);
+        assert!(controller.players().await.unwrap().is_empty());
+        let error = controller.control(&request(MediaAction::Play, None)).await.unwrap_err();
+        assert!(matches!(&error, ApiError::InvalidRequest(m) if m == "No media player is running"), "{}", error);
+    }
+}
@@ -0,0 +1,224 @@
+// Prometheus metrics, served at /metrics in the text exposition format
+//
+// Off unless ASTRA_METRICS is set: "local" answers only this machine (scrape through
+// a local agent or an SSH tunnel), "on" answers anyone who can reach the port. The
+// numbers are kept in memory and start from zero with every server start.
+use crate::error::ErrorCode;
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+// Upper bounds in seconds; enigo calls take well under a millisecond when X is local
+const INJECTION_BUCKETS: [f64; 11] = [0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Off,
+    Local,
+    Public,
+}
+
+impl Access {
+    fn from_env() -> Self {
+        match std::env::var("ASTRA_METRICS").as_deref() {
+            Err(_) | Ok("off") | Ok("0") | Ok("") => Access::Off,
+            Ok("local") => Access::Local,
+            Ok("on") | Ok("1") => Access::Public,
+            Ok(other) => {
+                warn!("📈 Unknown ASTRA_METRICS={:?}, metrics disabled (use local or on)", other);
+                Access::Off
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Histogram {
+    // Per bucket, not cumulative; summed when rendered
+    counts: [u64; INJECTION_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        if let Some(bucket) = INJECTION_BUCKETS.iter().position(|&bound| seconds <= bound) {
+            self.counts[bucket] += 1;
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+#[derive(Default)]
+struct Metrics {
+    // (method, route, result) -> count
+    requests: BTreeMap<(String, String, String), u64>,
+    injection: BTreeMap<&'static str, Histogram>,
+    rate_limited: BTreeMap<&'static str, u64>,
+    voice_parse_failures: u64,
+    backend_errors: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Session {
+    Events,
+    Screen,
+}
+
+static EVENT_SESSIONS: AtomicI64 = AtomicI64::new(0);
+static SCREEN_SESSIONS: AtomicI64 = AtomicI64::new(0);
+
+lazy_static::lazy_static! {
+    static ref ACCESS: Access = Access::from_env();
+    static ref METRICS: Mutex<Metrics> = Mutex::new(Metrics::default());
+}
+
+pub fn access() -> Access {
+    *ACCESS
+}
+
+fn enabled() -> bool {
+    access() != Access::Off
+}
+
+// Runs an input injection and records how long it took, lock wait included; the
+// duration is returned for the response timing
+pub fn injection<T>(action: &'static str, inject: impl FnOnce() -> T) -> (T, Duration) {
+    let started = Instant::now();
+    let result = inject();
+    let elapsed = started.elapsed();
+    record_injection(action, elapsed);
+    (result, elapsed)
+}
+
+pub fn record_injection(action: &'static str, duration: Duration) {
+    if enabled() {
+        METRICS.lock().unwrap().injection.entry(action).or_default().observe(duration.as_secs_f64());
+    }
+}
+
+pub fn rate_limited(class: &'static str) {
+    if enabled() {
+        *METRICS.lock().unwrap().rate_limited.entry(class).or_default() += 1;
+    }
+}
+
+// Counts an open connection for as long as the guard lives
+pub struct SessionGuard(Session);
+
+pub fn session(kind: Session) -> SessionGuard {
+    gauge(kind).fetch_add(1, Ordering::Relaxed);
+    SessionGuard(kind)
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        gauge(self.0).fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn gauge(kind: Session) -> &'static AtomicI64 {
+    match kind {
+        Session::Events => &EVENT_SESSIONS,
+        Session::Screen => &SCREEN_SESSIONS,
+    }
+}
+
+// Counts every request by route and result; the route is the pattern (/upload/:id),
+// so labels stay bounded
+pub async fn middleware(request: Request, next: Next) -> Response {
+    if !enabled() {
+        return next.run(request).await;
+    }
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let response = next.run(request).await;
+
+    let result = match response.extensions().get::<ErrorCode>() {
+        Some(ErrorCode(code)) => code.to_string(),
+        None if response.status().is_success() => "ok".to_string(),
+        None => response.status().as_u16().to_string(),
+    };
+    let mut metrics = METRICS.lock().unwrap();
+    match result.as_str() {
+        "unknown_voice_command" => metrics.voice_parse_failures += 1,
+        "backend_unavailable" => *metrics.backend_errors.entry(route.clone()).or_default() += 1,
+        _ => {}
+    }
+    *metrics.requests.entry((method, route, result)).or_default() += 1;
+    response
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+pub fn render() -> String {
+    let metrics = METRICS.lock().unwrap();
+    let mut out = String::new();
+
+    header(&mut out, "astra_requests_total", "counter", "HTTP requests by route and result (ok or error code).");
+    for ((method, route, result), count) in &metrics.requests {
+        let _ = writeln!(
+            out,
+            "astra_requests_total{{method=\"{}\",route=\"{}\",result=\"{}\"}} {}",
+            escape(method),
+            escape(route),
+            escape(result),
+            count
+        );
+    }
+
+    header(&mut out, "astra_input_injection_seconds", "histogram", "Time to inject an input action, including the wait for the input device.");
+    for (action, histogram) in &metrics.injection {
+        let mut cumulative = 0;
+        for (bound, count) in INJECTION_BUCKETS.iter().zip(histogram.counts) {
+            cumulative += count;
+            let _ = writeln!(out, "astra_input_injection_seconds_bucket{{action=\"{}\",le=\"{}\"}} {}", action, bound, cumulative);
+        }
+        let _ = writeln!(out, "astra_input_injection_seconds_bucket{{action=\"{}\",le=\"+Inf\"}} {}", action, histogram.count);
+        let _ = writeln!(out, "astra_input_injection_seconds_sum{{action=\"{}\"}} {}", action, histogram.sum);
+        let _ = writeln!(out, "astra_input_injection_seconds_count{{action=\"{}\"}} {}", action, histogram.count);
+    }
+
+    header(&mut out, "astra_sessions", "gauge", "Open client sessions by kind.");
+    let udp = crate::udp::channel().map(|channel| channel.session_count()).unwrap_or(0);
+    let _ = writeln!(out, "astra_sessions{{kind=\"events\"}} {}", EVENT_SESSIONS.load(Ordering::Relaxed));
+    let _ = writeln!(out, "astra_sessions{{kind=\"screen\"}} {}", SCREEN_SESSIONS.load(Ordering::Relaxed));
+    let _ = writeln!(out, "astra_sessions{{kind=\"udp\"}} {}", udp);
+
+    header(&mut out, "astra_rate_limited_total", "counter", "Requests refused by the rate limiter, by route class.");
+    for (class, count) in &metrics.rate_limited {
+        let _ = writeln!(out, "astra_rate_limited_total{{class=\"{}\"}} {}", class, count);
+    }
+
+    header(&mut out, "astra_voice_parse_failures_total", "counter", "Voice commands that were not understood.");
+    let _ = writeln!(out, "astra_voice_parse_failures_total {}", metrics.voice_parse_failures);
+
+    header(&mut out, "astra_backend_errors_total", "counter", "Requests failed by an unavailable desktop backend, by route.");
+    for (route, count) in &metrics.backend_errors {
+        let _ = writeln!(out, "astra_backend_errors_total{{route=\"{}\"}} {}", escape(route), count);
+    }
+
+    header(&mut out, "astra_input_paused", "gauge", "1 while remote input is paused.");
+    let _ = writeln!(out, "astra_input_paused {}", crate::killswitch::is_paused() as u8);
+    out
+}
@@ -0,0 +1,330 @@
+// OpenAPI 3 description of the HTTP API, served at /openapi.json
+//
+// Schemas are generated from the request/response types with schemars, so the
+// document follows the code; only the route table below is written by hand and
+// has to be kept in sync with the router in main.rs.
+use crate::{batch, clipboard, events, latency, media, pairing, presentation, screen, udp, upload, volume, windows};
+use crate::error::ErrorResponse;
+use crate::{ApiResponse, ClickRequest, KeyRequest, MouseMove, ScrollRequest, VoiceRequest};
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+use std::sync::OnceLock;
+
+struct Builder {
+    gen: SchemaGenerator,
+    paths: Map<String, Value>,
+}
+
+impl Builder {
+    fn add(&mut self, method: &str, path: &str, summary: &str, mut operation: Value) {
+        operation["summary"] = summary.into();
+        let item = self.paths.entry(path).or_insert_with(|| json!({}));
+        item[method] = operation;
+    }
+
+    fn json<T: JsonSchema>(&mut self) -> Value {
+        json!({ "application/json": { "schema": self.gen.subschema_for::<T>() } })
+    }
+
+    fn body<T: JsonSchema>(&mut self) -> Value {
+        json!({ "required": true, "content": self.json::<T>() })
+    }
+
+    fn responses<T: JsonSchema>(&mut self, description: &str) -> Value {
+        json!({
+            "200": { "description": description, "content": self.json::<T>() },
+            "default": self.error(),
+        })
+    }
+
+    // Every route reports failures as an ErrorResponse with a machine-readable code
+    fn error(&mut self) -> Value {
+        json!({ "description": "Error", "content": self.json::<ErrorResponse>() })
+    }
+
+    fn query<T: JsonSchema>(&mut self) -> Vec<Value> {
+        let root = self.gen.root_schema_for::<T>();
+        let Some(object) = root.schema.object else {
+            return Vec::new();
+        };
+        object
+            .properties
+            .iter()
+            .map(|(name, schema)| {
+                json!({
+                    "name": name,
+                    "in": "query",
+                    "required": object.required.contains(name),
+                    "schema": schema,
+                })
+            })
+            .collect()
+    }
+
+    // A JSON endpoint: optional request body, response type T
+    fn json_op<Req: JsonSchema, Res: JsonSchema>(&mut self, description: &str) -> Value {
+        json!({ "requestBody": self.body::<Req>(), "responses": self.responses::<Res>(description) })
+    }
+
+    fn get_op<Res: JsonSchema>(&mut self, description: &str) -> Value {
+        json!({ "responses": self.responses::<Res>(description) })
+    }
+}
+
+fn authenticated(mut operation: Value) -> Value {
+    operation["security"] = json!([{ "bearer": [] }]);
+    operation
+}
+
+fn build() -> Value {
+    let mut api = Builder {
+        gen: SchemaSettings::openapi3().into_generator(),
+        paths: Map::new(),
+    };
+
+    let html = json!({ "200": { "description": "Mobile web client", "content": { "text/html": {} } } });
+    api.add("get", "/", "Built-in mobile web client", json!({ "responses": html }));
+    let op = api.get_op::<ApiResponse>("Server is up");
+    api.add("get", "/ping", "Health check", op);
+    let op = json!({ "responses": { "200": { "description": "This document", "content": { "application/json": {} } } } });
+    api.add("get", "/openapi.json", "OpenAPI description", op);
+    let op = json!({
+        "responses": {
+            "200": { "description": "Prometheus text format", "content": { "text/plain": {} } },
+            "default": api.error(),
+        },
+    });
+    api.add("get", "/metrics", "Prometheus metrics (opt-in with ASTRA_METRICS)", op);
+
+    // Input
+    let op = api.json_op::<MouseMove, ApiResponse>("Pointer moved");
+    api.add("post", "/mouse", "Move the pointer by a relative offset", op);
+    let op = api.json_op::<ClickRequest, ApiResponse>("Clicked");
+    api.add("post", "/click", "Click (left, right, double)", op);
+    let op = api.json_op::<ScrollRequest, ApiResponse>("Scrolled");
+    api.add("post", "/scroll", "Scroll by detents or fling with momentum", op);
+    let op = api.json_op::<KeyRequest, ApiResponse>("Key pressed");
+    api.add("post", "/key", "Press a key with optional modifiers", op);
+    let op = api.json_op::<batch::BatchRequest, batch::BatchResponse>("Per-step results");
+    api.add("post", "/batch", "Run several input steps in order under the input lock", op);
+    let op = api.get_op::<crate::input::InputResponse>("Backend and held keys/buttons");
+    api.add("get", "/input", "Input backend state", op);
+    let op = api.get_op::<crate::input::InputResponse>("State after releasing");
+    api.add("post", "/reset-input", "Release every held key and mouse button", op);
+    let op = api.json_op::<VoiceRequest, ApiResponse>("Command executed");
+    api.add("post", "/voice", "Run a voice command", op);
+
+    // Desktop
+    let op = api.get_op::<media::MediaResponse>("MPRIS players, active first");
+    api.add("get", "/media", "List media players", op);
+    let op = api.json_op::<media::MediaRequest, media::MediaResponse>("Player state after the action");
+    api.add("post", "/media", "Control a media player", op);
+    let op = api.get_op::<volume::VolumeResponse>("Volume, mute and outputs");
+    api.add("get", "/volume", "Get system volume", op);
+    let op = api.json_op::<volume::VolumeRequest, volume::VolumeResponse>("Volume after the change");
+    api.add("post", "/volume", "Change volume, mute or output", op);
+    let op = api.get_op::<windows::WindowsResponse>("Top-level windows");
+    api.add("get", "/windows", "List windows", op);
+    let op = api.json_op::<windows::WindowRequest, windows::WindowsResponse>("Windows after the action");
+    api.add("post", "/windows", "Focus, minimize, maximize, close or move a window", op);
+    let op = api.get_op::<presentation::PresentationResponse>("Deck app, slide and elapsed time");
+    api.add("get", "/presentation", "Presentation status", op);
+    let op = api.json_op::<presentation::PresentationRequest, presentation::PresentationResponse>("Status after the action");
+    api.add("post", "/presentation", "Slide navigation and laser pointer", op);
+
+    let frames = json!({
+        "200": {
+            "description": "A JPEG/PNG frame, or an MJPEG stream with stream=true",
+            "content": {
+                "image/jpeg": {},
+                "image/png": {},
+                "multipart/x-mixed-replace": {},
+            },
+        },
+        "default": api.error(),
+    });
+    let op = json!({ "parameters": api.query::<screen::ScreenQuery>(), "responses": frames });
+    api.add("get", "/screen", "Screen preview (opt-in with ASTRA_SCREEN=1)", authenticated(op));
+
+    // Pairing and paired-device features
+    let op = api.json_op::<pairing::PairRequest, pairing::PairResponse>("Device token");
+    api.add("post", "/pair", "Pair a device with the pairing code", op);
+    let op = api.get_op::<udp::HandshakeResponse>("UDP session id and key");
+    api.add("post", "/udp/handshake", "Open a UDP pointer session", authenticated(op));
+
+    let mut op = api.get_op::<clipboard::ClipboardResponse>("Clipboard content");
+    op["parameters"] = api.query::<clipboard::ClipboardQuery>().into();
+    api.add("get", "/clipboard", "Read the clipboard", authenticated(op));
+    let op = api.json_op::<clipboard::ClipboardPut, clipboard::ClipboardResponse>("Clipboard written");
+    api.add("put", "/clipboard", "Write the clipboard", authenticated(op));
+
+    let op = json!({
+        "parameters": [{ "name": "token", "in": "query", "required": false, "schema": { "type": "string" } }],
+        "responses": {
+            "101": {
+                "description": "WebSocket; every text message is one ServerEvent",
+                "content": api.json::<events::ServerEvent>(),
+            },
+            "default": api.error(),
+        },
+    });
+    api.add("get", "/events", "Server push events (WebSocket)", authenticated(op));
+
+    let op = api.json_op::<upload::UploadInit, upload::UploadResponse>("Upload id and chunk size");
+    api.add("post", "/upload", "Start a resumable upload", authenticated(op));
+    let id = json!({ "name": "id", "in": "path", "required": true, "schema": { "type": "string" } });
+    let mut op = api.get_op::<upload::UploadResponse>("Current offset");
+    op["parameters"] = json!([id]);
+    api.add("get", "/upload/{id}", "Upload status", authenticated(op));
+    let mut parameters = vec![id];
+    parameters.extend(api.query::<upload::ChunkQuery>());
+    let op = json!({
+        "parameters": parameters,
+        "requestBody": { "required": true, "content": { "application/octet-stream": {} } },
+        "responses": api.responses::<upload::UploadResponse>("Offset after the chunk"),
+    });
+    api.add("put", "/upload/{id}", "Append a chunk at the current offset", authenticated(op));
+
+    // Admin routes, loopback only
+    let op = api.get_op::<ApiResponse>("Input paused");
+    api.add("post", "/pause", "Pause remote input and release held keys", op);
+    let op = api.get_op::<ApiResponse>("Input resumed");
+    api.add("post", "/resume", "Resume remote input", op);
+    let op = api.get_op::<ApiResponse>("Shutdown started");
+    api.add("post", "/shutdown", "Stop the server gracefully", op);
+    let op = api.get_op::<pairing::DevicesResponse>("Paired devices and their scopes");
+    api.add("get", "/devices", "List paired devices", op);
+    let id = json!({ "name": "id", "in": "path", "required": true, "schema": { "type": "string" } });
+    let mut op = api.json_op::<pairing::DeviceUpdate, pairing::DevicesResponse>("Devices after the change");
+    op["parameters"] = json!([id.clone()]);
+    api.add("put", "/devices/{id}", "Rename a device or change its scopes", op);
+    let mut op = api.get_op::<pairing::DevicesResponse>("Devices after unpairing");
+    op["parameters"] = json!([id]);
+    api.add("delete", "/devices/{id}", "Unpair a device", op);
+    let op = api.get_op::<latency::LatencyResponse>("Timing percentiles per client");
+    api.add("get", "/latency", "Input latency of recent requests", op);
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Astra Remote Control",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": api.paths,
+        "components": {
+            "schemas": api.gen.definitions(),
+            "securitySchemes": {
+                "bearer": {
+                    "type": "http",
+                    "scheme": "bearer",
+                    "description": "Device token from POST /pair",
+                },
+            },
+        },
+    })
+}
+
+pub fn document() -> &'static Value {
+    static DOCUMENT: OnceLock<Value> = OnceLock::new();
+    DOCUMENT.get_or_init(build)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    // Property names and enum values of a schema and its variants; nested
+    // types are compared on their own below
+    fn shape<T: JsonSchema>() -> BTreeSet<String> {
+        fn collect(schema: &Value, out: &mut BTreeSet<String>) {
+            if let Some(properties) = schema["properties"].as_object() {
+                out.extend(properties.keys().cloned());
+            }
+            if let Some(values) = schema["enum"].as_array() {
+                out.extend(values.iter().filter_map(Value::as_str).map(str::to_string));
+            }
+            for key in ["oneOf", "anyOf", "allOf"] {
+                for variant in schema[key].as_array().into_iter().flatten() {
+                    collect(variant, out);
+                }
+            }
+        }
+        let root = SchemaSettings::openapi3().into_generator().into_root_schema_for::<T>();
+        let mut out = BTreeSet::new();
+        collect(&serde_json::to_value(root.schema).unwrap(), &mut out);
+        out
+    }
+
+    macro_rules! same_shape {
+        ($($server:ty => $client:ident),* $(,)?) => {
+            $(
+                assert_eq!(
+                    shape::<$server>(),
+                    shape::<astra_client::types::$client>(),
+                    "astra-client's {} differs from the server's",
+                    stringify!($client)
+                );
+            )*
+        };
+    }
+
+    #[test]
+    fn client_types_match_the_server() {
+        same_shape! {
+            ApiResponse => ApiResponse,
+            latency::Timing => Timing,
+            MouseMove => MouseMove,
+            ClickRequest => ClickRequest,
+            ScrollRequest => ScrollRequest,
+            KeyRequest => KeyRequest,
+            VoiceRequest => VoiceRequest,
+            batch::BatchStep => BatchStep,
+            batch::BatchRequest => BatchRequest,
+            batch::StepResult => StepResult,
+            batch::BatchResponse => BatchResponse,
+            crate::input::InputResponse => InputResponse,
+            pairing::PairRequest => PairRequest,
+            pairing::PairResponse => PairResponse,
+            udp::HandshakeResponse => HandshakeResponse,
+            clipboard::Selection => Selection,
+            clipboard::ClipboardContent => ClipboardContent,
+            clipboard::ClipboardPut => ClipboardPut,
+            clipboard::ClipboardResponse => ClipboardResponse,
+            upload::UploadInit => UploadInit,
+            upload::UploadResponse => UploadResponse,
+            media::MediaAction => MediaAction,
+            media::MediaRequest => MediaRequest,
+            media::NowPlaying => NowPlaying,
+            media::PlayerInfo => PlayerInfo,
+            media::MediaResponse => MediaResponse,
+            volume::VolumeRequest => VolumeRequest,
+            volume::Sink => Sink,
+            volume::VolumeResponse => VolumeResponse,
+            screen::ScreenQuery => ScreenQuery,
+            windows::WindowInfo => WindowInfo,
+            windows::WindowAction => WindowAction,
+            windows::WindowRequest => WindowRequest,
+            windows::WindowsResponse => WindowsResponse,
+            presentation::PresentationAction => PresentationAction,
+            presentation::PresentationRequest => PresentationRequest,
+            presentation::PresentationResponse => PresentationResponse,
+            crate::scopes::Scope => Scope,
+            pairing::DeviceInfo => DeviceInfo,
+            pairing::DeviceUpdate => DeviceUpdate,
+            pairing::DevicesResponse => DevicesResponse,
+            latency::Percentiles => Percentiles,
+            latency::ClientLatency => ClientLatency,
+            latency::LatencyResponse => LatencyResponse,
+        }
+    }
+
+    #[test]
+    fn document_lists_every_admin_route() {
+        let paths = document()["paths"].as_object().unwrap();
+        for path in ["/pause", "/resume", "/shutdown", "/devices", "/devices/{id}", "/latency", "/metrics"] {
+            assert!(paths.contains_key(path), "{} missing from /openapi.json", path);
+        }
+    }
+}
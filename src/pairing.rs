@@ -0,0 +1,296 @@
+// Device pairing: a short code shown on the desktop is exchanged for a bearer token
+use crate::scopes::Scope;
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request},
+    http::{header, request::Parts, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use std::convert::Infallible;
+use rand::Rng;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+// After this many wrong codes the pairing code is replaced, or pairing is locked
+// for PAIRING_LOCKOUT when the code is fixed
+const MAX_FAILED_ATTEMPTS: u32 = 5;
+const PAIRING_LOCKOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Device {
+    pub id: String,
+    pub name: String,
+    pub token: String,
+    pub paired_at: String,
+    // Devices paired before scopes existed keep full access
+    #[serde(default = "crate::scopes::all")]
+    pub scopes: Vec<Scope>,
+}
+
+// A device as listed on /devices, without its token
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct DeviceInfo {
+    pub id: String,
+    pub name: String,
+    pub paired_at: String,
+    pub scopes: Vec<Scope>,
+}
+
+impl From<&Device> for DeviceInfo {
+    fn from(device: &Device) -> Self {
+        Self {
+            id: device.id.clone(),
+            name: device.name.clone(),
+            paired_at: device.paired_at.clone(),
+            scopes: device.scopes.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DeviceUpdate {
+    pub name: Option<String>,
+    pub scopes: Option<Vec<Scope>>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct DevicesResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub pairing_required: bool,
+    pub devices: Vec<DeviceInfo>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PairRequest {
+    pub code: String,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PairResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub device_id: Option<String>,
+    pub token: Option<String>,
+}
+
+pub struct PairingStore {
+    path: PathBuf,
+    devices: Vec<Device>,
+    code: String,
+    // A code set through ASTRA_PAIRING_CODE (e.g. by the GUI) is never rotated
+    fixed_code: bool,
+    failed_attempts: u32,
+    locked_until: Option<Instant>,
+}
+
+lazy_static::lazy_static! {
+    pub static ref PAIRING: Mutex<PairingStore> = Mutex::new(PairingStore::load());
+}
+
+pub fn random_hex(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    rand::thread_rng().fill(&mut buf[..]);
+    buf.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn random_code() -> String {
+    format!("{:06}", rand::thread_rng().gen_range(0..1_000_000))
+}
+
+// Compares secrets in a time that does not depend on where they differ
+fn secret_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+impl PairingStore {
+    // Whether any device lacks a scope, i.e. was narrowed in the GUI or through /devices
+    pub fn has_restricted_device(&self) -> bool {
+        self.devices
+            .iter()
+            .any(|device| Scope::ALL.iter().any(|scope| !device.scopes.contains(scope)))
+    }
+
+    fn load() -> Self {
+        let path = crate::config::config_dir().join("devices.json");
+        let devices = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("🔐 Ignoring unreadable {}: {}", path.display(), e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        let (code, fixed_code) = match std::env::var("ASTRA_PAIRING_CODE") {
+            Ok(code) if !code.trim().is_empty() => (code.trim().to_string(), true),
+            _ => (random_code(), false),
+        };
+        Self {
+            path,
+            devices,
+            code,
+            fixed_code,
+            failed_attempts: 0,
+            locked_until: None,
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    // Also tells subscribers (the mDNS advertisement) when pairing became required
+    // or optional; the devices in memory have changed even if writing fails
+    pub fn save(&self) -> Result<(), String> {
+        crate::scopes::publish_pairing_required(self);
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let json = serde_json::to_string_pretty(&self.devices).map_err(|e| e.to_string())?;
+        // Holds bearer tokens: owner-only, and replaced in one step so a file left
+        // readable by an older version is not reused
+        let temp = self.path.with_extension("json.tmp");
+        let write = || {
+            let mut file = std::fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o600)
+                .open(&temp)?;
+            file.write_all(json.as_bytes())?;
+            file.sync_all()?;
+            std::fs::rename(&temp, &self.path)
+        };
+        write().map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+
+    pub fn pair(&mut self, code: &str, name: Option<String>) -> Result<Device, String> {
+        if let Some(left) = self.locked_until.and_then(|until| until.checked_duration_since(Instant::now())) {
+            return Err(format!("Too many wrong pairing codes, try again in {}s", left.as_secs() + 1));
+        }
+        if !secret_eq(code.trim(), &self.code) {
+            self.failed_attempts += 1;
+            if self.failed_attempts >= MAX_FAILED_ATTEMPTS {
+                self.failed_attempts = 0;
+                if self.fixed_code {
+                    self.locked_until = Some(Instant::now() + PAIRING_LOCKOUT);
+                    warn!("🔐 Too many wrong pairing attempts, pairing locked for {}s", PAIRING_LOCKOUT.as_secs());
+                } else {
+                    self.code = random_code();
+                    warn!("🔐 Too many wrong pairing attempts, new pairing code: {}", self.code);
+                }
+            }
+            return Err("Invalid pairing code".to_string());
+        }
+        self.failed_attempts = 0;
+
+        let device = Device {
+            id: random_hex(8),
+            name: name
+                .map(|n| n.trim().to_string())
+                .filter(|n| !n.is_empty())
+                .unwrap_or_else(|| "Unnamed device".to_string()),
+            token: random_hex(32),
+            paired_at: chrono::Local::now().to_rfc3339(),
+            scopes: crate::scopes::all(),
+        };
+        self.devices.push(device.clone());
+        self.save()?;
+        info!("🔐 Paired device '{}' ({})", device.name, device.id);
+        Ok(device)
+    }
+
+    pub fn find_by_token(&self, token: &str) -> Option<&Device> {
+        self.devices.iter().find(|d| secret_eq(&d.token, token))
+    }
+
+    pub fn find(&self, id: &str) -> Option<&Device> {
+        self.devices.iter().find(|d| d.id == id)
+    }
+
+    pub fn devices(&self) -> Vec<DeviceInfo> {
+        self.devices.iter().map(DeviceInfo::from).collect()
+    }
+
+    pub fn update(&mut self, id: &str, update: DeviceUpdate) -> Result<DeviceInfo, String> {
+        let device = self
+            .devices
+            .iter_mut()
+            .find(|d| d.id == id)
+            .ok_or_else(|| format!("No paired device {}", id))?;
+        if let Some(name) = update.name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty()) {
+            device.name = name;
+        }
+        if let Some(mut scopes) = update.scopes {
+            scopes.sort_by_key(|scope| Scope::ALL.iter().position(|s| s == scope));
+            scopes.dedup();
+            device.scopes = scopes;
+        }
+        let info = DeviceInfo::from(&*device);
+        self.save()?;
+        let scopes: Vec<&str> = info.scopes.iter().map(|s| s.name()).collect();
+        info!("🔐 Device '{}' ({}) scopes: {}", info.name, info.id, scopes.join(", "));
+        Ok(info)
+    }
+
+    // Unpairs a device; its token stops working right away
+    pub fn remove(&mut self, id: &str) -> Result<Device, String> {
+        let index = self
+            .devices
+            .iter()
+            .position(|d| d.id == id)
+            .ok_or_else(|| format!("No paired device {}", id))?;
+        let device = self.devices.remove(index);
+        self.save()?;
+        info!("🔐 Unpaired device '{}' ({})", device.name, device.id);
+        Ok(device)
+    }
+}
+
+// Resolves `Authorization: Bearer <token>` to a paired device
+pub fn device_from_headers(headers: &HeaderMap) -> Option<Device> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    device_from_token(value.strip_prefix("Bearer ")?.trim())
+}
+
+pub fn device_from_token(token: &str) -> Option<Device> {
+    PAIRING.lock().unwrap().find_by_token(token).cloned()
+}
+
+// The paired device behind a request, if any; resolved once by `identify` and
+// read by the later middleware and handlers from the request extensions
+#[derive(Debug, Clone)]
+pub struct Caller(pub Option<Device>);
+
+impl Caller {
+    pub fn of(request: &Request) -> Option<&Device> {
+        request.extensions().get::<Caller>().and_then(|caller| caller.0.as_ref())
+    }
+}
+
+// Outermost middleware, so the token is looked up once per request
+pub async fn identify(mut request: Request, next: Next) -> Response {
+    let caller = Caller(device_from_headers(request.headers()));
+    request.extensions_mut().insert(caller);
+    next.run(request).await
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Caller {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(match parts.extensions.get::<Caller>() {
+            Some(caller) => caller.clone(),
+            None => Caller(device_from_headers(&parts.headers)),
+        })
+    }
+}
@@ -0,0 +1,303 @@
+// Presentation mode: slide navigation keys per deck app, a laser pointer overlay,
+// and elapsed time / slide position for the presenter
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::info;
+use x11rb::connection::Connection;
+use x11rb::protocol::shape::{self, ConnectionExt as _};
+use x11rb::protocol::xproto::{
+    ClipOrdering, ConfigureWindowAux, ConnectionExt as _, CreateWindowAux, Rectangle, StackMode, WindowClass,
+};
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeckApp {
+    Impress,
+    Evince,
+    Okular,
+    Browser,
+    // Unknown app: the keys most slide software agrees on
+    Generic,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PresentationAction {
+    Next,
+    Previous,
+    Start,
+    End,
+    Blank,
+    Laser,
+    LaserOff,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PresentationRequest {
+    pub action: PresentationAction,
+    // Relative laser movement, in screen pixels
+    #[serde(default)]
+    pub dx: f32,
+    #[serde(default)]
+    pub dy: f32,
+}
+
+#[derive(Debug, Serialize, Default, JsonSchema)]
+pub struct PresentationResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub app: Option<DeckApp>,
+    // Counted from start; unknown after jumping to the end
+    pub slide: Option<u32>,
+    pub elapsed_seconds: Option<f64>,
+    pub blanked: bool,
+}
+
+impl DeckApp {
+    // Classifies the focused window by its app id and title
+    pub fn detect(app_id: &str, title: &str) -> Self {
+        let app_id = app_id.to_lowercase();
+        let title = title.to_lowercase();
+        if app_id.contains("impress") || (app_id.contains("office") && title.contains("impress")) {
+            DeckApp::Impress
+        } else if app_id.contains("evince") {
+            DeckApp::Evince
+        } else if app_id.contains("okular") {
+            DeckApp::Okular
+        } else if ["firefox", "chrom", "brave", "vivaldi", "epiphany", "edge"]
+            .iter()
+            .any(|browser| app_id.contains(browser))
+        {
+            DeckApp::Browser
+        } else {
+            DeckApp::Generic
+        }
+    }
+
+    // Key and modifiers for an action, or None when the app has no binding for it
+    pub fn keys(self, action: PresentationAction) -> Option<(&'static str, &'static [&'static str])> {
+        use PresentationAction::*;
+        match (self, action) {
+            (_, Next) => Some(("right", &[])),
+            (_, Previous) => Some(("left", &[])),
+            (_, End) => Some(("end", &[])),
+            (DeckApp::Okular, Start) => Some(("p", &["ctrl", "shift"])),
+            // Browsers reload on F5, so decks are restarted from the first slide instead
+            (DeckApp::Browser, Start) => Some(("home", &[])),
+            (_, Start) => Some(("f5", &[])),
+            (DeckApp::Okular, Blank) => None,
+            (_, Blank) => Some(("b", &[])),
+            (_, Laser | LaserOff) => None,
+        }
+    }
+}
+
+struct PresentationState {
+    started_at: Option<Instant>,
+    slide: Option<u32>,
+    blanked: bool,
+}
+
+lazy_static::lazy_static! {
+    static ref STATE: Mutex<PresentationState> = Mutex::new(PresentationState {
+        started_at: None,
+        slide: None,
+        blanked: false,
+    });
+    static ref LASER: Mutex<Option<mpsc::Sender<LaserCommand>>> = Mutex::new(None);
+}
+
+// The deck app in the focused window; Generic when windows can't be inspected
+pub fn focused_app() -> DeckApp {
+    crate::windows::backend()
+        .and_then(|backend| backend.list())
+        .ok()
+        .and_then(|windows| windows.into_iter().find(|w| w.focused))
+        .map(|w| DeckApp::detect(w.app_id.as_deref().unwrap_or_default(), &w.title))
+        .unwrap_or(DeckApp::Generic)
+}
+
+pub fn status(app: Option<DeckApp>) -> PresentationResponse {
+    let state = STATE.lock().unwrap();
+    PresentationResponse {
+        status: "success".to_string(),
+        message: None,
+        app,
+        slide: state.slide,
+        elapsed_seconds: state.started_at.map(|t| t.elapsed().as_secs_f64()),
+        blanked: state.blanked,
+    }
+}
+
+// Runs one action for `client` (see input::lock_as); blocking, as it may talk to
+// the window manager and X server
+pub fn execute(request: &PresentationRequest, client: &str) -> Result<PresentationResponse, String> {
+    match request.action {
+        PresentationAction::Laser => {
+            laser(LaserCommand::Move(request.dx, request.dy))?;
+            return Ok(status(None));
+        }
+        PresentationAction::LaserOff => {
+            laser(LaserCommand::Hide)?;
+            return Ok(status(None));
+        }
+        _ => {}
+    }
+
+    let app = focused_app();
+    let (key, modifiers) = app
+        .keys(request.action)
+        .ok_or_else(|| format!("{:?} has no key for {:?}", app, request.action))?;
+    info!("📽️  {:?} on {:?}: {}", request.action, app, key);
+    let modifiers = modifiers.iter().map(|m| m.to_string()).collect();
+    crate::execute_key(&mut crate::input::lock_as(client), key, Some(modifiers)).map_err(|e| e.to_string())?;
+
+    let mut state = STATE.lock().unwrap();
+    match request.action {
+        PresentationAction::Start => {
+            state.started_at = Some(Instant::now());
+            state.slide = Some(1);
+            state.blanked = false;
+        }
+        PresentationAction::Next => state.slide = state.slide.map(|n| n + 1),
+        PresentationAction::Previous => state.slide = state.slide.map(|n| n.saturating_sub(1).max(1)),
+        PresentationAction::End => state.slide = None,
+        PresentationAction::Blank => state.blanked = !state.blanked,
+        PresentationAction::Laser | PresentationAction::LaserOff => {}
+    }
+    drop(state);
+    Ok(status(Some(app)))
+}
+
+// --- Laser pointer overlay ---
+
+enum LaserCommand {
+    Move(f32, f32),
+    Hide,
+}
+
+const LASER_SIZE: u16 = 28;
+const LASER_COLOR: u32 = 0xff2020;
+// The dot disappears after this long without movement
+const LASER_IDLE: Duration = Duration::from_secs(2);
+
+fn laser(command: LaserCommand) -> Result<(), String> {
+    let mut sender = LASER.lock().unwrap();
+    let command = match sender.as_ref() {
+        Some(tx) => match tx.send(command) {
+            Ok(()) => return Ok(()),
+            // The overlay thread is gone; start a new one
+            Err(mpsc::SendError(command)) => command,
+        },
+        None => command,
+    };
+    if let LaserCommand::Hide = command {
+        return Ok(());
+    }
+    let tx = spawn_overlay()?;
+    let _ = tx.send(command);
+    *sender = Some(tx);
+    Ok(())
+}
+
+// Rows of a filled circle, for the overlay's shape mask
+fn circle_rectangles(size: u16) -> Vec<Rectangle> {
+    let r = size as f32 / 2.0;
+    (0..size)
+        .map(|y| {
+            let dy = y as f32 + 0.5 - r;
+            let half = (r * r - dy * dy).max(0.0).sqrt();
+            Rectangle {
+                x: (r - half).round() as i16,
+                y: y as i16,
+                width: (half * 2.0).round() as u16,
+                height: 1,
+            }
+        })
+        .collect()
+}
+
+// Creates a round, click-through, always-on-top window and moves it on a
+// dedicated thread that owns the X connection
+fn spawn_overlay() -> Result<mpsc::Sender<LaserCommand>, String> {
+    let (conn, screen_num) = x11rb::connect(None).map_err(|e| format!("Laser overlay needs X11: {}", e))?;
+    let screen = &conn.setup().roots[screen_num];
+    let (root, screen_w, screen_h) = (screen.root, screen.width_in_pixels as f32, screen.height_in_pixels as f32);
+    let window = conn.generate_id().map_err(|e| e.to_string())?;
+    conn.create_window(
+        x11rb::COPY_DEPTH_FROM_PARENT,
+        window,
+        root,
+        0,
+        0,
+        LASER_SIZE,
+        LASER_SIZE,
+        0,
+        WindowClass::INPUT_OUTPUT,
+        x11rb::COPY_FROM_PARENT,
+        &CreateWindowAux::new().override_redirect(1).background_pixel(LASER_COLOR),
+    )
+    .map_err(|e| e.to_string())?;
+    conn.shape_rectangles(
+        shape::SO::SET,
+        shape::SK::BOUNDING,
+        ClipOrdering::UNSORTED,
+        window,
+        0,
+        0,
+        &circle_rectangles(LASER_SIZE),
+    )
+    .map_err(|e| e.to_string())?;
+    // An empty input region lets clicks through to the slides
+    conn.shape_rectangles(shape::SO::SET, shape::SK::INPUT, ClipOrdering::UNSORTED, window, 0, 0, &[])
+        .map_err(|e| e.to_string())?;
+    conn.flush().map_err(|e| e.to_string())?;
+
+    // Start from the real pointer so the dot appears where the presenter expects
+    let (mut x, mut y) = conn
+        .query_pointer(root)
+        .ok()
+        .and_then(|cookie| cookie.reply().ok())
+        .map(|p| (p.root_x as f32, p.root_y as f32))
+        .unwrap_or((screen_w / 2.0, screen_h / 2.0));
+
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut visible = false;
+        loop {
+            let command = match rx.recv_timeout(LASER_IDLE) {
+                Ok(command) => Some(command),
+                Err(mpsc::RecvTimeoutError::Timeout) => None,
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            };
+            match command {
+                Some(LaserCommand::Move(dx, dy)) => {
+                    x = (x + dx).clamp(0.0, screen_w - 1.0);
+                    y = (y + dy).clamp(0.0, screen_h - 1.0);
+                    let half = LASER_SIZE as f32 / 2.0;
+                    let aux = ConfigureWindowAux::new()
+                        .x((x - half) as i32)
+                        .y((y - half) as i32)
+                        .stack_mode(StackMode::ABOVE);
+                    let _ = conn.configure_window(window, &aux);
+                    if !visible {
+                        let _ = conn.map_window(window);
+                        visible = true;
+                    }
+                }
+                Some(LaserCommand::Hide) | None => {
+                    if visible {
+                        let _ = conn.unmap_window(window);
+                        visible = false;
+                    }
+                }
+            }
+            let _ = conn.flush();
+        }
+    });
+    info!("🔴 Laser overlay ready");
+    Ok(tx)
+}
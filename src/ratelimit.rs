@@ -0,0 +1,335 @@
+// Flood protection for the input routes
+//
+// Every client (a paired device, otherwise the remote IP) gets a token bucket per
+// route class. An empty bucket answers 429 with a retry hint. When the server has
+// refused more than ASTRA_RATE_PANIC requests within PANIC_WINDOW, something is
+// hammering it and the kill switch pauses all remote input until it is resumed on
+// this machine.
+use crate::error::ApiError;
+use crate::events::gui_log;
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+const PANIC_WINDOW: Duration = Duration::from_secs(10);
+const DEFAULT_PANIC_THRESHOLD: usize = 300;
+// Buckets untouched for this long are dropped
+const IDLE_BUCKET: Duration = Duration::from_secs(300);
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+// Slower rates are refused at startup: one request a day already means "never"
+// in practice, and the retry hint for an even smaller rate overflows a Duration
+const MIN_RATE: f64 = 1.0 / 86_400.0;
+// Retry hints are capped to this, whatever the rate
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(86_400);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RouteClass {
+    Pointer,
+    Keys,
+    Voice,
+    Launch,
+    // Pairing attempts, so a fixed pairing code cannot be guessed quickly
+    Pair,
+}
+
+impl RouteClass {
+    const ALL: [RouteClass; 5] = [
+        RouteClass::Pointer,
+        RouteClass::Keys,
+        RouteClass::Voice,
+        RouteClass::Launch,
+        RouteClass::Pair,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RouteClass::Pointer => "pointer",
+            RouteClass::Keys => "keys",
+            RouteClass::Voice => "voice",
+            RouteClass::Launch => "launch",
+            RouteClass::Pair => "pair",
+        }
+    }
+
+    // Requests per second and burst size when ASTRA_RATE_<CLASS> is not set
+    fn defaults(self) -> (f64, f64) {
+        match self {
+            // A touchpad streams at display refresh rate
+            RouteClass::Pointer => (250.0, 500.0),
+            RouteClass::Keys => (40.0, 80.0),
+            RouteClass::Voice => (2.0, 5.0),
+            RouteClass::Launch => (0.2, 3.0),
+            RouteClass::Pair => (0.1, 5.0),
+        }
+    }
+
+    // The class limiting a route; launching apps is limited by the voice handler
+    pub fn for_route(method: &Method, path: &str) -> Option<Self> {
+        match path {
+            "/mouse" | "/click" | "/scroll" => Some(RouteClass::Pointer),
+            // Laser pointer moves stream like pointer moves
+            "/presentation" if method == Method::POST => Some(RouteClass::Pointer),
+            "/key" | "/batch" | "/reset-input" => Some(RouteClass::Keys),
+            "/voice" => Some(RouteClass::Voice),
+            "/pair" => Some(RouteClass::Pair),
+            _ => None,
+        }
+    }
+}
+
+// Who is sending: buckets are keyed by `key`, `label` is for logs
+#[derive(Debug, Clone)]
+pub struct Client {
+    key: String,
+    pub label: String,
+}
+
+impl Client {
+    pub fn identify(device: Option<&crate::pairing::Device>, addr: SocketAddr) -> Self {
+        match device {
+            Some(device) => Self {
+                key: format!("device:{}", device.id),
+                label: format!("'{}' ({})", device.name, addr.ip()),
+            },
+            None => Self {
+                key: format!("ip:{}", addr.ip()),
+                label: addr.ip().to_string(),
+            },
+        }
+    }
+
+    // UDP pointer sessions share the buckets of their device
+    pub fn device(device_id: &str) -> Self {
+        Self {
+            key: format!("device:{}", device_id),
+            label: format!("UDP session of device {}", device_id),
+        }
+    }
+
+    // Also names the client's held keys and buttons (input::lock_as)
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Limit {
+    per_second: f64,
+    burst: f64,
+}
+
+impl Limit {
+    // ASTRA_RATE_KEYS=40 or ASTRA_RATE_KEYS=40/80 (rate/burst); 0 disables the limit
+    fn from_env(class: RouteClass) -> Self {
+        let (per_second, burst) = class.defaults();
+        let name = format!("ASTRA_RATE_{}", class.name().to_uppercase());
+        let Ok(value) = std::env::var(&name) else {
+            return Self { per_second, burst };
+        };
+        Self::parse(&value).unwrap_or_else(|| {
+            warn!(
+                "🚦 Ignoring invalid {}={:?}, expected <per second> or <per second>/<burst> (0, or at least one a day)",
+                name, value
+            );
+            Self { per_second, burst }
+        })
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        let valid_rate = |rate: f64| rate == 0.0 || (rate.is_finite() && rate >= MIN_RATE);
+        let mut parts = value.split('/').map(|part| part.trim().parse::<f64>());
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(rate)), None, None) if valid_rate(rate) => Some(Self {
+                per_second: rate,
+                burst: (rate * 2.0).max(1.0),
+            }),
+            (Some(Ok(rate)), Some(Ok(burst)), None) if valid_rate(rate) && burst.is_finite() && burst >= 1.0 => {
+                Some(Self { per_second: rate, burst })
+            }
+            _ => None,
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    // Set while refusing, so the GUI gets one line per flood rather than one per request
+    limited: bool,
+}
+
+struct Limiter {
+    limits: HashMap<RouteClass, Limit>,
+    buckets: HashMap<(String, RouteClass), Bucket>,
+    refusals: VecDeque<Instant>,
+    panic_threshold: usize,
+    last_sweep: Instant,
+}
+
+lazy_static::lazy_static! {
+    static ref LIMITER: Mutex<Limiter> = Mutex::new(Limiter::from_env());
+}
+
+impl Limiter {
+    fn from_env() -> Self {
+        Self {
+            limits: RouteClass::ALL.iter().map(|&class| (class, Limit::from_env(class))).collect(),
+            buckets: HashMap::new(),
+            refusals: VecDeque::new(),
+            panic_threshold: crate::config::env_parse("ASTRA_RATE_PANIC", DEFAULT_PANIC_THRESHOLD),
+            last_sweep: Instant::now(),
+        }
+    }
+
+    // All or nothing: every bucket is refilled and checked before any is charged,
+    // so a refused batch costs nothing
+    fn take(&mut self, client: &Client, charges: &[(RouteClass, usize)]) -> Result<(), ApiError> {
+        let now = Instant::now();
+        if now.duration_since(self.last_sweep) >= SWEEP_INTERVAL {
+            self.buckets.retain(|_, bucket| now.duration_since(bucket.updated) < IDLE_BUCKET);
+            self.last_sweep = now;
+        }
+
+        let mut accepted = Vec::with_capacity(charges.len());
+        for &(class, cost) in charges {
+            let limit = self.limits[&class];
+            if cost == 0 || limit.per_second <= 0.0 {
+                continue;
+            }
+            let bucket = self.buckets.entry((client.key.clone(), class)).or_insert(Bucket {
+                tokens: limit.burst,
+                updated: now,
+                limited: false,
+            });
+            let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * limit.per_second).min(limit.burst);
+            bucket.updated = now;
+
+            // A single request larger than the burst (a long batch) drains a full bucket
+            let cost = (cost as f64).min(limit.burst);
+            if bucket.tokens >= cost {
+                accepted.push((class, cost));
+                continue;
+            }
+
+            let retry_after = Duration::try_from_secs_f64((cost - bucket.tokens) / limit.per_second)
+                .map_or(MAX_RETRY_AFTER, |retry_after| retry_after.min(MAX_RETRY_AFTER));
+            if !bucket.limited {
+                bucket.limited = true;
+                warn!("🚦 Rate limiting {} on {} requests", client.label, class.name());
+                gui_log(format!("🚦 Rate limited {} ({})", client.label, class.name()));
+            }
+            crate::metrics::rate_limited(class.name());
+            // Guessing pairing codes is no input flood; pausing would only let it lock out everyone
+            if class != RouteClass::Pair {
+                self.record_refusal(now);
+            }
+            return Err(ApiError::RateLimited { retry_after });
+        }
+
+        for (class, cost) in accepted {
+            if let Some(bucket) = self.buckets.get_mut(&(client.key.clone(), class)) {
+                bucket.tokens -= cost;
+                if bucket.limited {
+                    bucket.limited = false;
+                    info!("🚦 {} is back under the {} limit", client.label, class.name());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn record_refusal(&mut self, now: Instant) {
+        if self.panic_threshold == 0 {
+            return;
+        }
+        self.refusals.push_back(now);
+        while self
+            .refusals
+            .front()
+            .is_some_and(|&at| now.duration_since(at) > PANIC_WINDOW)
+        {
+            self.refusals.pop_front();
+        }
+        if self.refusals.len() >= self.panic_threshold {
+            self.refusals.clear();
+            crate::killswitch::pause(format!(
+                "{} requests refused within {} seconds",
+                self.panic_threshold,
+                PANIC_WINDOW.as_secs()
+            ));
+        }
+    }
+}
+
+pub fn log_limits() {
+    let limiter = LIMITER.lock().unwrap();
+    let limits: Vec<String> = RouteClass::ALL
+        .iter()
+        .map(|class| {
+            let limit = limiter.limits[class];
+            if limit.per_second <= 0.0 {
+                format!("{} unlimited", class.name())
+            } else {
+                format!("{} {}/s (burst {})", class.name(), limit.per_second, limit.burst)
+            }
+        })
+        .collect();
+    info!("🚦 Rate limits: {}", limits.join(", "));
+}
+
+// Charges `cost` requests of `class` to the client
+pub fn check(client: &Client, class: RouteClass, cost: usize) -> Result<(), ApiError> {
+    check_all(client, &[(class, cost)])
+}
+
+// Charges several classes at once, e.g. the steps of a batch; nothing is charged
+// unless every class has room
+pub fn check_all(client: &Client, charges: &[(RouteClass, usize)]) -> Result<(), ApiError> {
+    LIMITER.lock().unwrap().take(client, charges)
+}
+
+// Identifies the client for handlers (as an Extension<Client>) and applies the
+// route's limit; /batch is charged per step, to each step's class, by its handler
+// once the body is parsed
+pub async fn middleware(ConnectInfo(addr): ConnectInfo<SocketAddr>, mut request: Request, next: Next) -> Response {
+    let client = Client::identify(crate::pairing::Caller::of(&request), addr);
+    if let Some(class) = RouteClass::for_route(request.method(), request.uri().path()) {
+        let cost = if request.uri().path() == "/batch" { 0 } else { 1 };
+        if let Err(e) = check(&client, class, cost) {
+            return e.into_response();
+        }
+    }
+    request.extensions_mut().insert(client);
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_parse_with_an_optional_burst() {
+        let limit = Limit::parse("40").unwrap();
+        assert_eq!((limit.per_second, limit.burst), (40.0, 80.0));
+        let limit = Limit::parse(" 20 / 40 ").unwrap();
+        assert_eq!((limit.per_second, limit.burst), (20.0, 40.0));
+        let limit = Limit::parse("0").unwrap();
+        assert_eq!(limit.per_second, 0.0);
+    }
+
+    #[test]
+    fn unusable_rates_are_refused() {
+        for value in ["1e-300", "-1", "NaN", "inf", "5/0.5", "5/inf", "1/2/3", "fast"] {
+            assert!(Limit::parse(value).is_none(), "{} was accepted", value);
+        }
+    }
+}
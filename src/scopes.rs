@@ -0,0 +1,189 @@
+// Per-device permissions
+//
+// Every paired device has a set of scopes (all of them unless narrowed in the GUI or
+// through /devices). The middleware checks the scope a route needs before the
+// handler runs; /batch and /voice check per step or command in their handlers, and
+// the event stream and UDP channel filter what they deliver. Media and volume need
+// no scope, so a device with none left is a media remote. Unpaired clients get every
+// scope unless pairing is required, in which case they get 401. Pairing is required
+// with ASTRA_REQUIRE_PAIRING, and as soon as any device's scopes are narrowed:
+// otherwise that device could drop its token and do everything unpaired.
+use crate::error::ApiError;
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::{header, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use tokio::sync::watch;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    // Pointer moves, clicks, scrolling, the UDP channel and window actions
+    Pointer,
+    // Key presses and shortcuts, presentation control
+    Keyboard,
+    // Typing text ("type ..." voice commands, single-character keys)
+    Text,
+    Voice,
+    // Opening apps ("open ..." voice commands)
+    Launch,
+    // Reserved for running commands; no route needs it yet
+    Shell,
+    Clipboard,
+    Files,
+    // Screen preview and the window list
+    Screen,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 9] = [
+        Scope::Pointer,
+        Scope::Keyboard,
+        Scope::Text,
+        Scope::Voice,
+        Scope::Launch,
+        Scope::Shell,
+        Scope::Clipboard,
+        Scope::Files,
+        Scope::Screen,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Scope::Pointer => "pointer",
+            Scope::Keyboard => "keyboard",
+            Scope::Text => "text",
+            Scope::Voice => "voice",
+            Scope::Launch => "launch",
+            Scope::Shell => "shell",
+            Scope::Clipboard => "clipboard",
+            Scope::Files => "files",
+            Scope::Screen => "screen",
+        }
+    }
+
+    // What a route needs before its handler runs
+    pub fn for_route(method: &Method, path: &str) -> Option<Self> {
+        match path {
+            "/mouse" | "/click" | "/scroll" | "/udp/handshake" => Some(Scope::Pointer),
+            "/windows" if method == Method::POST => Some(Scope::Pointer),
+            "/windows" | "/screen" => Some(Scope::Screen),
+            // With the recording backend it lists what was typed
+            "/key" | "/input" => Some(Scope::Keyboard),
+            "/presentation" if method == Method::POST => Some(Scope::Keyboard),
+            "/voice" => Some(Scope::Voice),
+            "/clipboard" => Some(Scope::Clipboard),
+            path if path == "/upload" || path.starts_with("/upload/") => Some(Scope::Files),
+            _ => None,
+        }
+    }
+}
+
+// A key without modifiers (other than shift) that produces a character is typing:
+// it needs the text scope on top of keyboard, and the audit log redacts it
+pub fn is_typing<S: AsRef<str>>(key: &str, modifiers: &[S]) -> bool {
+    key.chars().count() == 1 && modifiers.iter().all(|m| m.as_ref().eq_ignore_ascii_case("shift"))
+}
+
+pub fn all() -> Vec<Scope> {
+    Scope::ALL.to_vec()
+}
+
+lazy_static::lazy_static! {
+    // Whether pairing is required, republished whenever the paired devices change.
+    // Starts out false rather than reading the store, which the publisher holds
+    // locked; subscribers compare against the state they last acted on
+    static ref PAIRING_REQUIRED: watch::Sender<bool> = watch::channel(false).0;
+}
+
+// Called by the pairing store after every change to its devices
+pub fn publish_pairing_required(store: &crate::pairing::PairingStore) {
+    let required = pairing_required_with(store);
+    PAIRING_REQUIRED.send_if_modified(|current| std::mem::replace(current, required) != required);
+}
+
+pub fn watch_pairing_required() -> watch::Receiver<bool> {
+    PAIRING_REQUIRED.subscribe()
+}
+
+pub fn pairing_required() -> bool {
+    pairing_required_with(&crate::pairing::PAIRING.lock().unwrap())
+}
+
+// For callers already holding the pairing store
+pub fn pairing_required_with(store: &crate::pairing::PairingStore) -> bool {
+    crate::config::env_flag("ASTRA_REQUIRE_PAIRING", false) || store.has_restricted_device()
+}
+
+// Reachable without a device even when pairing is required; /events checks its
+// ?token= itself and /metrics has its own ASTRA_METRICS setting
+fn is_public(path: &str) -> bool {
+    matches!(
+        path,
+        "/" | "/app.js" | "/style.css" | "/ping" | "/openapi.json" | "/pair" | "/events" | "/metrics"
+    )
+}
+
+// Loopback-only admin routes check the caller themselves
+pub fn is_admin(path: &str) -> bool {
+    matches!(path, "/pause" | "/resume" | "/shutdown" | "/devices" | "/latency") || path.starts_with("/devices/")
+}
+
+// What the requesting client may do, for handlers that check per step or command
+#[derive(Debug, Clone)]
+pub struct Grant {
+    // None for unpaired clients, which are allowed everything
+    device: Option<(String, Vec<Scope>)>,
+}
+
+impl Grant {
+    pub fn require(&self, scope: Scope) -> Result<(), ApiError> {
+        match &self.device {
+            Some((name, scopes)) if !scopes.contains(&scope) => Err(ApiError::MissingScope {
+                scope: scope.name(),
+                device: name.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Whether a paired device currently has `scope`; for connections that outlive a request
+pub fn device_has(device_id: &str, scope: Scope) -> bool {
+    crate::pairing::PAIRING
+        .lock()
+        .unwrap()
+        .find(device_id)
+        .is_some_and(|device| device.scopes.contains(&scope))
+}
+
+pub async fn middleware(ConnectInfo(addr): ConnectInfo<SocketAddr>, mut request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+    // Loopback is not enough for admin routes: any web page open on this machine
+    // could reach them. Browsers always send Origin on such requests; the GUI,
+    // astra-ctl and curl do not.
+    if is_admin(path) && request.headers().contains_key(header::ORIGIN) {
+        return ApiError::Forbidden("Admin routes do not accept requests from web pages".to_string()).into_response();
+    }
+    let grant = match crate::pairing::Caller::of(&request) {
+        Some(device) => Grant {
+            device: Some((device.name.clone(), device.scopes.clone())),
+        },
+        None if pairing_required() && !is_public(path) && !(is_admin(path) && addr.ip().is_loopback()) => {
+            return ApiError::Unauthorized.into_response();
+        }
+        None => Grant { device: None },
+    };
+    if let Some(scope) = Scope::for_route(request.method(), path) {
+        if let Err(e) = grant.require(scope) {
+            return e.into_response();
+        }
+    }
+    request.extensions_mut().insert(grant);
+    next.run(request).await
+}
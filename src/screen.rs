@@ -0,0 +1,489 @@
+// Opt-in screen preview for the phone client: single frames or a low-framerate MJPEG stream
+//
+// X11 frames are grabbed with MIT-SHM (which also works under Xvfb). With the
+// `screencast` cargo feature, Wayland frames come from the xdg-desktop-portal
+// ScreenCast session and anything else falls back to one-shot screenshots.
+use axum::body::{Body, Bytes};
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::io::Cursor;
+#[cfg(feature = "screencast")]
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::info;
+use x11rb::connection::Connection;
+use x11rb::protocol::randr::ConnectionExt as _;
+use x11rb::protocol::shm::ConnectionExt as _;
+use x11rb::protocol::xproto::{ConnectionExt as _, ImageFormat};
+use x11rb::rust_connection::RustConnection;
+
+pub const BOUNDARY: &str = "astraframe";
+const MIN_QUALITY: u8 = 20;
+
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+pub struct ScreenQuery {
+    // Index into the monitor list, default 0 (primary first)
+    #[serde(default)]
+    pub monitor: usize,
+    // Optional region in monitor coordinates
+    pub x: Option<u32>,
+    pub y: Option<u32>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    #[serde(default = "default_format")]
+    pub format: String,
+    #[serde(default = "default_quality")]
+    pub quality: u8,
+    #[serde(default = "default_max_width")]
+    pub max_width: u32,
+    #[serde(default = "default_fps")]
+    pub fps: f32,
+    #[serde(default = "default_cursor")]
+    pub cursor: bool,
+    // Stream as multipart/x-mixed-replace instead of a single frame
+    #[serde(default)]
+    pub stream: bool,
+}
+
+fn default_format() -> String {
+    "jpeg".to_string()
+}
+
+fn default_quality() -> u8 {
+    60
+}
+
+fn default_max_width() -> u32 {
+    1280
+}
+
+fn default_fps() -> f32 {
+    2.0
+}
+
+fn default_cursor() -> bool {
+    true
+}
+
+pub fn enabled() -> bool {
+    crate::config::env_flag("ASTRA_SCREEN", false)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Geometry {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+// MIT-SHM grabber: the server writes each frame straight into a shared segment
+struct XShmGrabber {
+    conn: RustConnection,
+    root: u32,
+    seg: u32,
+    map: memmap2::Mmap,
+}
+
+impl XShmGrabber {
+    fn new(geometry: Geometry) -> Result<Self, String> {
+        let (conn, screen_num) = x11rb::connect(None).map_err(|e| format!("X11 connection failed: {}", e))?;
+        let root = conn.setup().roots[screen_num].root;
+        conn.shm_query_version()
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| format!("MIT-SHM unavailable: {}", e))?;
+
+        let size = geometry.width * geometry.height * 4;
+        let seg = conn.generate_id().map_err(|e| e.to_string())?;
+        let reply = conn
+            .shm_create_segment(seg, size, false)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| format!("Failed to create SHM segment: {}", e))?;
+        let file = std::fs::File::from(reply.shm_fd);
+        // SAFETY: the segment is only written by the X server during shm_get_image,
+        // and we only read it after that request has completed
+        let map = unsafe { memmap2::MmapOptions::new().len(size as usize).map(&file) }
+            .map_err(|e| format!("Failed to map SHM segment: {}", e))?;
+        Ok(Self { conn, root, seg, map })
+    }
+
+    fn grab(&mut self, geometry: Geometry) -> Result<RgbaImage, String> {
+        let reply = self
+            .conn
+            .shm_get_image(
+                self.root,
+                geometry.x as i16,
+                geometry.y as i16,
+                geometry.width as u16,
+                geometry.height as u16,
+                !0,
+                ImageFormat::Z_PIXMAP.into(),
+                self.seg,
+                0,
+            )
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| format!("SHM grab failed: {}", e))?;
+        if reply.depth < 24 {
+            return Err(format!("Unsupported X11 depth {}", reply.depth));
+        }
+
+        // 32bpp ZPixmap on little endian is BGRX
+        let pixels = (geometry.width * geometry.height) as usize;
+        let mut rgba = Vec::with_capacity(pixels * 4);
+        for bgrx in self.map[..pixels * 4].chunks_exact(4) {
+            rgba.extend_from_slice(&[bgrx[2], bgrx[1], bgrx[0], 255]);
+        }
+        RgbaImage::from_raw(geometry.width, geometry.height, rgba).ok_or_else(|| "Bad frame size".to_string())
+    }
+
+    fn cursor(&self) -> Option<(i32, i32)> {
+        let reply = self.conn.query_pointer(self.root).ok()?.reply().ok()?;
+        Some((reply.root_x as i32, reply.root_y as i32))
+    }
+}
+
+impl Drop for XShmGrabber {
+    fn drop(&mut self) {
+        let _ = self.conn.shm_detach(self.seg);
+        let _ = self.conn.flush();
+    }
+}
+
+enum Source {
+    XShm(XShmGrabber),
+    // Portal ScreenCast session; frames arrive on the receiver
+    #[cfg(feature = "screencast")]
+    Screencast(xcap::VideoRecorder, Receiver<xcap::Frame>),
+    #[cfg(feature = "screencast")]
+    Screenshot(xcap::Monitor),
+}
+
+// RandR monitors, primary first
+fn x11_monitor(monitor_index: usize) -> Result<Geometry, String> {
+    let (conn, screen_num) = x11rb::connect(None).map_err(|e| format!("X11 connection failed: {}", e))?;
+    let root = conn.setup().roots[screen_num].root;
+    let mut monitors = conn
+        .randr_get_monitors(root, true)
+        .map_err(|e| e.to_string())?
+        .reply()
+        .map_err(|e| format!("Failed to list monitors: {}", e))?
+        .monitors;
+    monitors.sort_by_key(|m| !m.primary);
+    let count = monitors.len();
+    let monitor = monitors
+        .get(monitor_index)
+        .ok_or_else(|| format!("Monitor {} does not exist ({} found)", monitor_index, count))?;
+    Ok(Geometry {
+        x: monitor.x as i32,
+        y: monitor.y as i32,
+        width: monitor.width as u32,
+        height: monitor.height as u32,
+    })
+}
+
+struct Capture {
+    source: Source,
+    geometry: Geometry,
+}
+
+impl Capture {
+    #[cfg(not(feature = "screencast"))]
+    fn open(monitor_index: usize) -> Result<Self, String> {
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() || std::env::var_os("DISPLAY").is_none() {
+            return Err("Screen capture needs X11 (Wayland needs a build with --features screencast)".to_string());
+        }
+        let geometry = x11_monitor(monitor_index)?;
+        Ok(Self {
+            source: Source::XShm(XShmGrabber::new(geometry)?),
+            geometry,
+        })
+    }
+
+    #[cfg(feature = "screencast")]
+    fn open(monitor_index: usize) -> Result<Self, String> {
+        let mut monitors = xcap::Monitor::all().map_err(|e| format!("Failed to list monitors: {}", e))?;
+        monitors.sort_by_key(|m| !m.is_primary().unwrap_or(false));
+        let count = monitors.len();
+        if monitor_index >= count {
+            return Err(format!("Monitor {} does not exist ({} found)", monitor_index, count));
+        }
+        let monitor = monitors.swap_remove(monitor_index);
+        let geometry = Geometry {
+            x: monitor.x().map_err(|e| e.to_string())?,
+            y: monitor.y().map_err(|e| e.to_string())?,
+            width: monitor.width().map_err(|e| e.to_string())?,
+            height: monitor.height().map_err(|e| e.to_string())?,
+        };
+
+        let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+        if !wayland && std::env::var_os("DISPLAY").is_some() {
+            match XShmGrabber::new(geometry) {
+                Ok(grabber) => {
+                    return Ok(Self {
+                        source: Source::XShm(grabber),
+                        geometry,
+                    })
+                }
+                Err(e) => info!("🖥️  XShm capture unavailable ({}), using screenshots", e),
+            }
+        }
+        if wayland {
+            match monitor.video_recorder() {
+                Ok((recorder, frames)) => match recorder.start() {
+                    Ok(()) => {
+                        return Ok(Self {
+                            source: Source::Screencast(recorder, frames),
+                            geometry,
+                        })
+                    }
+                    Err(e) => info!("🖥️  ScreenCast portal refused ({}), using screenshots", e),
+                },
+                Err(e) => info!("🖥️  ScreenCast portal unavailable ({}), using screenshots", e),
+            }
+        }
+        Ok(Self {
+            source: Source::Screenshot(monitor),
+            geometry,
+        })
+    }
+
+    fn grab(&mut self) -> Result<(RgbaImage, Option<(i32, i32)>), String> {
+        match &mut self.source {
+            Source::XShm(grabber) => {
+                let frame = grabber.grab(self.geometry)?;
+                let cursor = grabber
+                    .cursor()
+                    .map(|(x, y)| (x - self.geometry.x, y - self.geometry.y));
+                Ok((frame, cursor))
+            }
+            #[cfg(feature = "screencast")]
+            Source::Screencast(_, frames) => {
+                // Use the newest frame and drop any backlog
+                let mut frame = frames
+                    .recv_timeout(Duration::from_secs(2))
+                    .map_err(|e| format!("No frame from ScreenCast: {}", e))?;
+                while let Ok(newer) = frames.try_recv() {
+                    frame = newer;
+                }
+                let image = RgbaImage::from_raw(frame.width, frame.height, frame.raw)
+                    .ok_or_else(|| "Bad frame size".to_string())?;
+                // The portal embeds the cursor itself
+                Ok((image, None))
+            }
+            #[cfg(feature = "screencast")]
+            Source::Screenshot(monitor) => {
+                let image = monitor.capture_image().map_err(|e| format!("Screenshot failed: {}", e))?;
+                Ok((image, None))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "screencast")]
+impl Drop for Capture {
+    fn drop(&mut self) {
+        if let Source::Screencast(recorder, _) = &self.source {
+            let _ = recorder.stop();
+        }
+    }
+}
+
+fn draw_cursor(image: &mut RgbaImage, x: i32, y: i32) {
+    // A red ring with a white outline, readable on light and dark backgrounds
+    let (width, height) = (image.width() as i32, image.height() as i32);
+    for dy in -9i32..=9 {
+        for dx in -9i32..=9 {
+            let (px, py) = (x + dx, y + dy);
+            if px < 0 || py < 0 || px >= width || py >= height {
+                continue;
+            }
+            let d2 = dx * dx + dy * dy;
+            let color = if (36..=49).contains(&d2) {
+                Rgba([255, 40, 40, 255])
+            } else if (50..=81).contains(&d2) || d2 <= 4 {
+                Rgba([255, 255, 255, 255])
+            } else {
+                continue;
+            };
+            image.put_pixel(px as u32, py as u32, color);
+        }
+    }
+}
+
+// Crops, overlays the cursor, scales down and encodes one frame
+fn render(
+    frame: RgbaImage,
+    cursor: Option<(i32, i32)>,
+    query: &ScreenQuery,
+    quality: u8,
+) -> Result<(Vec<u8>, &'static str), String> {
+    let mut image = frame;
+    if let Some((cx, cy)) = cursor.filter(|_| query.cursor) {
+        draw_cursor(&mut image, cx, cy);
+    }
+    if let (Some(w), Some(h)) = (query.width, query.height) {
+        let x = query.x.unwrap_or(0).min(image.width().saturating_sub(1));
+        let y = query.y.unwrap_or(0).min(image.height().saturating_sub(1));
+        let w = w.clamp(1, image.width() - x);
+        let h = h.clamp(1, image.height() - y);
+        image = imageops::crop_imm(&image, x, y, w, h).to_image();
+    }
+    let max_width = query.max_width.clamp(160, 3840);
+    if image.width() > max_width {
+        let height = (image.height() as u64 * max_width as u64 / image.width() as u64).max(1) as u32;
+        image = imageops::resize(&image, max_width, height, imageops::FilterType::Triangle);
+    }
+
+    let mut out = Vec::new();
+    if query.format == "png" {
+        image
+            .write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png)
+            .map_err(|e| format!("PNG encoding failed: {}", e))?;
+        Ok((out, "image/png"))
+    } else {
+        let rgb = DynamicImage::ImageRgba8(image).to_rgb8();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, quality.clamp(MIN_QUALITY, 95))
+            .encode_image(&rgb)
+            .map_err(|e| format!("JPEG encoding failed: {}", e))?;
+        Ok((out, "image/jpeg"))
+    }
+}
+
+// Blocking: captures and encodes a single frame
+pub fn snapshot(query: &ScreenQuery) -> Result<(Vec<u8>, &'static str), String> {
+    let mut capture = Capture::open(query.monitor)?;
+    let (frame, cursor) = capture.grab()?;
+    render(frame, cursor, query, query.quality)
+}
+
+// Starts a capture thread feeding multipart MJPEG parts; it stops when the client goes away
+pub fn stream(query: ScreenQuery) -> Result<Body, String> {
+    let mut capture = Capture::open(query.monitor)?;
+    let (tx, rx) = mpsc::channel::<Result<Bytes, std::io::Error>>(1);
+    let interval = Duration::from_secs_f32(1.0 / query.fps.clamp(0.2, 10.0));
+
+    std::thread::spawn(move || {
+        let _session = crate::metrics::session(crate::metrics::Session::Screen);
+        let mut quality = query.quality.clamp(MIN_QUALITY, 95);
+        loop {
+            let started = Instant::now();
+            let part = capture.grab().and_then(|(frame, cursor)| render(frame, cursor, &query, quality));
+            let (data, content_type) = match part {
+                Ok(part) => part,
+                Err(e) => {
+                    info!("🖥️  Screen stream stopped: {}", e);
+                    return;
+                }
+            };
+            let mut chunk = format!(
+                "--{}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+                BOUNDARY,
+                content_type,
+                data.len()
+            )
+            .into_bytes();
+            chunk.extend_from_slice(&data);
+            chunk.extend_from_slice(b"\r\n");
+            if crate::shutdown::is_requested() {
+                info!("🖥️  Screen stream closed for shutdown");
+                return;
+            }
+            // The channel holds one frame, so a slow client makes this wait
+            if tx.blocking_send(Ok(Bytes::from(chunk))).is_err() {
+                info!("🖥️  Screen stream client disconnected");
+                return;
+            }
+
+            // Adapt quality to what the link sustains
+            let elapsed = started.elapsed();
+            if elapsed > interval {
+                quality = quality.saturating_sub(10).max(MIN_QUALITY);
+            } else if elapsed < interval / 2 && quality < query.quality {
+                quality = (quality + 5).min(query.quality);
+            }
+            if let Some(rest) = interval.checked_sub(elapsed) {
+                std::thread::sleep(rest);
+            }
+        }
+    });
+
+    Ok(Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(rx)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+
+    fn query(json: serde_json::Value) -> ScreenQuery {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn dimensions(png: &[u8]) -> (u32, u32) {
+        let image = image::load_from_memory_with_format(png, image::ImageFormat::Png).unwrap();
+        (image.width(), image.height())
+    }
+
+    #[test]
+    fn renders_crops_and_scales() {
+        let frame = RgbaImage::from_pixel(1920, 1080, Rgba([10, 20, 30, 255]));
+        let (png, content_type) = render(frame.clone(), None, &query(serde_json::json!({ "format": "png" })), 60).unwrap();
+        assert_eq!(content_type, "image/png");
+        assert_eq!(dimensions(&png), (1280, 720));
+
+        let region = query(serde_json::json!({ "format": "png", "x": 100, "y": 50, "width": 400, "height": 300 }));
+        let (png, _) = render(frame.clone(), Some((150, 80)), &region, 60).unwrap();
+        assert_eq!(dimensions(&png), (400, 300));
+
+        let (jpeg, content_type) = render(frame, None, &query(serde_json::json!({})), 60).unwrap();
+        assert_eq!(content_type, "image/jpeg");
+        assert_eq!(&jpeg[..2], &[0xFF, 0xD8]);
+    }
+
+    struct Xvfb(Child);
+
+    impl Drop for Xvfb {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    // Starts Xvfb on a free display and points DISPLAY at it
+    fn xvfb(width: u32, height: u32) -> Xvfb {
+        let mut child = Command::new("Xvfb")
+            .args(["-displayfd", "1", "-nolisten", "tcp", "-screen", "0"])
+            .arg(format!("{}x{}x24", width, height))
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Xvfb is installed");
+        // Written once the server accepts connections
+        let mut display = String::new();
+        BufReader::new(child.stdout.take().unwrap()).read_line(&mut display).unwrap();
+        std::env::set_var("DISPLAY", format!(":{}", display.trim()));
+        std::env::remove_var("WAYLAND_DISPLAY");
+        Xvfb(child)
+    }
+
+    #[test]
+    #[ignore = "needs Xvfb"]
+    fn captures_under_xvfb() {
+        let _server = xvfb(800, 600);
+        let (png, content_type) = snapshot(&query(serde_json::json!({ "format": "png", "cursor": false }))).unwrap();
+        assert_eq!(content_type, "image/png");
+        assert_eq!(dimensions(&png), (800, 600));
+
+        let (jpeg, _) = snapshot(&query(serde_json::json!({ "max_width": 400 }))).unwrap();
+        let image = image::load_from_memory(&jpeg).unwrap();
+        assert_eq!((image.width(), image.height()), (400, 300));
+
+        let error = snapshot(&query(serde_json::json!({ "monitor": 5 }))).unwrap_err();
+        assert!(error.contains("does not exist"), "{}", error);
+    }
+}
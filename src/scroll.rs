@@ -0,0 +1,220 @@
+// Smooth scrolling: fractional deltas, high-resolution wheel events and kinetic momentum
+use crate::input::Input;
+use evdev::uinput::{VirtualDevice, VirtualDeviceBuilder};
+use evdev::{AttributeSet, EventType, InputEvent, Key, RelativeAxisType};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::info;
+
+// One wheel detent in REL_WHEEL_HI_RES units (fixed by the kernel ABI)
+const HI_RES_PER_DETENT: i32 = 120;
+
+// Momentum tuning: velocities are in detents per second
+const MOMENTUM_TICK: Duration = Duration::from_millis(16);
+const MOMENTUM_TIME_CONSTANT: f32 = 0.325;
+const MOMENTUM_MIN_VELOCITY: f32 = 0.5;
+const MOMENTUM_MAX_VELOCITY: f32 = 200.0;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Axis {
+    Vertical,
+    Horizontal,
+}
+
+impl Axis {
+    fn index(self) -> usize {
+        match self {
+            Axis::Vertical => 0,
+            Axis::Horizontal => 1,
+        }
+    }
+}
+
+// Parses a request direction into an axis and a sign (+1 = up/right)
+pub fn parse_direction(direction: &str) -> Option<(Axis, f32)> {
+    match direction {
+        "up" => Some((Axis::Vertical, 1.0)),
+        "down" => Some((Axis::Vertical, -1.0)),
+        "left" => Some((Axis::Horizontal, -1.0)),
+        "right" => Some((Axis::Horizontal, 1.0)),
+        _ => None,
+    }
+}
+
+struct ScrollState {
+    // Virtual wheel device, None when uinput is unavailable (falls back to enigo)
+    wheel: Option<VirtualDevice>,
+    probed: bool,
+    // Sub-unit remainders so that small deltas add up instead of being dropped
+    remainder: [f32; 2],
+    // Hi-res units not yet reported as a legacy detent
+    hi_res_pending: [i32; 2],
+}
+
+lazy_static::lazy_static! {
+    static ref SCROLL: Mutex<ScrollState> = Mutex::new(ScrollState {
+        wheel: None,
+        probed: false,
+        remainder: [0.0; 2],
+        hi_res_pending: [0; 2],
+    });
+}
+
+// Also how the kill switch recognises, and skips, the device among /dev/input
+pub const WHEEL_DEVICE_NAME: &str = "Astra Remote Wheel";
+
+// Bumped on every new touch; a running momentum task stops when it sees a newer value
+static MOMENTUM_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+fn create_wheel_device() -> std::io::Result<VirtualDevice> {
+    let mut axes = AttributeSet::<RelativeAxisType>::new();
+    axes.insert(RelativeAxisType::REL_X);
+    axes.insert(RelativeAxisType::REL_Y);
+    axes.insert(RelativeAxisType::REL_WHEEL);
+    axes.insert(RelativeAxisType::REL_HWHEEL);
+    axes.insert(RelativeAxisType::REL_WHEEL_HI_RES);
+    axes.insert(RelativeAxisType::REL_HWHEEL_HI_RES);
+
+    // libinput only treats the device as a pointer if it also has buttons
+    let mut keys = AttributeSet::<Key>::new();
+    keys.insert(Key::BTN_LEFT);
+    keys.insert(Key::BTN_RIGHT);
+    keys.insert(Key::BTN_MIDDLE);
+
+    VirtualDeviceBuilder::new()?
+        .name(WHEEL_DEVICE_NAME)
+        .with_relative_axes(&axes)?
+        .with_keys(&keys)?
+        .build()
+}
+
+impl ScrollState {
+    fn ensure_probed(&mut self) {
+        if self.probed {
+            return;
+        }
+        self.probed = true;
+        if !crate::config::env_flag("ASTRA_HIRES_SCROLL", true) {
+            info!("📜 High-resolution scrolling disabled, using enigo");
+            return;
+        }
+        match create_wheel_device() {
+            Ok(device) => {
+                info!("📜 High-resolution scrolling enabled via uinput");
+                self.wheel = Some(device);
+            }
+            Err(e) => {
+                info!("📜 uinput unavailable ({}), falling back to enigo scrolling", e);
+            }
+        }
+    }
+
+    fn scroll_hi_res(&mut self, axis: Axis, detents: f32) -> Result<(), String> {
+        let i = axis.index();
+        let exact = detents * HI_RES_PER_DETENT as f32 + self.remainder[i];
+        let units = exact.trunc() as i32;
+        self.remainder[i] = exact - units as f32;
+        if units == 0 {
+            return Ok(());
+        }
+
+        // Legacy clients only listen to REL_WHEEL, so report whole detents alongside
+        self.hi_res_pending[i] += units;
+        let legacy = self.hi_res_pending[i] / HI_RES_PER_DETENT;
+        self.hi_res_pending[i] -= legacy * HI_RES_PER_DETENT;
+
+        let (hi_res_axis, legacy_axis) = match axis {
+            Axis::Vertical => (RelativeAxisType::REL_WHEEL_HI_RES, RelativeAxisType::REL_WHEEL),
+            Axis::Horizontal => (RelativeAxisType::REL_HWHEEL_HI_RES, RelativeAxisType::REL_HWHEEL),
+        };
+        let mut events = vec![InputEvent::new(EventType::RELATIVE, hi_res_axis.0, units)];
+        if legacy != 0 {
+            events.push(InputEvent::new(EventType::RELATIVE, legacy_axis.0, legacy));
+        }
+
+        let device = self.wheel.as_mut().expect("hi-res scroll without a wheel device");
+        device.emit(&events).map_err(|e| format!("Failed to emit scroll event: {}", e))
+    }
+
+    fn scroll_coarse(&mut self, input: &mut Input, axis: Axis, detents: f32) -> Result<(), String> {
+        let i = axis.index();
+        let exact = detents + self.remainder[i];
+        let steps = exact.trunc() as i32;
+        self.remainder[i] = exact - steps as f32;
+        if steps == 0 {
+            return Ok(());
+        }
+
+        match axis {
+            Axis::Vertical => input.mouse_scroll_y(steps),
+            Axis::Horizontal => input.mouse_scroll_x(steps),
+        }
+        Ok(())
+    }
+}
+
+// Creates the virtual wheel at startup: libinput takes a moment to pick up a new
+// device and drops what it sends before that, which would eat the first scroll
+pub fn init() {
+    SCROLL.lock().unwrap().ensure_probed();
+}
+
+// Scrolls by a fractional number of detents; positive is up/right
+pub fn scroll(axis: Axis, detents: f32) -> Result<(), String> {
+    scroll_with(&mut crate::input::lock(), axis, detents)
+}
+
+// Same as `scroll` for callers already holding the input lock.
+// Lock order is always INPUT, then SCROLL.
+pub fn scroll_with(input: &mut Input, axis: Axis, detents: f32) -> Result<(), String> {
+    if !detents.is_finite() {
+        return Err(format!("Invalid scroll amount: {}", detents));
+    }
+    let mut state = SCROLL.lock().unwrap();
+    state.ensure_probed();
+    if state.wheel.is_some() {
+        state.scroll_hi_res(axis, detents)
+    } else {
+        state.scroll_coarse(input, axis, detents)
+    }
+}
+
+// Stops any running momentum; called whenever a new touch arrives
+pub fn cancel_momentum() {
+    MOMENTUM_GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+// Starts a kinetic scroll from a fling velocity (detents per second, signed like `scroll`)
+pub fn start_momentum(axis: Axis, velocity: f32) {
+    let generation = MOMENTUM_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let mut velocity = velocity.clamp(-MOMENTUM_MAX_VELOCITY, MOMENTUM_MAX_VELOCITY);
+    if !velocity.is_finite() || velocity.abs() < MOMENTUM_MIN_VELOCITY {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let dt = MOMENTUM_TICK.as_secs_f32();
+        let decay = (-dt / MOMENTUM_TIME_CONSTANT).exp();
+        let mut interval = tokio::time::interval(MOMENTUM_TICK);
+        interval.tick().await;
+
+        while velocity.abs() >= MOMENTUM_MIN_VELOCITY {
+            interval.tick().await;
+            if MOMENTUM_GENERATION.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            // The input lock may be held by a long /batch; wait for it off the runtime
+            let step = velocity * dt;
+            match tokio::task::spawn_blocking(move || scroll(axis, step)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    info!("📜 Momentum scroll stopped: {}", e);
+                    return;
+                }
+                Err(_) => return,
+            }
+            velocity *= decay;
+        }
+    });
+}
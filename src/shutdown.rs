@@ -0,0 +1,65 @@
+// Coordinated shutdown, triggered by SIGINT/SIGTERM or POST /shutdown from the desktop
+//
+// Everything long-lived waits on `requested()`: the HTTP server stops accepting and
+// drains in-flight requests, event streams send a goodbye, screen streams end.
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+lazy_static::lazy_static! {
+    // The reason, once shutdown has been requested
+    static ref SHUTDOWN: watch::Sender<Option<String>> = watch::channel(None).0;
+}
+
+pub fn request(reason: impl Into<String>) {
+    let reason = reason.into();
+    SHUTDOWN.send_if_modified(|state| {
+        if state.is_some() {
+            return false;
+        }
+        info!("👋 Shutting down: {}", reason);
+        *state = Some(reason);
+        true
+    });
+}
+
+pub fn is_requested() -> bool {
+    SHUTDOWN.borrow().is_some()
+}
+
+// Resolves with the reason once shutdown is requested
+pub async fn requested() -> String {
+    let mut state = SHUTDOWN.subscribe();
+    // Cloned out so the watch::Ref (a lock guard) is gone before any other await
+    let reason = state.wait_for(Option::is_some).await.ok().map(|reason| reason.clone());
+    match reason {
+        Some(reason) => reason.unwrap_or_default(),
+        // The sender lives in a static and is never dropped
+        None => std::future::pending().await,
+    }
+}
+
+// The first signal starts a graceful shutdown; a second one exits right away
+pub async fn listen_for_signals() {
+    let mut terminate = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+        Ok(terminate) => terminate,
+        Err(e) => {
+            warn!("👋 Cannot listen for SIGTERM: {}", e);
+            return;
+        }
+    };
+    let mut signals = 0;
+    loop {
+        let name = tokio::select! {
+            _ = tokio::signal::ctrl_c() => "SIGINT",
+            _ = terminate.recv() => "SIGTERM",
+        };
+        signals += 1;
+        if signals == 1 {
+            request(format!("{} received", name));
+        } else {
+            warn!("👋 Second {}, exiting without waiting", name);
+            crate::input::release_all("forced exit");
+            std::process::exit(130);
+        }
+    }
+}
@@ -0,0 +1,504 @@
+// Low-latency UDP pointer channel
+//
+// Sessions are negotiated over HTTP (POST /udp/handshake with a paired device's
+// bearer token). Each datagram is authenticated with HMAC-SHA256 using the
+// session key, truncated to 16 bytes:
+//
+//   0      u8   version (1)
+//   1      u8   kind (1 = pointer, 2 = scroll, 3 = ping, 4 = pong)
+//   2..6   u32  session id (big endian)
+//   6..10  u32  sequence number (big endian, wrapping)
+//   10..   payload
+//   last   16-byte MAC over everything before it
+//
+// Pointer and scroll payloads carry the client's cumulative totals since the
+// session started (two i32: x and y, scroll in 1/120 detent units) rather
+// than deltas. Lost packets are harmless and stale or reordered ones are
+// dropped: the newest total always wins and the server applies the difference.
+use hmac::{Hmac, Mac};
+use schemars::JsonSchema;
+use serde::Serialize;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tracing::{info, warn};
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const DEFAULT_PORT: u16 = 44829;
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 10;
+const MAC_LEN: usize = 16;
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+const KIND_POINTER: u8 = 1;
+const KIND_SCROLL: u8 = 2;
+const KIND_PING: u8 = 3;
+const KIND_PONG: u8 = 4;
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct HandshakeResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub port: Option<u16>,
+    pub session_id: Option<u32>,
+    pub key: Option<String>,
+}
+
+struct Session {
+    key: Vec<u8>,
+    device_id: String,
+    // For latency samples
+    device_name: String,
+    last_seen: Instant,
+    // Last accepted sequence number per state kind (pointer, scroll)
+    last_seq: [Option<u32>; 2],
+    // Cumulative totals already applied
+    pointer: (i32, i32),
+    scroll: (i32, i32),
+}
+
+#[derive(Clone)]
+pub struct UdpChannel {
+    port: u16,
+    sessions: Arc<Mutex<HashMap<u32, Session>>>,
+}
+
+impl UdpChannel {
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn session_count(&self) -> usize {
+        let sessions = self.sessions.lock().unwrap();
+        sessions.values().filter(|s| s.last_seen.elapsed() < SESSION_IDLE_TIMEOUT).count()
+    }
+
+    // Creates a session for a paired device and returns (session id, hex key)
+    pub fn open_session(&self, device_id: &str, device_name: &str) -> (u32, String) {
+        let key_hex = crate::pairing::random_hex(32);
+        let key = decode_hex(&key_hex).expect("random_hex produces valid hex");
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, s| s.last_seen.elapsed() < SESSION_IDLE_TIMEOUT);
+
+        let mut session_id = rand::random::<u32>();
+        while session_id == 0 || sessions.contains_key(&session_id) {
+            session_id = rand::random::<u32>();
+        }
+        sessions.insert(
+            session_id,
+            Session {
+                key,
+                device_id: device_id.to_string(),
+                device_name: device_name.to_string(),
+                last_seen: Instant::now(),
+                last_seq: [None; 2],
+                pointer: (0, 0),
+                scroll: (0, 0),
+            },
+        );
+        (session_id, key_hex)
+    }
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn mac_for(key: &[u8], data: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac
+}
+
+pub fn encode_packet(key: &[u8], kind: u8, session_id: u32, seq: u32, payload: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(HEADER_LEN + payload.len() + MAC_LEN);
+    packet.push(VERSION);
+    packet.push(kind);
+    packet.extend_from_slice(&session_id.to_be_bytes());
+    packet.extend_from_slice(&seq.to_be_bytes());
+    packet.extend_from_slice(payload);
+    let tag = mac_for(key, &packet).finalize().into_bytes();
+    packet.extend_from_slice(&tag[..MAC_LEN]);
+    packet
+}
+
+fn read_i32_pair(payload: &[u8]) -> Option<(i32, i32)> {
+    if payload.len() != 8 {
+        return None;
+    }
+    let x = i32::from_be_bytes(payload[0..4].try_into().ok()?);
+    let y = i32::from_be_bytes(payload[4..8].try_into().ok()?);
+    Some((x, y))
+}
+
+// Serial number comparison so that sequence numbers may wrap around
+fn is_newer(seq: u32, last: Option<u32>) -> bool {
+    match last {
+        None => true,
+        Some(last) => (seq.wrapping_sub(last) as i32) > 0,
+    }
+}
+
+enum Action {
+    // Also names the device, for its latency samples
+    Pointer(i32, i32, String),
+    Scroll(i32, i32, String),
+    Reply(Vec<u8>),
+    // The session of this device timed out; release what the device holds
+    Expired(String),
+}
+
+// What to do with an authenticated pointer or scroll packet
+enum Gate {
+    Apply,
+    // Paused or missing the scope: the movement is skipped for good
+    Discard,
+    // Rate-limited: the movement is folded into the next accepted packet
+    Defer,
+}
+
+fn gate(device_id: &str) -> Gate {
+    // Scopes may have been narrowed since the handshake
+    if crate::killswitch::is_paused() || !crate::scopes::device_has(device_id, crate::scopes::Scope::Pointer) {
+        return Gate::Discard;
+    }
+    let client = crate::ratelimit::Client::device(device_id);
+    match crate::ratelimit::check(&client, crate::ratelimit::RouteClass::Pointer, 1) {
+        Ok(()) => Gate::Apply,
+        Err(_) => Gate::Defer,
+    }
+}
+
+impl UdpChannel {
+    // Authenticates a datagram and updates session state; None means "drop it"
+    fn process(&self, packet: &[u8], gate: impl Fn(&str) -> Gate) -> Option<Action> {
+        if packet.len() < HEADER_LEN + MAC_LEN || packet[0] != VERSION {
+            return None;
+        }
+        let kind = packet[1];
+        let session_id = u32::from_be_bytes(packet[2..6].try_into().ok()?);
+        let seq = u32::from_be_bytes(packet[6..10].try_into().ok()?);
+        let (body, tag) = packet.split_at(packet.len() - MAC_LEN);
+        let payload = &body[HEADER_LEN..];
+
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(&session_id)?;
+        // Authenticate first, so a forged packet cannot end someone else's session
+        mac_for(&session.key, body).verify_truncated_left(tag).ok()?;
+        if session.last_seen.elapsed() >= SESSION_IDLE_TIMEOUT {
+            info!("📡 UDP session {} of device {} expired", session_id, session.device_id);
+            let session = sessions.remove(&session_id)?;
+            return Some(Action::Expired(session.device_id));
+        }
+        session.last_seen = Instant::now();
+
+        match kind {
+            KIND_POINTER | KIND_SCROLL => {
+                let slot = (kind - KIND_POINTER) as usize;
+                if !is_newer(seq, session.last_seq[slot]) {
+                    return None;
+                }
+                let (x, y) = read_i32_pair(payload)?;
+                session.last_seq[slot] = Some(seq);
+                let applied = if kind == KIND_POINTER { &mut session.pointer } else { &mut session.scroll };
+                match gate(&session.device_id) {
+                    Gate::Apply => {}
+                    // Take the totals as applied, so resuming does not replay the movement as a jump
+                    Gate::Discard => {
+                        *applied = (x, y);
+                        return None;
+                    }
+                    // Totals are absolute: keeping the old ones makes the next packet catch up
+                    Gate::Defer => return None,
+                }
+                let (dx, dy) = (x.wrapping_sub(applied.0), y.wrapping_sub(applied.1));
+                *applied = (x, y);
+                if kind == KIND_POINTER {
+                    Some(Action::Pointer(dx, dy, session.device_name.clone()))
+                } else {
+                    Some(Action::Scroll(dx, dy, session.device_name.clone()))
+                }
+            }
+            KIND_PING => Some(Action::Reply(encode_packet(&session.key, KIND_PONG, session_id, seq, payload))),
+            _ => None,
+        }
+    }
+}
+
+// Blocking: waits for the input lock. `received` is when the datagram was read,
+// for the latency samples
+fn apply(action: Action, received: Instant) {
+    match action {
+        Action::Pointer(dx, dy, device_name) if dx != 0 || dy != 0 => {
+            crate::scroll::cancel_momentum();
+            let (_, injection) =
+                crate::metrics::injection("udp_pointer", || crate::move_mouse(&mut crate::input::lock(), dx, dy));
+            crate::latency::record_udp(&device_name, received, injection);
+        }
+        Action::Scroll(dx, dy, device_name) if dx != 0 || dy != 0 => {
+            // Scroll totals are in 1/120 detent units, y positive = up, x positive = right
+            crate::scroll::cancel_momentum();
+            let (_, injection) = crate::metrics::injection("udp_scroll", || {
+                if dy != 0 {
+                    let _ = crate::scroll::scroll(crate::scroll::Axis::Vertical, dy as f32 / 120.0);
+                }
+                if dx != 0 {
+                    let _ = crate::scroll::scroll(crate::scroll::Axis::Horizontal, dx as f32 / 120.0);
+                }
+            });
+            crate::latency::record_udp(&device_name, received, injection);
+        }
+        Action::Expired(device_id) => {
+            let client = crate::ratelimit::Client::device(&device_id);
+            crate::input::release_client(client.key(), "UDP session expired");
+        }
+        _ => {}
+    }
+}
+
+// Binds the UDP listener and spawns its receive loop, which hands input to a
+// blocking worker so waiting for the input lock never stalls the runtime
+pub async fn start(port: u16) -> std::io::Result<UdpChannel> {
+    let socket = Arc::new(UdpSocket::bind(SocketAddr::from(([0, 0, 0, 0], port))).await?);
+    let channel = UdpChannel {
+        port: socket.local_addr()?.port(),
+        sessions: Arc::new(Mutex::new(HashMap::new())),
+    };
+    info!("📡 UDP pointer channel listening on port {}", channel.port);
+
+    let (actions, mut pending) = tokio::sync::mpsc::unbounded_channel::<(Action, Instant)>();
+    tokio::task::spawn_blocking(move || {
+        while let Some((action, received)) = pending.blocking_recv() {
+            apply(action, received);
+        }
+    });
+
+    let receiver = channel.clone();
+    tokio::spawn(async move {
+        let mut buf = [0u8; 512];
+        loop {
+            let (len, from) = match socket.recv_from(&mut buf).await {
+                Ok(received) => received,
+                Err(e) => {
+                    warn!("📡 UDP receive failed: {}", e);
+                    continue;
+                }
+            };
+            let received = Instant::now();
+            match receiver.process(&buf[..len], gate) {
+                Some(Action::Reply(reply)) => {
+                    let _ = socket.send_to(&reply, from).await;
+                }
+                Some(action) => {
+                    let _ = actions.send((action, received));
+                }
+                None => {}
+            }
+        }
+    });
+
+    Ok(channel)
+}
+
+// Measures round trips over loopback with ping packets on a throwaway session
+pub async fn loopback_latency(channel: &UdpChannel, samples: u32) -> std::io::Result<Vec<Duration>> {
+    let (session_id, key_hex) = channel.open_session("loopback-test", "loopback test");
+    let key = decode_hex(&key_hex).expect("random_hex produces valid hex");
+    let socket = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await?;
+    socket.connect(SocketAddr::from(([127, 0, 0, 1], channel.port))).await?;
+
+    let mut rtts = Vec::with_capacity(samples as usize);
+    let mut buf = [0u8; 512];
+    for seq in 0..samples {
+        let sent = Instant::now();
+        let ping = encode_packet(&key, KIND_PING, session_id, seq, &[0u8; 8]);
+        socket.send(&ping).await?;
+        match tokio::time::timeout(Duration::from_secs(1), socket.recv(&mut buf)).await {
+            Ok(Ok(len)) if len > 1 && buf[1] == KIND_PONG => rtts.push(sent.elapsed()),
+            Ok(Err(e)) => return Err(e),
+            _ => {}
+        }
+    }
+
+    channel.sessions.lock().unwrap().remove(&session_id);
+    Ok(rtts)
+}
+
+// Logs a short loopback latency check so misconfiguration shows up at startup
+pub async fn log_loopback_latency(channel: &UdpChannel) {
+    match loopback_latency(channel, 20).await {
+        Ok(mut rtts) if !rtts.is_empty() => {
+            rtts.sort();
+            info!(
+                "📡 UDP loopback latency: median {:?}, max {:?} ({} samples)",
+                rtts[rtts.len() / 2],
+                rtts[rtts.len() - 1],
+                rtts.len()
+            );
+        }
+        Ok(_) => warn!("📡 UDP loopback latency test got no replies"),
+        Err(e) => warn!("📡 UDP loopback latency test failed: {}", e),
+    }
+}
+
+// The running channel, set once at startup when ASTRA_UDP is enabled
+static CHANNEL: OnceLock<UdpChannel> = OnceLock::new();
+
+pub fn channel() -> Option<&'static UdpChannel> {
+    CHANNEL.get()
+}
+
+pub fn install(channel: UdpChannel) {
+    let _ = CHANNEL.set(channel);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel() -> UdpChannel {
+        UdpChannel {
+            port: 0,
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn session(channel: &UdpChannel) -> (u32, Vec<u8>) {
+        let (session_id, key_hex) = channel.open_session("test-device", "Test device");
+        (session_id, decode_hex(&key_hex).unwrap())
+    }
+
+    fn totals(x: i32, y: i32) -> Vec<u8> {
+        [x.to_be_bytes(), y.to_be_bytes()].concat()
+    }
+
+    fn apply_all(_: &str) -> Gate {
+        Gate::Apply
+    }
+
+    #[tokio::test]
+    async fn loopback_pings_get_answered() {
+        let channel = start(0).await.unwrap();
+        let rtts = loopback_latency(&channel, 5).await.unwrap();
+        assert_eq!(rtts.len(), 5);
+        // The throwaway session is gone afterwards
+        assert_eq!(channel.session_count(), 0);
+    }
+
+    #[test]
+    fn bad_mac_is_dropped() {
+        let channel = channel();
+        let (session_id, key) = session(&channel);
+        let mut packet = encode_packet(&key, KIND_POINTER, session_id, 1, &totals(5, 5));
+        *packet.last_mut().unwrap() ^= 1;
+        assert!(channel.process(&packet, apply_all).is_none());
+
+        let wrong_key = vec![0u8; key.len()];
+        let packet = encode_packet(&wrong_key, KIND_POINTER, session_id, 1, &totals(5, 5));
+        assert!(channel.process(&packet, apply_all).is_none());
+
+        // The session is untouched, so the genuine packet still applies in full
+        let packet = encode_packet(&key, KIND_POINTER, session_id, 1, &totals(5, 5));
+        assert!(matches!(channel.process(&packet, apply_all), Some(Action::Pointer(5, 5, _))));
+    }
+
+    #[test]
+    fn only_authenticated_packets_expire_a_session() {
+        let channel = channel();
+        let (session_id, key) = session(&channel);
+        let idle = Instant::now().checked_sub(SESSION_IDLE_TIMEOUT).unwrap();
+        channel.sessions.lock().unwrap().get_mut(&session_id).unwrap().last_seen = idle;
+
+        let forged = encode_packet(&[0u8; 32], KIND_POINTER, session_id, 1, &totals(1, 1));
+        assert!(channel.process(&forged, apply_all).is_none());
+        assert!(channel.sessions.lock().unwrap().contains_key(&session_id));
+
+        let packet = encode_packet(&key, KIND_POINTER, session_id, 1, &totals(1, 1));
+        assert!(matches!(channel.process(&packet, apply_all), Some(Action::Expired(_))));
+        assert!(channel.sessions.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn unknown_session_is_dropped() {
+        let channel = channel();
+        let (session_id, key) = session(&channel);
+        let packet = encode_packet(&key, KIND_POINTER, session_id.wrapping_add(1), 1, &totals(1, 1));
+        assert!(channel.process(&packet, apply_all).is_none());
+    }
+
+    #[test]
+    fn replayed_and_stale_packets_are_dropped() {
+        let channel = channel();
+        let (session_id, key) = session(&channel);
+        let first = encode_packet(&key, KIND_POINTER, session_id, 10, &totals(10, 0));
+        assert!(matches!(channel.process(&first, apply_all), Some(Action::Pointer(10, 0, _))));
+        assert!(channel.process(&first, apply_all).is_none());
+
+        let older = encode_packet(&key, KIND_POINTER, session_id, 9, &totals(3, 0));
+        assert!(channel.process(&older, apply_all).is_none());
+
+        // Pointer and scroll are sequenced separately
+        let scroll = encode_packet(&key, KIND_SCROLL, session_id, 1, &totals(0, 120));
+        assert!(matches!(channel.process(&scroll, apply_all), Some(Action::Scroll(0, 120, _))));
+
+        let newer = encode_packet(&key, KIND_POINTER, session_id, 11, &totals(15, -4));
+        assert!(matches!(channel.process(&newer, apply_all), Some(Action::Pointer(5, -4, _))));
+    }
+
+    #[test]
+    fn sequence_numbers_wrap_around() {
+        assert!(is_newer(0, None));
+        assert!(is_newer(0, Some(u32::MAX)));
+        assert!(is_newer(5, Some(u32::MAX - 5)));
+        assert!(!is_newer(u32::MAX, Some(0)));
+        assert!(!is_newer(7, Some(7)));
+
+        let channel = channel();
+        let (session_id, key) = session(&channel);
+        let last = encode_packet(&key, KIND_POINTER, session_id, u32::MAX, &totals(1, 1));
+        assert!(channel.process(&last, apply_all).is_some());
+        let wrapped = encode_packet(&key, KIND_POINTER, session_id, 0, &totals(2, 2));
+        assert!(matches!(channel.process(&wrapped, apply_all), Some(Action::Pointer(1, 1, _))));
+    }
+
+    #[test]
+    fn movement_dropped_while_paused_is_not_replayed() {
+        let channel = channel();
+        let (session_id, key) = session(&channel);
+        let paused = encode_packet(&key, KIND_POINTER, session_id, 1, &totals(300, 200));
+        assert!(channel.process(&paused, |_| Gate::Discard).is_none());
+        let resumed = encode_packet(&key, KIND_POINTER, session_id, 2, &totals(310, 200));
+        assert!(matches!(channel.process(&resumed, apply_all), Some(Action::Pointer(10, 0, _))));
+    }
+
+    #[test]
+    fn rate_limited_movement_is_caught_up() {
+        let channel = channel();
+        let (session_id, key) = session(&channel);
+        let limited = encode_packet(&key, KIND_SCROLL, session_id, 1, &totals(0, 240));
+        assert!(channel.process(&limited, |_| Gate::Defer).is_none());
+        let next = encode_packet(&key, KIND_SCROLL, session_id, 2, &totals(0, 360));
+        assert!(matches!(channel.process(&next, apply_all), Some(Action::Scroll(0, 360, _))));
+    }
+
+    #[test]
+    fn ping_gets_an_authenticated_pong() {
+        let channel = channel();
+        let (session_id, key) = session(&channel);
+        let ping = encode_packet(&key, KIND_PING, session_id, 3, &[7u8; 8]);
+        let Some(Action::Reply(pong)) = channel.process(&ping, apply_all) else {
+            panic!("ping was not answered");
+        };
+        assert_eq!(pong, encode_packet(&key, KIND_PONG, session_id, 3, &[7u8; 8]));
+    }
+}
@@ -0,0 +1,448 @@
+// Chunked, resumable file uploads from a paired device into the download directory
+//
+//   POST /upload                  {"filename", "size", "sha256"?} -> upload id
+//   PUT  /upload/:id?offset=N     raw chunk bytes, must start at the current offset
+//   GET  /upload/:id              current offset, to resume after a dropped connection
+//
+// Data goes to a hidden .part file and is renamed to a collision-free name once
+// the last byte arrives and the optional SHA-256 matches.
+use crate::error::ApiError;
+use crate::events::{self, ServerEvent};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+pub const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+// Allow some slack over CHUNK_SIZE for clients that pick their own chunk size
+pub const MAX_CHUNK_BYTES: usize = 2 * CHUNK_SIZE;
+const DEFAULT_MAX_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+// Unfinished uploads at once, and the bytes they may announce together
+const MAX_UPLOADS: usize = 16;
+const DEFAULT_MAX_TOTAL_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UploadInit {
+    pub filename: String,
+    pub size: u64,
+    // Hex SHA-256 of the whole file, verified on completion
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ChunkQuery {
+    pub offset: u64,
+}
+
+#[derive(Debug, Serialize, Default, JsonSchema)]
+pub struct UploadResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub upload_id: Option<String>,
+    pub offset: Option<u64>,
+    pub size: Option<u64>,
+    pub chunk_size: Option<usize>,
+    // Final file name once complete
+    pub saved_as: Option<String>,
+}
+
+struct Upload {
+    device_id: String,
+    filename: String,
+    size: u64,
+    sha256: Option<String>,
+    part_path: PathBuf,
+    offset: u64,
+    last_activity: Instant,
+    // Last progress quarter reported to the GUI log
+    reported_quarter: u64,
+}
+
+lazy_static::lazy_static! {
+    static ref UPLOADS: Mutex<HashMap<String, Upload>> = Mutex::new(HashMap::new());
+}
+
+// Unfinished uploads are written out on shutdown so clients can resume after a restart
+#[derive(Serialize, Deserialize)]
+struct SavedUpload {
+    upload_id: String,
+    device_id: String,
+    filename: String,
+    size: u64,
+    sha256: Option<String>,
+    part_path: PathBuf,
+}
+
+fn state_path() -> PathBuf {
+    crate::config::config_dir().join("uploads.json")
+}
+
+// Blocking: returns how many uploads were saved
+pub fn save_state() -> Result<usize, String> {
+    let uploads = UPLOADS.lock().unwrap();
+    let saved: Vec<SavedUpload> = uploads
+        .iter()
+        .map(|(id, u)| SavedUpload {
+            upload_id: id.clone(),
+            device_id: u.device_id.clone(),
+            filename: u.filename.clone(),
+            size: u.size,
+            sha256: u.sha256.clone(),
+            part_path: u.part_path.clone(),
+        })
+        .collect();
+    let path = state_path();
+    if saved.is_empty() {
+        let _ = std::fs::remove_file(&path);
+        return Ok(0);
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(&saved).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(saved.len())
+}
+
+// Picks up uploads saved by the last shutdown; the offset is whatever reached the disk
+pub fn load_state() {
+    let path = state_path();
+    let Ok(text) = std::fs::read_to_string(&path) else {
+        return;
+    };
+    let _ = std::fs::remove_file(&path);
+    let saved: Vec<SavedUpload> = match serde_json::from_str(&text) {
+        Ok(saved) => saved,
+        Err(e) => {
+            warn!("📁 Ignoring unreadable {}: {}", path.display(), e);
+            return;
+        }
+    };
+
+    let mut uploads = UPLOADS.lock().unwrap();
+    for saved in saved {
+        let offset = match std::fs::metadata(&saved.part_path) {
+            Ok(meta) if saved.size > 0 && meta.len() <= saved.size => meta.len(),
+            _ => continue,
+        };
+        uploads.insert(
+            saved.upload_id,
+            Upload {
+                device_id: saved.device_id,
+                filename: saved.filename,
+                size: saved.size,
+                sha256: saved.sha256,
+                part_path: saved.part_path,
+                offset,
+                last_activity: Instant::now(),
+                reported_quarter: offset * 4 / saved.size,
+            },
+        );
+    }
+    if !uploads.is_empty() {
+        info!("📁 Restored {} unfinished uploads", uploads.len());
+    }
+}
+
+// Forgets uploads abandoned long ago, along with their partial data
+fn sweep_stale() {
+    let mut uploads = UPLOADS.lock().unwrap();
+    uploads.retain(|id, u| {
+        let keep = u.last_activity.elapsed() < STALE_AFTER;
+        if !keep {
+            info!("📁 Dropping upload {} of '{}', idle for a day", id, u.filename);
+            let _ = std::fs::remove_file(&u.part_path);
+        }
+        keep
+    });
+}
+
+pub fn spawn_sweeper() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            let _ = tokio::task::spawn_blocking(sweep_stale).await;
+        }
+    });
+}
+
+pub fn max_bytes() -> u64 {
+    crate::config::env_parse("ASTRA_UPLOAD_MAX_BYTES", DEFAULT_MAX_BYTES)
+}
+
+fn max_total_bytes() -> u64 {
+    crate::config::env_parse("ASTRA_UPLOAD_MAX_TOTAL_BYTES", DEFAULT_MAX_TOTAL_BYTES)
+}
+
+// ASTRA_DOWNLOAD_DIR, then $XDG_DOWNLOAD_DIR/Astra, then ~/Downloads/Astra
+pub fn download_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("ASTRA_DOWNLOAD_DIR") {
+        return PathBuf::from(dir);
+    }
+    let base = std::env::var_os("XDG_DOWNLOAD_DIR")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Downloads")))
+        .unwrap_or_else(|| PathBuf::from("."));
+    base.join("Astra")
+}
+
+// Keeps only the final path component and drops characters that are unsafe in file names
+fn sanitize_filename(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or("");
+    let cleaned: String = base
+        .chars()
+        .filter(|c| !c.is_control() && !matches!(c, '<' | '>' | ':' | '"' | '|' | '?' | '*'))
+        .collect();
+    let cleaned = cleaned.trim().trim_start_matches('.').to_string();
+    if cleaned.is_empty() {
+        "upload".to_string()
+    } else {
+        cleaned.chars().take(200).collect()
+    }
+}
+
+// Creates "photo.jpg", or "photo (1).jpg", "photo (2).jpg", ... if taken, and returns
+// its path. Creating it claims the name, so the rename over it cannot replace a file
+// that appeared in the meantime.
+fn create_unique(dir: &Path, filename: &str) -> std::io::Result<PathBuf> {
+    let (stem, ext) = match filename.rfind('.') {
+        Some(i) if i > 0 => (&filename[..i], &filename[i..]),
+        _ => (filename, ""),
+    };
+    for n in 0.. {
+        let candidate = match n {
+            0 => dir.join(filename),
+            n => dir.join(format!("{} ({}){}", stem, n, ext)),
+        };
+        match std::fs::OpenOptions::new().write(true).create_new(true).open(&candidate) {
+            Ok(_) => return Ok(candidate),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!("an unused file name exists")
+}
+
+fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+pub fn start(device: &crate::pairing::Device, init: UploadInit) -> Result<UploadResponse, ApiError> {
+    if init.size > max_bytes() {
+        return Err(ApiError::InvalidRequest(format!("File is {} bytes, the limit is {}", init.size, max_bytes())));
+    }
+    if let Some(sum) = &init.sha256 {
+        if sum.len() != 64 || !sum.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(ApiError::InvalidRequest("sha256 must be 64 hex characters".to_string()));
+        }
+    }
+
+    // Checked and inserted under one lock, so parallel starts cannot overshoot the caps
+    let mut uploads = UPLOADS.lock().unwrap();
+    if uploads.len() >= MAX_UPLOADS {
+        return Err(ApiError::Conflict(format!(
+            "{} uploads are already in progress, finish one first",
+            uploads.len()
+        )));
+    }
+    let pending: u64 = uploads.values().map(|u| u.size - u.offset).sum();
+    if pending + init.size > max_total_bytes() {
+        return Err(ApiError::PayloadTooLarge(format!(
+            "Unfinished uploads already need {} bytes, the limit is {}",
+            pending,
+            max_total_bytes()
+        )));
+    }
+
+    let dir = download_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| ApiError::Internal(format!("Failed to create {}: {}", dir.display(), e)))?;
+    let upload_id = crate::pairing::random_hex(12);
+    let part_path = dir.join(format!(".astra-{}.part", upload_id));
+    std::fs::File::create(&part_path).map_err(|e| ApiError::Internal(format!("Failed to create upload file: {}", e)))?;
+
+    let filename = sanitize_filename(&init.filename);
+    info!("📁 Upload {} of '{}' ({} bytes) started by '{}'", upload_id, filename, init.size, device.name);
+    events::gui_log(format!("📁 Receiving '{}' from {}", filename, device.name));
+
+    uploads.insert(
+        upload_id.clone(),
+        Upload {
+            device_id: device.id.clone(),
+            filename,
+            size: init.size,
+            sha256: init.sha256.map(|s| s.to_lowercase()),
+            part_path,
+            offset: 0,
+            last_activity: Instant::now(),
+            reported_quarter: 0,
+        },
+    );
+    drop(uploads);
+
+    // An empty file is complete as soon as it is announced
+    if init.size == 0 {
+        return finish(&upload_id);
+    }
+    Ok(UploadResponse {
+        status: "success".to_string(),
+        upload_id: Some(upload_id),
+        offset: Some(0),
+        size: Some(init.size),
+        chunk_size: Some(CHUNK_SIZE),
+        ..Default::default()
+    })
+}
+
+pub fn status(device: &crate::pairing::Device, upload_id: &str) -> Result<UploadResponse, ApiError> {
+    let uploads = UPLOADS.lock().unwrap();
+    let upload = uploads
+        .get(upload_id)
+        .filter(|u| u.device_id == device.id)
+        .ok_or_else(unknown_upload)?;
+    Ok(UploadResponse {
+        status: "success".to_string(),
+        upload_id: Some(upload_id.to_string()),
+        offset: Some(upload.offset),
+        size: Some(upload.size),
+        chunk_size: Some(CHUNK_SIZE),
+        ..Default::default()
+    })
+}
+
+// Also for ids of another device, so they cannot be probed
+fn unknown_upload() -> ApiError {
+    ApiError::NotFound("Unknown upload id".to_string())
+}
+
+// Blocking: writes a chunk at the offset and completes the upload when it was the last one
+pub fn write_chunk(
+    device: &crate::pairing::Device,
+    upload_id: &str,
+    offset: u64,
+    data: &[u8],
+) -> Result<UploadResponse, ApiError> {
+    let mut uploads = UPLOADS.lock().unwrap();
+    let upload = uploads
+        .get_mut(upload_id)
+        .filter(|u| u.device_id == device.id)
+        .ok_or_else(unknown_upload)?;
+
+    if offset != upload.offset {
+        return Err(ApiError::Conflict(format!("Expected offset {}, got {}", upload.offset, offset)));
+    }
+    if offset + data.len() as u64 > upload.size {
+        return Err(ApiError::InvalidRequest(format!(
+            "Chunk runs past the announced size of {} bytes",
+            upload.size
+        )));
+    }
+
+    // Whatever a failed earlier write left past the offset is cut off, so a retried
+    // chunk lands where the client thinks it does
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .open(&upload.part_path)
+        .map_err(|e| ApiError::Internal(format!("Failed to open upload file: {}", e)))?;
+    file.set_len(offset)
+        .and_then(|_| file.seek(SeekFrom::Start(offset)))
+        .and_then(|_| file.write_all(data))
+        .map_err(|e| ApiError::Internal(format!("Failed to write upload data: {}", e)))?;
+    upload.offset += data.len() as u64;
+    upload.last_activity = Instant::now();
+
+    // An empty file only gets here when a failed save is retried
+    let quarter = (upload.offset * 4).checked_div(upload.size).unwrap_or(4);
+    if quarter > upload.reported_quarter && upload.offset < upload.size {
+        upload.reported_quarter = quarter;
+        events::gui_log(format!("📁 '{}' {}%", upload.filename, quarter * 25));
+    }
+    events::emit(ServerEvent::UploadProgress {
+        upload_id: upload_id.to_string(),
+        filename: upload.filename.clone(),
+        offset: upload.offset,
+        size: upload.size,
+    });
+
+    let (offset, size) = (upload.offset, upload.size);
+    drop(uploads);
+    if offset == size {
+        return finish(upload_id);
+    }
+    Ok(UploadResponse {
+        status: "success".to_string(),
+        upload_id: Some(upload_id.to_string()),
+        offset: Some(offset),
+        size: Some(size),
+        chunk_size: Some(CHUNK_SIZE),
+        ..Default::default()
+    })
+}
+
+fn finish(upload_id: &str) -> Result<UploadResponse, ApiError> {
+    let upload = UPLOADS
+        .lock()
+        .unwrap()
+        .remove(upload_id)
+        .ok_or_else(unknown_upload)?;
+
+    if let Some(expected) = &upload.sha256 {
+        let actual = sha256_file(&upload.part_path).map_err(|e| ApiError::Internal(format!("Failed to hash upload: {}", e)))?;
+        if &actual != expected {
+            let _ = std::fs::remove_file(&upload.part_path);
+            events::gui_log(format!("❌ '{}' failed its checksum and was discarded", upload.filename));
+            return Err(ApiError::Conflict(format!("Checksum mismatch: expected {}, got {}", expected, actual)));
+        }
+    }
+
+    let dir = upload.part_path.parent().map(Path::to_path_buf).unwrap_or_else(download_dir);
+    let saved = create_unique(&dir, &upload.filename).and_then(|target| match std::fs::rename(&upload.part_path, &target) {
+        Ok(()) => Ok(target),
+        Err(e) => {
+            let _ = std::fs::remove_file(&target);
+            Err(e)
+        }
+    });
+    let target = match saved {
+        Ok(target) => target,
+        Err(e) => {
+            // Keep the upload so the client can retry with an empty chunk at the end
+            warn!("📁 Failed to save upload {}: {}", upload_id, e);
+            UPLOADS.lock().unwrap().insert(upload_id.to_string(), upload);
+            return Err(ApiError::Internal(format!("Failed to save upload: {}", e)));
+        }
+    };
+    let saved_as = target
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    info!("📁 Upload {} saved to {}", upload_id, target.display());
+    events::gui_log(format!("✅ Saved '{}' ({} bytes)", saved_as, upload.size));
+    Ok(UploadResponse {
+        status: "success".to_string(),
+        message: Some(format!("Saved to {}", target.display())),
+        upload_id: Some(upload_id.to_string()),
+        offset: Some(upload.size),
+        size: Some(upload.size),
+        saved_as: Some(saved_as),
+        ..Default::default()
+    })
+}
@@ -0,0 +1,325 @@
+// System volume: default sink level, mute and output selection
+//
+// The real backend drives PulseAudio (or PipeWire through pipewire-pulse) with
+// `pactl`, which must be installed at runtime (pulseaudio-utils);
+// ASTRA_VOLUME_BACKEND=mock swaps in an in-memory backend for tests and machines
+// without audio.
+use crate::error::ApiError;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::process::Command;
+use std::sync::Mutex;
+
+// Upper bound for absolute levels, matching the usual desktop "over-amplification" limit
+pub const MAX_PERCENT: u32 = 150;
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Sink {
+    pub name: String,
+    pub description: Option<String>,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VolumeStatus {
+    pub volume: u32,
+    pub muted: bool,
+    pub sink: String,
+}
+
+#[derive(Debug)]
+pub enum VolumeError {
+    // The request names a malformed or unknown output
+    InvalidSink(String),
+    // pactl is missing or the sound server failed
+    Backend(String),
+}
+
+impl fmt::Display for VolumeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VolumeError::InvalidSink(message) | VolumeError::Backend(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<VolumeError> for ApiError {
+    fn from(e: VolumeError) -> Self {
+        match e {
+            VolumeError::InvalidSink(message) => ApiError::InvalidRequest(message),
+            VolumeError::Backend(message) => ApiError::BackendUnavailable(message),
+        }
+    }
+}
+
+pub trait VolumeBackend: Send + Sync {
+    fn status(&self) -> Result<VolumeStatus, VolumeError>;
+    fn set_volume(&self, percent: u32) -> Result<(), VolumeError>;
+    fn set_muted(&self, muted: bool) -> Result<(), VolumeError>;
+    fn sinks(&self) -> Result<Vec<Sink>, VolumeError>;
+    fn set_default_sink(&self, name: &str) -> Result<(), VolumeError>;
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct VolumeRequest {
+    // Absolute level in percent
+    pub volume: Option<u32>,
+    // Relative change in percent
+    pub delta: Option<i32>,
+    pub muted: Option<bool>,
+    // Output to make the default
+    pub sink: Option<String>,
+}
+
+#[derive(Debug, Serialize, Default, JsonSchema)]
+pub struct VolumeResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub volume: Option<u32>,
+    pub muted: Option<bool>,
+    pub sink: Option<String>,
+    pub sinks: Vec<Sink>,
+}
+
+lazy_static::lazy_static! {
+    static ref BACKEND: Box<dyn VolumeBackend> = match std::env::var("ASTRA_VOLUME_BACKEND").as_deref() {
+        Ok("mock") => Box::new(MockVolume::default()),
+        _ => Box::new(PactlVolume),
+    };
+}
+
+pub fn backend() -> &'static dyn VolumeBackend {
+    BACKEND.as_ref()
+}
+
+// Applies a request in order: output, then level, then mute
+pub fn apply(backend: &dyn VolumeBackend, request: &VolumeRequest) -> Result<VolumeStatus, VolumeError> {
+    if let Some(sink) = &request.sink {
+        // pactl would take it for an option
+        if sink.starts_with('-') {
+            return Err(VolumeError::InvalidSink(format!("Invalid sink name: {}", sink)));
+        }
+        if !backend.sinks()?.iter().any(|s| &s.name == sink) {
+            return Err(VolumeError::InvalidSink(format!("Unknown sink: {}", sink)));
+        }
+        backend.set_default_sink(sink)?;
+    }
+    if let Some(volume) = request.volume {
+        backend.set_volume(volume.min(MAX_PERCENT))?;
+    } else if let Some(delta) = request.delta {
+        let current = backend.status()?.volume as i32;
+        backend.set_volume(current.saturating_add(delta).clamp(0, MAX_PERCENT as i32) as u32)?;
+    }
+    if let Some(muted) = request.muted {
+        backend.set_muted(muted)?;
+    }
+    backend.status()
+}
+
+pub struct PactlVolume;
+
+fn pactl(args: &[&str]) -> Result<String, VolumeError> {
+    let output = Command::new("pactl")
+        .args(args)
+        .output()
+        .map_err(|e| VolumeError::Backend(format!("Failed to run pactl (is pulseaudio-utils installed?): {}", e)))?;
+    if !output.status.success() {
+        return Err(VolumeError::Backend(format!(
+            "pactl {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Average of the "NN%" channel levels in `pactl get-sink-volume` output, so an
+// unbalanced sink reports what the user hears overall
+fn parse_percent(output: &str) -> Option<u32> {
+    let levels: Vec<u32> = output
+        .split_whitespace()
+        .filter_map(|word| word.strip_suffix('%').and_then(|n| n.parse().ok()))
+        .collect();
+    let total: u32 = levels.iter().sum();
+    (!levels.is_empty()).then(|| (total as f32 / levels.len() as f32).round() as u32)
+}
+
+impl VolumeBackend for PactlVolume {
+    fn status(&self) -> Result<VolumeStatus, VolumeError> {
+        let volume = pactl(&["get-sink-volume", "@DEFAULT_SINK@"])?;
+        let mute = pactl(&["get-sink-mute", "@DEFAULT_SINK@"])?;
+        Ok(VolumeStatus {
+            volume: parse_percent(&volume)
+                .ok_or_else(|| VolumeError::Backend("Could not parse the sink volume".to_string()))?,
+            muted: mute.trim().ends_with("yes"),
+            sink: pactl(&["get-default-sink"])?.trim().to_string(),
+        })
+    }
+
+    fn set_volume(&self, percent: u32) -> Result<(), VolumeError> {
+        pactl(&["set-sink-volume", "@DEFAULT_SINK@", &format!("{}%", percent)]).map(|_| ())
+    }
+
+    fn set_muted(&self, muted: bool) -> Result<(), VolumeError> {
+        pactl(&["set-sink-mute", "@DEFAULT_SINK@", if muted { "1" } else { "0" }]).map(|_| ())
+    }
+
+    fn sinks(&self) -> Result<Vec<Sink>, VolumeError> {
+        let default = pactl(&["get-default-sink"])?.trim().to_string();
+        // JSON output carries descriptions; older pactl only has the short listing
+        if let Ok(json) = pactl(&["--format=json", "list", "sinks"]) {
+            if let Ok(serde_json::Value::Array(entries)) = serde_json::from_str(&json) {
+                return Ok(entries
+                    .iter()
+                    .filter_map(|entry| {
+                        let name = entry.get("name")?.as_str()?.to_string();
+                        Some(Sink {
+                            is_default: name == default,
+                            description: entry.get("description").and_then(|d| d.as_str()).map(str::to_string),
+                            name,
+                        })
+                    })
+                    .collect());
+            }
+        }
+        Ok(pactl(&["list", "short", "sinks"])?
+            .lines()
+            .filter_map(|line| line.split('\t').nth(1))
+            .map(|name| Sink {
+                is_default: name == default,
+                description: None,
+                name: name.to_string(),
+            })
+            .collect())
+    }
+
+    fn set_default_sink(&self, name: &str) -> Result<(), VolumeError> {
+        pactl(&["set-default-sink", name]).map(|_| ())
+    }
+}
+
+// In-memory backend with two fake outputs
+pub struct MockVolume {
+    state: Mutex<(u32, bool, String)>,
+}
+
+impl Default for MockVolume {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new((50, false, "mock.speakers".to_string())),
+        }
+    }
+}
+
+impl VolumeBackend for MockVolume {
+    fn status(&self) -> Result<VolumeStatus, VolumeError> {
+        let (volume, muted, sink) = self.state.lock().unwrap().clone();
+        Ok(VolumeStatus { volume, muted, sink })
+    }
+
+    fn set_volume(&self, percent: u32) -> Result<(), VolumeError> {
+        self.state.lock().unwrap().0 = percent;
+        Ok(())
+    }
+
+    fn set_muted(&self, muted: bool) -> Result<(), VolumeError> {
+        self.state.lock().unwrap().1 = muted;
+        Ok(())
+    }
+
+    fn sinks(&self) -> Result<Vec<Sink>, VolumeError> {
+        let default = self.state.lock().unwrap().2.clone();
+        Ok(["mock.speakers", "mock.headphones"]
+            .iter()
+            .map(|name| Sink {
+                name: name.to_string(),
+                description: None,
+                is_default: *name == default,
+            })
+            .collect())
+    }
+
+    fn set_default_sink(&self, name: &str) -> Result<(), VolumeError> {
+        if !self.sinks()?.iter().any(|s| s.name == name) {
+            return Err(VolumeError::InvalidSink(format!("Unknown sink: {}", name)));
+        }
+        self.state.lock().unwrap().2 = name.to_string();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> VolumeRequest {
+        VolumeRequest {
+            volume: None,
+            delta: None,
+            muted: None,
+            sink: None,
+        }
+    }
+
+    #[test]
+    fn absolute_levels_are_capped() {
+        let mock = MockVolume::default();
+        let status = apply(&mock, &VolumeRequest { volume: Some(80), ..request() }).unwrap();
+        assert_eq!(status.volume, 80);
+        let status = apply(&mock, &VolumeRequest { volume: Some(500), ..request() }).unwrap();
+        assert_eq!(status.volume, MAX_PERCENT);
+    }
+
+    #[test]
+    fn relative_changes_clamp_and_saturate() {
+        let mock = MockVolume::default();
+        assert_eq!(apply(&mock, &VolumeRequest { delta: Some(-10), ..request() }).unwrap().volume, 40);
+        assert_eq!(apply(&mock, &VolumeRequest { delta: Some(-100), ..request() }).unwrap().volume, 0);
+        assert_eq!(apply(&mock, &VolumeRequest { delta: Some(i32::MAX), ..request() }).unwrap().volume, MAX_PERCENT);
+        assert_eq!(apply(&mock, &VolumeRequest { delta: Some(i32::MIN), ..request() }).unwrap().volume, 0);
+    }
+
+    #[test]
+    fn mute_and_output_switch() {
+        let mock = MockVolume::default();
+        let status = apply(
+            &mock,
+            &VolumeRequest {
+                muted: Some(true),
+                sink: Some("mock.headphones".to_string()),
+                ..request()
+            },
+        )
+        .unwrap();
+        assert!(status.muted);
+        assert_eq!(status.sink, "mock.headphones");
+        let sinks = mock.sinks().unwrap();
+        assert!(sinks.iter().any(|s| s.name == "mock.headphones" && s.is_default));
+    }
+
+    #[test]
+    fn bad_sinks_change_nothing() {
+        let mock = MockVolume::default();
+        for sink in ["mock.nowhere", "--help", "-0"] {
+            let bad = VolumeRequest {
+                volume: Some(90),
+                sink: Some(sink.to_string()),
+                ..request()
+            };
+            let error = apply(&mock, &bad).unwrap_err();
+            assert!(matches!(error, VolumeError::InvalidSink(_)), "{} gave {}", sink, error);
+        }
+        let status = mock.status().unwrap();
+        assert_eq!((status.volume, status.sink.as_str()), (50, "mock.speakers"));
+    }
+
+    #[test]
+    fn parses_pactl_volume() {
+        let output = "Volume: front-left: 42597 /  65% / -11.23 dB,   front-right: 42597 /  65% / -11.23 dB";
+        assert_eq!(parse_percent(output), Some(65));
+        let unbalanced = "Volume: front-left: 42597 /  65% / -11.23 dB,   front-right: 36044 /  55% / -15.58 dB";
+        assert_eq!(parse_percent(unbalanced), Some(60));
+        assert_eq!(parse_percent("garbage"), None);
+    }
+}
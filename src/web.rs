@@ -0,0 +1,22 @@
+// Built-in mobile web client, compiled into the binary so that scanning the
+// GUI's QR code opens a working remote in any phone browser
+use axum::{http::header, response::IntoResponse, routing::get, Router};
+
+const INDEX_HTML: &str = include_str!("../assets/web/index.html");
+const APP_JS: &str = include_str!("../assets/web/app.js");
+const STYLE_CSS: &str = include_str!("../assets/web/style.css");
+
+// no-cache so phones pick up a new client right after the server is upgraded
+fn asset(content_type: &'static str, body: &'static str) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, content_type), (header::CACHE_CONTROL, "no-cache")],
+        body,
+    )
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/", get(|| async { asset("text/html; charset=utf-8", INDEX_HTML) }))
+        .route("/app.js", get(|| async { asset("text/javascript; charset=utf-8", APP_JS) }))
+        .route("/style.css", get(|| async { asset("text/css; charset=utf-8", STYLE_CSS) }))
+}
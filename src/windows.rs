@@ -0,0 +1,425 @@
+// Window management: list, focus, minimize, maximize, close and move to a workspace
+//
+// X11 window managers are driven through EWMH; sway and Hyprland through their
+// IPC tools (swaymsg, hyprctl). Other Wayland compositors expose no public API
+// for this, so the endpoint reports them as unsupported.
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{AtomEnum, ClientMessageEvent, ConnectionExt as _, EventMask, Window};
+use x11rb::rust_connection::RustConnection;
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct WindowInfo {
+    pub id: String,
+    pub title: String,
+    pub app_id: Option<String>,
+    pub workspace: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub focused: bool,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowAction {
+    Focus,
+    Minimize,
+    Maximize,
+    Close,
+    MoveToWorkspace,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct WindowRequest {
+    pub id: String,
+    pub action: WindowAction,
+    // Target for move_to_workspace: a number on X11/Hyprland, any name on sway
+    pub workspace: Option<String>,
+}
+
+#[derive(Debug, Serialize, Default, JsonSchema)]
+pub struct WindowsResponse {
+    pub status: String,
+    pub message: Option<String>,
+    pub backend: Option<String>,
+    pub windows: Vec<WindowInfo>,
+}
+
+pub trait WindowBackend {
+    fn name(&self) -> &'static str;
+    fn list(&self) -> Result<Vec<WindowInfo>, String>;
+    // Rejects requests the backend cannot express, before anything runs
+    fn validate(&self, request: &WindowRequest) -> Result<(), String> {
+        if request.action == WindowAction::MoveToWorkspace {
+            valid_workspace(required_workspace(request)?)?;
+        }
+        Ok(())
+    }
+    fn act(&self, request: &WindowRequest) -> Result<(), String>;
+}
+
+// Picks the backend for the current session
+pub fn backend() -> Result<Box<dyn WindowBackend>, String> {
+    if std::env::var_os("SWAYSOCK").is_some() {
+        return Ok(Box::new(Sway));
+    }
+    if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+        return Ok(Box::new(Hyprland));
+    }
+    if std::env::var_os("WAYLAND_DISPLAY").is_none() && std::env::var_os("DISPLAY").is_some() {
+        return Ok(Box::new(Ewmh::connect()?));
+    }
+    Err("Window management is not supported in this session (needs X11, sway or Hyprland)".to_string())
+}
+
+// Finds a window whose title or app id contains `name`, preferring app id matches
+pub fn find_by_name<'a>(windows: &'a [WindowInfo], name: &str) -> Option<&'a WindowInfo> {
+    let name = name.to_lowercase();
+    windows
+        .iter()
+        .find(|w| w.app_id.as_deref().is_some_and(|a| a.to_lowercase().contains(&name)))
+        .or_else(|| windows.iter().find(|w| w.title.to_lowercase().contains(&name)))
+}
+
+fn required_workspace(request: &WindowRequest) -> Result<&str, String> {
+    request
+        .workspace
+        .as_deref()
+        .ok_or_else(|| "move_to_workspace needs a workspace".to_string())
+}
+
+// Workspace names end up inside swaymsg and hyprctl commands, so only plain names pass
+fn valid_workspace(name: &str) -> Result<&str, String> {
+    let plain = |c: char| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.' | ':');
+    if name.trim().is_empty() || name.chars().count() > 64 || !name.chars().all(plain) {
+        return Err(format!(
+            "Invalid workspace name {:?} (letters, digits, spaces and - _ . : only)",
+            name
+        ));
+    }
+    Ok(name)
+}
+
+fn run(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// --- X11 / EWMH ---
+
+pub struct Ewmh {
+    conn: RustConnection,
+    root: Window,
+}
+
+// _NET_WM_STATE actions and source indication ("pager", i.e. a user request)
+const NET_WM_STATE_ADD: u32 = 1;
+const SOURCE_PAGER: u32 = 2;
+const ICONIC_STATE: u32 = 3;
+
+impl Ewmh {
+    fn connect() -> Result<Self, String> {
+        let (conn, screen_num) = x11rb::connect(None).map_err(|e| format!("X11 connection failed: {}", e))?;
+        let root = conn.setup().roots[screen_num].root;
+        Ok(Self { conn, root })
+    }
+
+    fn atom(&self, name: &str) -> Result<u32, String> {
+        Ok(self
+            .conn
+            .intern_atom(false, name.as_bytes())
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())?
+            .atom)
+    }
+
+    fn property32(&self, window: Window, name: &str) -> Vec<u32> {
+        let Ok(atom) = self.atom(name) else { return Vec::new() };
+        self.conn
+            .get_property(false, window, atom, AtomEnum::ANY, 0, 1024)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .and_then(|reply| reply.value32().map(|values| values.collect()))
+            .unwrap_or_default()
+    }
+
+    fn property_string(&self, window: Window, name: &str) -> Option<String> {
+        let atom = self.atom(name).ok()?;
+        let reply = self
+            .conn
+            .get_property(false, window, atom, AtomEnum::ANY, 0, 1024)
+            .ok()?
+            .reply()
+            .ok()?;
+        (!reply.value.is_empty()).then(|| String::from_utf8_lossy(&reply.value).into_owned())
+    }
+
+    fn send_client_message(&self, window: Window, message: &str, data: [u32; 5]) -> Result<(), String> {
+        let event = ClientMessageEvent::new(32, window, self.atom(message)?, data);
+        self.conn
+            .send_event(
+                false,
+                self.root,
+                EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                event,
+            )
+            .map_err(|e| e.to_string())?;
+        self.conn.flush().map_err(|e| e.to_string())
+    }
+
+    fn window_info(&self, window: Window, active: Option<Window>) -> Option<WindowInfo> {
+        let title = self
+            .property_string(window, "_NET_WM_NAME")
+            .or_else(|| self.property_string(window, "WM_NAME"))
+            .unwrap_or_default();
+        // WM_CLASS is "instance\0class\0"; the class is the app id
+        let app_id = self
+            .property_string(window, "WM_CLASS")
+            .and_then(|class| class.split('\0').rfind(|s| !s.is_empty()).map(str::to_string));
+        let workspace = self
+            .property32(window, "_NET_WM_DESKTOP")
+            .first()
+            .map(|desktop| desktop.to_string());
+
+        let geometry = self.conn.get_geometry(window).ok()?.reply().ok()?;
+        let origin = self
+            .conn
+            .translate_coordinates(window, self.root, 0, 0)
+            .ok()?
+            .reply()
+            .ok()?;
+        Some(WindowInfo {
+            id: format!("0x{:x}", window),
+            title,
+            app_id,
+            workspace,
+            x: origin.dst_x as i32,
+            y: origin.dst_y as i32,
+            width: geometry.width as u32,
+            height: geometry.height as u32,
+            focused: Some(window) == active,
+        })
+    }
+}
+
+fn parse_x11_id(id: &str) -> Result<Window, String> {
+    let hex = id.trim_start_matches("0x");
+    Window::from_str_radix(hex, 16).map_err(|_| format!("Invalid window id: {}", id))
+}
+
+impl WindowBackend for Ewmh {
+    fn name(&self) -> &'static str {
+        "x11"
+    }
+
+    fn validate(&self, request: &WindowRequest) -> Result<(), String> {
+        if request.action == WindowAction::MoveToWorkspace {
+            required_workspace(request)?
+                .parse::<u32>()
+                .map_err(|_| "X11 workspaces are numbered from 0".to_string())?;
+        }
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<WindowInfo>, String> {
+        let clients = self.property32(self.root, "_NET_CLIENT_LIST");
+        if clients.is_empty() {
+            return Err("The window manager does not publish _NET_CLIENT_LIST".to_string());
+        }
+        let active = self.property32(self.root, "_NET_ACTIVE_WINDOW").first().copied();
+        Ok(clients.into_iter().filter_map(|w| self.window_info(w, active)).collect())
+    }
+
+    fn act(&self, request: &WindowRequest) -> Result<(), String> {
+        let window = parse_x11_id(&request.id)?;
+        match request.action {
+            WindowAction::Focus => self.send_client_message(window, "_NET_ACTIVE_WINDOW", [SOURCE_PAGER, 0, 0, 0, 0]),
+            WindowAction::Close => self.send_client_message(window, "_NET_CLOSE_WINDOW", [0, SOURCE_PAGER, 0, 0, 0]),
+            WindowAction::Minimize => self.send_client_message(window, "WM_CHANGE_STATE", [ICONIC_STATE, 0, 0, 0, 0]),
+            WindowAction::Maximize => {
+                let vert = self.atom("_NET_WM_STATE_MAXIMIZED_VERT")?;
+                let horz = self.atom("_NET_WM_STATE_MAXIMIZED_HORZ")?;
+                self.send_client_message(window, "_NET_WM_STATE", [NET_WM_STATE_ADD, vert, horz, SOURCE_PAGER, 0])
+            }
+            WindowAction::MoveToWorkspace => {
+                let desktop: u32 = required_workspace(request)?
+                    .parse()
+                    .map_err(|_| "X11 workspaces are numbered from 0".to_string())?;
+                self.send_client_message(window, "_NET_WM_DESKTOP", [desktop, SOURCE_PAGER, 0, 0, 0])
+            }
+        }
+    }
+}
+
+// --- sway ---
+
+pub struct Sway;
+
+fn collect_sway(node: &serde_json::Value, workspace: Option<&str>, out: &mut Vec<WindowInfo>) {
+    let workspace = if node["type"] == "workspace" {
+        node["name"].as_str()
+    } else {
+        workspace
+    };
+    let is_window = node["type"] == "con" || node["type"] == "floating_con";
+    if is_window && node["pid"].is_number() {
+        let rect = &node["rect"];
+        out.push(WindowInfo {
+            id: node["id"].to_string(),
+            title: node["name"].as_str().unwrap_or_default().to_string(),
+            app_id: node["app_id"]
+                .as_str()
+                .or_else(|| node["window_properties"]["class"].as_str())
+                .map(str::to_string),
+            workspace: workspace.map(str::to_string),
+            x: rect["x"].as_i64().unwrap_or(0) as i32,
+            y: rect["y"].as_i64().unwrap_or(0) as i32,
+            width: rect["width"].as_u64().unwrap_or(0) as u32,
+            height: rect["height"].as_u64().unwrap_or(0) as u32,
+            focused: node["focused"].as_bool().unwrap_or(false),
+        });
+    }
+    for key in ["nodes", "floating_nodes"] {
+        if let Some(children) = node[key].as_array() {
+            for child in children {
+                collect_sway(child, workspace, out);
+            }
+        }
+    }
+}
+
+impl WindowBackend for Sway {
+    fn name(&self) -> &'static str {
+        "sway"
+    }
+
+    fn list(&self) -> Result<Vec<WindowInfo>, String> {
+        let tree: serde_json::Value =
+            serde_json::from_str(&run("swaymsg", &["-r", "-t", "get_tree"])?).map_err(|e| e.to_string())?;
+        let mut windows = Vec::new();
+        collect_sway(&tree, None, &mut windows);
+        Ok(windows)
+    }
+
+    fn act(&self, request: &WindowRequest) -> Result<(), String> {
+        let id: u64 = request.id.parse().map_err(|_| format!("Invalid window id: {}", request.id))?;
+        let command = match request.action {
+            WindowAction::Focus => "focus".to_string(),
+            WindowAction::Close => "kill".to_string(),
+            WindowAction::Minimize => "move scratchpad".to_string(),
+            WindowAction::Maximize => "fullscreen enable".to_string(),
+            WindowAction::MoveToWorkspace => {
+                format!("move container to workspace \"{}\"", valid_workspace(required_workspace(request)?)?)
+            }
+        };
+        run("swaymsg", &[&format!("[con_id={}] {}", id, command)]).map(|_| ())
+    }
+}
+
+// --- Hyprland ---
+
+pub struct Hyprland;
+
+impl WindowBackend for Hyprland {
+    fn name(&self) -> &'static str {
+        "hyprland"
+    }
+
+    fn list(&self) -> Result<Vec<WindowInfo>, String> {
+        let clients: serde_json::Value =
+            serde_json::from_str(&run("hyprctl", &["clients", "-j"])?).map_err(|e| e.to_string())?;
+        let active: serde_json::Value = serde_json::from_str(&run("hyprctl", &["activewindow", "-j"])?).unwrap_or_default();
+        let active_address = active["address"].as_str().unwrap_or_default().to_string();
+
+        Ok(clients
+            .as_array()
+            .map(|clients| {
+                clients
+                    .iter()
+                    .map(|c| WindowInfo {
+                        id: c["address"].as_str().unwrap_or_default().to_string(),
+                        title: c["title"].as_str().unwrap_or_default().to_string(),
+                        app_id: c["class"].as_str().map(str::to_string),
+                        workspace: c["workspace"]["name"].as_str().map(str::to_string),
+                        x: c["at"][0].as_i64().unwrap_or(0) as i32,
+                        y: c["at"][1].as_i64().unwrap_or(0) as i32,
+                        width: c["size"][0].as_u64().unwrap_or(0) as u32,
+                        height: c["size"][1].as_u64().unwrap_or(0) as u32,
+                        focused: c["address"].as_str() == Some(active_address.as_str()),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn act(&self, request: &WindowRequest) -> Result<(), String> {
+        if !request.id.starts_with("0x") || !request.id[2..].chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Invalid window id: {}", request.id));
+        }
+        let target = format!("address:{}", request.id);
+        let dispatch = |args: &[&str]| run("hyprctl", &[&["dispatch"], args].concat()).map(|_| ());
+        match request.action {
+            WindowAction::Focus => dispatch(&["focuswindow", &target]),
+            WindowAction::Close => dispatch(&["closewindow", &target]),
+            WindowAction::Minimize => dispatch(&["movetoworkspacesilent", &format!("special,{}", target)]),
+            WindowAction::Maximize => {
+                dispatch(&["focuswindow", &target])?;
+                dispatch(&["fullscreen", "1"])
+            }
+            WindowAction::MoveToWorkspace => {
+                let workspace = valid_workspace(required_workspace(request)?)?;
+                dispatch(&["movetoworkspacesilent", &format!("{},{}", workspace, target)])
+            }
+        }
+    }
+}
+
+// Resolves a window by name (or the focused one when `name` is None) and applies
+// `action` to it; used by voice commands. Returns a reply for the user.
+pub fn act_by_name(name: Option<&str>, action: WindowAction, workspace: Option<String>) -> Result<String, String> {
+    let backend = backend()?;
+    let windows = backend.list()?;
+    let window = match name {
+        Some(name) => find_by_name(&windows, name).ok_or_else(|| format!("No window matches '{}'", name))?,
+        None => windows
+            .iter()
+            .find(|w| w.focused)
+            .ok_or_else(|| "No window is focused".to_string())?,
+    };
+    let request = WindowRequest {
+        id: window.id.clone(),
+        action,
+        workspace,
+    };
+    backend.validate(&request)?;
+    backend.act(&request)?;
+
+    let label = window.app_id.clone().unwrap_or_else(|| window.title.clone());
+    Ok(match action {
+        WindowAction::Focus => format!("Switched to {}", label),
+        WindowAction::Minimize => format!("Minimized {}", label),
+        WindowAction::Maximize => format!("Maximized {}", label),
+        WindowAction::Close => format!("Closed {}", label),
+        WindowAction::MoveToWorkspace => format!(
+            "Moved {} to workspace {}",
+            label,
+            request.workspace.unwrap_or_default()
+        ),
+    })
+}
@@ -0,0 +1,275 @@
+// End-to-end tests: the real server binary, driven through astra-client
+//
+// Each test starts its own server on a free port with the recording input backend
+// and the mock volume backend, so nothing reaches the desktop. Config (paired
+// devices, audit log) goes to a throwaway XDG_CONFIG_HOME.
+use astra_client::types::*;
+use astra_client::{Client, Error};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const PAIRING_CODE: &str = "424242";
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(20);
+
+struct Server {
+    child: Child,
+    port: u16,
+    config_dir: PathBuf,
+}
+
+impl Server {
+    async fn start() -> Self {
+        // Let the OS pick a port, then hand it to the server
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("free port")
+            .port();
+        let config_dir = std::env::temp_dir().join(format!("astra-client-test-{}-{}", std::process::id(), port));
+        std::fs::create_dir_all(&config_dir).expect("config dir");
+        let child = Command::new(env!("CARGO_BIN_EXE_astra-remote"))
+            .env("ASTRA_PORT", port.to_string())
+            .env("ASTRA_PORT_FALLBACK", "0")
+            .env("ASTRA_INPUT_BACKEND", "recording")
+            .env("ASTRA_VOLUME_BACKEND", "mock")
+            .env("ASTRA_PAIRING_CODE", PAIRING_CODE)
+            .env("ASTRA_MDNS", "off")
+            .env("ASTRA_UDP", "off")
+            .env("XDG_CONFIG_HOME", &config_dir)
+            .env("ASTRA_DOWNLOAD_DIR", config_dir.join("downloads"))
+            .env_remove("ASTRA_REQUIRE_PAIRING")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("start astra-remote");
+        let server = Self { child, port, config_dir };
+
+        let client = server.client();
+        let deadline = Instant::now() + STARTUP_TIMEOUT;
+        while client.ping().await.is_err() {
+            assert!(Instant::now() < deadline, "server did not answer /ping in time");
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        server
+    }
+
+    fn client(&self) -> Client {
+        Client::new(format!("http://127.0.0.1:{}", self.port)).with_timeout(Duration::from_secs(5))
+    }
+
+    // A raw request, for headers astra-client never sends
+    async fn raw(&self, request: &str) -> String {
+        let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", self.port)).await.expect("connect");
+        stream.write_all(request.as_bytes()).await.expect("send");
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.expect("read");
+        response
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.config_dir);
+    }
+}
+
+fn api_code(error: Error) -> (u16, Option<String>) {
+    match error {
+        Error::Api { status, code, .. } => (status, code),
+        other => panic!("expected an API error, got {}", other),
+    }
+}
+
+fn key_step(key: &str) -> BatchStep {
+    BatchStep::Key(KeyRequest {
+        key: key.to_string(),
+        modifiers: None,
+    })
+}
+
+#[tokio::test]
+async fn input_reaches_the_recording_backend() {
+    let server = Server::start().await;
+    let client = server.client();
+
+    assert_eq!(client.ping().await.unwrap().status, "ok");
+    let moved = client.mouse(3.0, -2.0).await.unwrap();
+    assert!(moved.timing.is_some());
+    client.key("enter", &[]).await.unwrap();
+    client.click("left").await.unwrap();
+
+    let input = client.input().await.unwrap();
+    assert_eq!(input.backend, "recording");
+    assert!(input.held.is_empty());
+    let recorded = input.recorded.unwrap();
+    // Latest first
+    assert_eq!(recorded[0], "button up Left");
+    assert_eq!(recorded[1], "button down Left");
+    assert!(recorded.contains(&"key down Return".to_string()));
+    assert!(recorded.contains(&"key up Return".to_string()));
+    assert!(recorded.iter().any(|event| event.starts_with("move ")));
+
+    let error = client.key("no-such-key", &[]).await.unwrap_err();
+    assert_eq!(api_code(error), (400, Some("unknown_key".to_string())));
+}
+
+#[tokio::test]
+async fn batch_runs_every_step() {
+    let server = Server::start().await;
+    let client = server.client();
+
+    let response = client
+        .batch(&BatchRequest {
+            steps: vec![
+                key_step("tab"),
+                BatchStep::Delay { ms: 10 },
+                BatchStep::Mouse(MouseMove { dx: 1.0, dy: 1.0 }),
+                key_step("escape"),
+            ],
+            stop_on_error: true,
+        })
+        .await
+        .unwrap();
+    assert_eq!(response.status, "success");
+    assert_eq!(response.results.len(), 4);
+    assert!(response.results.iter().all(|result| result.status == "success"));
+
+    let recorded = client.input().await.unwrap().recorded.unwrap();
+    assert_eq!(recorded[0], "key up Escape");
+
+    // A failed step fails the whole request, with the steps that ran in the details
+    let error = client
+        .batch(&BatchRequest {
+            steps: vec![key_step("tab"), key_step("no-such-key"), key_step("escape")],
+            stop_on_error: true,
+        })
+        .await
+        .unwrap_err();
+    let Error::Api { status, code, details, .. } = error else {
+        panic!("expected an API error, got {}", error);
+    };
+    assert_eq!((status, code.as_deref()), (422, Some("batch_failed")));
+    let results = details.unwrap()["results"].as_array().cloned().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[1]["code"], "unknown_key");
+}
+
+#[tokio::test]
+async fn volume_uses_the_mock_backend() {
+    let server = Server::start().await;
+    let client = server.client();
+
+    let before = client.volume().await.unwrap();
+    assert!(!before.sinks.is_empty());
+    let after = client
+        .set_volume(&VolumeRequest {
+            volume: Some(30),
+            delta: None,
+            muted: Some(true),
+            sink: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(after.volume, Some(30));
+    assert_eq!(after.muted, Some(true));
+    assert_eq!(client.volume().await.unwrap().volume, Some(30));
+
+    let error = client
+        .set_volume(&VolumeRequest {
+            volume: None,
+            delta: None,
+            muted: None,
+            sink: Some("mock.nowhere".to_string()),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(api_code(error), (400, Some("invalid_request".to_string())));
+}
+
+#[tokio::test]
+async fn pause_blocks_input_until_resumed() {
+    let server = Server::start().await;
+    let client = server.client();
+
+    client.pause().await.unwrap();
+    let error = client.mouse(1.0, 1.0).await.unwrap_err();
+    assert_eq!(api_code(error), (503, Some("input_paused".to_string())));
+    let error = client.batch(&BatchRequest { steps: vec![key_step("tab")], stop_on_error: false }).await.unwrap_err();
+    assert_eq!(api_code(error), (503, Some("input_paused".to_string())));
+    // Reading state and releasing input stay available
+    client.input().await.unwrap();
+    client.reset_input().await.unwrap();
+
+    client.resume().await.unwrap();
+    client.mouse(1.0, 1.0).await.unwrap();
+}
+
+#[tokio::test]
+async fn paired_devices_can_be_managed() {
+    let server = Server::start().await;
+    let client = server.client();
+
+    let error = client.pair("000000", Some("phone")).await.unwrap_err();
+    assert_eq!(api_code(error).0, 401);
+    let paired = client.pair(PAIRING_CODE, Some("phone")).await.unwrap();
+    let device_id = paired.device_id.unwrap();
+    let device = server.client().with_token(paired.token.unwrap());
+    device.ping().await.unwrap();
+
+    let devices = client.devices().await.unwrap();
+    assert!(!devices.pairing_required);
+    assert_eq!(devices.devices.len(), 1);
+    assert_eq!(devices.devices[0].name, "phone");
+
+    let renamed = client
+        .update_device(
+            &device_id,
+            &DeviceUpdate {
+                name: Some("tablet".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(renamed.devices[0].name, "tablet");
+
+    // Recorded input may contain typed text, so reading it needs the keyboard scope
+    device.input().await.unwrap();
+    client
+        .update_device(
+            &device_id,
+            &DeviceUpdate {
+                scopes: Some(vec![Scope::Pointer]),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let error = device.input().await.unwrap_err();
+    assert_eq!(api_code(error), (403, Some("missing_scope".to_string())));
+    device.mouse(1.0, 1.0).await.unwrap();
+
+    let remaining = client.delete_device(&device_id).await.unwrap();
+    assert!(remaining.devices.is_empty());
+}
+
+#[tokio::test]
+async fn openapi_and_admin_routes() {
+    let server = Server::start().await;
+    let client = server.client();
+
+    let document = client.openapi().await.unwrap();
+    for path in ["/ping", "/mouse", "/batch", "/pause", "/devices"] {
+        assert!(document["paths"][path].is_object(), "{} missing from /openapi.json", path);
+    }
+
+    // A web page must not be able to drive admin routes through the browser
+    let response = server
+        .raw("POST /pause HTTP/1.1\r\nHost: 127.0.0.1\r\nOrigin: http://example.com\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+        .await;
+    assert!(response.starts_with("HTTP/1.1 403"), "unexpected response: {}", response);
+    client.mouse(1.0, 0.0).await.unwrap();
+}